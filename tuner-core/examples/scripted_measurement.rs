@@ -0,0 +1,75 @@
+//! # Scripted Measurement
+//!
+//! Runs a full measurement session without the GUI or a microphone: a few
+//! synthesized piano notes go through the same analysis, frame tracking and
+//! stability-gated `CaptureSession` as the application, and each capture is
+//! stored in a profile. A starting point for automated checks of the
+//! measurement pipeline.
+//!
+//! Run with `cargo run -p tuner-core --example scripted_measurement`.
+
+use std::time::{Duration, Instant};
+use tuner_core::{
+    analysis::{self, AnalysisConfig},
+    audio::{self, DEFAULT_SAMPLE_RATE},
+    capture_processing::{CaptureConfig, ProcessingOperation},
+    capture_session::CaptureSession,
+    demo,
+    inharmonicity::{InharmonicityProfile, PianoType},
+    tracking::FrameTracker,
+    tuning::{self, OctaveConvention},
+};
+
+/// Keys measured, from the bass into the treble.
+const KEYS: [u8; 5] = [15, 27, 39, 48, 60];
+
+/// Samples between consecutive frames (half-overlapping frames).
+const HOP_SIZE: usize = audio::BUFFER_SIZE / 2;
+
+fn main() {
+    let sample_rate = DEFAULT_SAMPLE_RATE;
+    let config = AnalysisConfig { max_partials: 10, ..AnalysisConfig::default() };
+    let mut session = CaptureSession::new(CaptureConfig::default());
+    let mut profile = InharmonicityProfile::default();
+
+    // Frames are analyzed far faster than real time, so each gets the time it would have had live
+    let hop_duration = Duration::from_secs_f64(HOP_SIZE as f64 / sample_rate as f64);
+    let mut clock = Instant::now();
+
+    for key_index in KEYS {
+        let name = tuning::key_name(key_index, OctaveConvention::Scientific);
+        let note = demo::synthesize_note(key_index, sample_rate);
+        let mut tracker = FrameTracker::new();
+        session.start();
+
+        let mut outcome = None;
+        for frame in note.windows(audio::BUFFER_SIZE).step_by(HOP_SIZE) {
+            let mut result = analysis::analyze_frame(frame, sample_rate, &config);
+            clock += hop_duration;
+            result.timestamp = Some(clock);
+            result.hop_size = HOP_SIZE;
+            tracker.track(&mut result, hop_duration.as_secs_f32());
+            outcome = session.push(&result, ProcessingOperation::BestConfidence);
+            if outcome.is_some() {
+                break;
+            }
+        }
+
+        match outcome {
+            Some(Ok(measurement)) => {
+                let expected = PianoType::BabyGrand.preset_b(key_index);
+                let b = measurement.calculated_b.map_or("--".to_string(), |b| format!("{:.3e}", b));
+                let reliability = measurement.reliability.map_or("--".to_string(), |r| format!("{:.0}%", r * 100.0));
+                println!("{:<4} B = {:<10} (synthesized with {:.3e}, {} partials, reliability {})", name, b, expected, measurement.partials.len(), reliability);
+                profile.insert_measurement(measurement);
+            }
+            Some(Err(e)) => println!("{:<4} not stored: {}", name, e),
+            None => {
+                session.stop();
+                println!("{:<4} never became stable", name);
+            }
+        }
+    }
+
+    println!("Measured {} of {} keys", profile.measurements.len(), KEYS.len());
+}
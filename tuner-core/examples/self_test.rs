@@ -0,0 +1,83 @@
+//! # Detection Self-Test
+//!
+//! Synthesizes a sine tone at the frequency of every piano key, runs one
+//! frame of it through `analysis::analyze_frame`, and checks that the
+//! detected frequency is within tolerance and the note name matches. This
+//! is repeated at each common device sample rate, since the rate a device
+//! delivers may not be the one requested.
+//!
+//! Run with `cargo run -p tuner-core --example self_test -- [frame size]`.
+//! Notes that fail are listed with their error in cents, and the process
+//! exits with a non-zero status if any note failed.
+
+use tuner_core::{
+    analysis::{self, AnalysisConfig},
+    audio::{self, BUFFER_SIZE},
+    tuning,
+};
+
+/// Largest accepted detection error, in cents.
+const TOLERANCE_CENTS: f32 = 5.0;
+
+/// Length of each synthesized tone, in frames. The analyzed frame is the
+/// second, after the fade-in; the third leaves room for the fade-out.
+const TONE_FRAMES: usize = 3;
+
+/// Sample rates tested; input devices commonly deliver one of these.
+const SAMPLE_RATES: [u32; 2] = [44100, 48000];
+
+fn main() -> anyhow::Result<()> {
+    let frame_size = match std::env::args().nth(1) {
+        Some(size) => size.parse()?,
+        None => BUFFER_SIZE,
+    };
+    let failures: usize = SAMPLE_RATES.iter().map(|&sample_rate| test_sample_rate(sample_rate, frame_size)).sum();
+    if failures > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Checks every key at one sample rate and frame size and returns the number of failures.
+fn test_sample_rate(sample_rate: u32, frame_size: usize) -> usize {
+    let config = AnalysisConfig::default();
+    let tone_ms = (TONE_FRAMES * frame_size) as u64 * 1000 / sample_rate as u64;
+    let tone = |freq: f32| audio::synthesize_tone(freq, sample_rate, tone_ms);
+    let mut failures = 0;
+
+    for key_index in 0..88u8 {
+        let (expected_name, expected_freq) = tuning::find_nearest_note_by_index(key_index);
+        let samples = tone(expected_freq);
+        // Skip the fade-in so the frame is at full, steady amplitude
+        let frame = &samples[frame_size..2 * frame_size];
+        let result = analysis::analyze_frame(frame, sample_rate, &config);
+
+        let problem = match (result.detected_frequency, &result.note_name) {
+            (Some(freq), Some(name)) => {
+                let error = tuning::calculate_cents_deviation(freq, expected_freq);
+                if name != &expected_name {
+                    Some(format!("detected {} ({:.2} Hz, {:+.1} cents)", name, freq, error))
+                } else if error.abs() > TOLERANCE_CENTS {
+                    Some(format!("{:.2} Hz, {:+.1} cents", freq, error))
+                } else {
+                    None
+                }
+            }
+            _ => Some("no pitch detected".to_string()),
+        };
+
+        if let Some(problem) = problem {
+            failures += 1;
+            println!("FAIL {:>4} ({:>8.2} Hz) at {} Hz: {}", expected_name, expected_freq, sample_rate, problem);
+        }
+    }
+
+    println!(
+        "{} of 88 notes within {} cents at {} Hz with {}-sample frames",
+        88 - failures,
+        TOLERANCE_CENTS,
+        sample_rate,
+        frame_size
+    );
+    failures
+}
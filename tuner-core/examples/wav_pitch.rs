@@ -0,0 +1,49 @@
+//! # WAV Pitch Trace
+//!
+//! Reads a recorded note frame by frame, exactly as the live input would
+//! deliver it, and prints the pitch detected in every frame. Useful for
+//! checking changes to the detection against known recordings, without a
+//! microphone.
+//!
+//! Run with `cargo run -p tuner-core --example wav_pitch -- <note.wav> [frame size]`.
+//! Frames overlap by half, like the "50%" frame overlap setting of the application.
+
+use std::path::Path;
+use tuner_core::{
+    analysis::{self, AnalysisConfig},
+    audio,
+    tracking::FrameTracker,
+    wav,
+};
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("Usage: wav_pitch <note.wav> [frame size]");
+        std::process::exit(2);
+    };
+    let frame_size = match args.next() {
+        Some(size) => size.parse()?,
+        None => audio::BUFFER_SIZE,
+    };
+    let hop_size = frame_size / 2;
+
+    let (frames, sample_rate) = wav::read_frames(Path::new(&path), frame_size, hop_size)?;
+    let config = AnalysisConfig::default();
+    let mut tracker = FrameTracker::new();
+    let hop_seconds = hop_size as f32 / sample_rate as f32;
+
+    for (index, frame) in frames.enumerate() {
+        let mut result = analysis::analyze_frame(&frame, sample_rate, &config);
+        tracker.track(&mut result, hop_seconds);
+        let time = index as f32 * hop_seconds;
+        match (&result.note_name, result.detected_frequency, result.cents_deviation, result.confidence) {
+            (Some(name), Some(freq), Some(cents), Some(confidence)) => println!(
+                "{:6.3} s  {:<4} {:8.2} Hz {:+6.1} cents  confidence {:.2}",
+                time, name, freq, cents, confidence
+            ),
+            _ => println!("{:6.3} s  --", time),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! # WAV Folder Profile
+//!
+//! Builds an inharmonicity profile from a folder of recorded notes, without
+//! a live microphone. Every `.wav` file is named after the note it holds
+//! (e.g. `A4.wav`, `C#3 soft.wav`) and is measured with the same analysis
+//! and capture processing as the application.
+//!
+//! Run with `cargo run -p tuner-core --example wav_profile -- <folder> [profile.json] [frame size]`.
+//! The profile is written as JSON (to `profile.json` by default) in the same
+//! format the application saves and loads. Frames follow each other back to
+//! back; A0–C1 need a frame size of 4096 or more.
+
+use std::path::Path;
+use tuner_core::{
+    analysis::AnalysisConfig,
+    audio,
+    capture_processing::{CaptureConfig, ProcessingOperation},
+    tuning::{self, OctaveConvention},
+    wav,
+};
+
+/// Output file used when none is given.
+const DEFAULT_OUTPUT: &str = "profile.json";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let Some(dir) = args.next() else {
+        eprintln!("Usage: wav_profile <folder> [output.json] [frame size]");
+        std::process::exit(2);
+    };
+    let output = args.next().unwrap_or_else(|| DEFAULT_OUTPUT.to_string());
+    let frame_size = match args.next() {
+        Some(size) => size.parse()?,
+        None => audio::BUFFER_SIZE,
+    };
+
+    let (profile, failures) = wav::profile_from_dir(
+        Path::new(&dir),
+        frame_size,
+        frame_size,
+        &AnalysisConfig::default(),
+        &CaptureConfig::default(),
+        ProcessingOperation::BestConfidence,
+    )?;
+
+    for (key_index, measurement) in &profile.measurements {
+        let b = measurement.calculated_b.map_or("--".to_string(), |b| format!("{:.3e}", b));
+        println!(
+            "{:<4} B = {:<10} ({} partials)",
+            tuning::key_name(*key_index, OctaveConvention::Scientific),
+            b,
+            measurement.partials.len()
+        );
+    }
+    for (path, reason) in &failures {
+        println!("Skipped {}: {}", path.display(), reason);
+    }
+
+    profile.save(&output)?;
+    println!("Wrote {} measurements to {}", profile.measurements.len(), output);
+    Ok(())
+}
@@ -6,103 +6,590 @@
 //! 
 //! ## Features
 //! - Automatic audio device selection
+//! - Choice of input device from every available host (e.g. ALSA and JACK)
 //! - Configurable sample rates and formats
+//! - 16-bit integer inputs converted to the same f32 frames as float inputs
 //! - Real-time audio streaming with buffering
 //! - Error handling and device fallback
+//! - Reconnecting to an input whose device disappeared mid-session
+//! - Reference tone playback on the default output device
+//! - Optional two-microphone coherence filtering of reflections
+//! - Averaging of multi-channel inputs that offer no mono format
+//! - Optionally overlapping analysis frames for a finer time resolution
+//! - Selectable analysis frame size, trading time for frequency resolution
+//! - WAV recordings as a stand-in input, in the frames a stream would deliver
+//!
+//! ## Lost devices
+//! `start_audio_capture` reports every error of the running stream on its
+//! `errors` channel. A consumer that sees `cpal::StreamError::DeviceNotAvailable`
+//! there, or no frames for `INPUT_STALL_TIMEOUT` (some backends never report an
+//! unplugged device), drops the stream and calls `reopen_audio_capture` after
+//! each `reconnect_delay` until it succeeds or the attempts run out.
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::SupportedStreamConfigRange;
+use cpal::{FromSample, SizedSample, SupportedStreamConfigRange};
 use crossbeam_channel::Sender;
 use anyhow::{Result, anyhow};
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use std::fmt;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-/// Audio buffer size for processing frames.
+/// Input sample formats the capture can convert, most preferred first.
+const SAMPLE_FORMATS: [cpal::SampleFormat; 3] = [cpal::SampleFormat::F32, cpal::SampleFormat::I16, cpal::SampleFormat::U16];
+
+/// Default audio buffer size for processing frames.
 /// 
-/// This constant defines the number of samples per audio frame.
+/// This constant defines the number of samples per audio frame unless
+/// `AudioConfig::frame_size` asks for another.
 /// Larger buffers provide more frequency resolution but increase latency.
 pub const BUFFER_SIZE: usize = 2048;
 
-/// Starts audio capture from the default input device.
+/// Smallest analysis frame accepted in `AudioConfig::frame_size`.
+pub const MIN_FRAME_SIZE: usize = 1024;
+
+/// Largest analysis frame accepted in `AudioConfig::frame_size`. YIN's cost
+/// grows with the square of the frame, so 16384 samples are already slow.
+pub const MAX_FRAME_SIZE: usize = 16384;
+
+/// Sample rate requested from the input device when the caller has no preference.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/// Lowest sample rate the analysis accepts. Its Nyquist frequency of 8 kHz
+/// still covers C8 (4186 Hz) and its second partial's neighbourhood.
+pub const MIN_SAMPLE_RATE: u32 = 16_000;
+
+/// Highest sample rate the analysis accepts. Pitch detection needs two
+/// periods in a frame, so the lowest detectable fundamental is
+/// `2 * rate / frame_size`: 43 Hz at 44.1 kHz with `BUFFER_SIZE` frames, but
+/// already 94 Hz at this rate unless the frames are made longer.
+pub const MAX_SAMPLE_RATE: u32 = 96_000;
+
+/// Smallest hop between analysis frames, as a fraction of the frame (87.5%
+/// overlap). Every frame costs a full analysis, so finer hops are not worth the CPU time.
+pub const MIN_HOP_DIVISOR: usize = 8;
+
+/// Attempts made to reopen an input whose device was lost, see `reconnect_delay`.
+pub const RECONNECT_ATTEMPTS: u32 = 3;
+
+/// Wait before the first attempt to reopen a lost input; each further attempt waits twice as long.
+pub const RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Time without a single frame after which a running input counts as lost.
+pub const INPUT_STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Weight of the previous estimate when averaging the channel spectra across
+/// frames (~5 frames, about a quarter of a second at 44.1 kHz).
+const COHERENCE_SMOOTHING: f32 = 0.8;
+
+/// Settings used to open the input stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioConfig {
+    /// Name of the input device to open (None = the system default)
+    pub input_device: Option<String>,
+    /// Preferred sample rate in Hz
+    pub sample_rate: u32,
+    /// Device buffer size in frames (None = device default). Larger buffers
+    /// add latency but make dropouts (xruns) less likely on slow machines.
+    pub stream_buffer: Option<u32>,
+    /// Open the input in stereo and keep only the component the two channels
+    /// have in common (see `CoherenceFilter`). Falls back to mono if the
+    /// device has no stereo input.
+    pub stereo_coherence: bool,
+    /// Samples in each analysis frame (`BUFFER_SIZE` by default). Longer
+    /// frames resolve the partials of the lowest notes, but react more slowly.
+    /// Clamped to `MIN_FRAME_SIZE..=MAX_FRAME_SIZE`.
+    pub frame_size: usize,
+    /// Samples between the starts of consecutive analysis frames (None = the
+    /// frame size, i.e. back-to-back frames). Smaller hops make frames overlap,
+    /// so results arrive more often. Clamped to `frame_size / MIN_HOP_DIVISOR..=frame_size`.
+    pub hop_size: Option<usize>,
+}
+
+impl AudioConfig {
+    /// Returns the frame size and hop actually used, clamped into their ranges.
+    pub fn frame_and_hop(&self) -> (usize, usize) {
+        let frame_size = self.frame_size.clamp(MIN_FRAME_SIZE, MAX_FRAME_SIZE);
+        let hop_size = self.hop_size.unwrap_or(frame_size);
+        (frame_size, hop_size.clamp(frame_size / MIN_HOP_DIVISOR, frame_size))
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            input_device: None,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            stream_buffer: None,
+            stereo_coherence: false,
+            frame_size: BUFFER_SIZE,
+            hop_size: None,
+        }
+    }
+}
+
+/// Combines two microphone channels into one, keeping only what they share.
+///
+/// The direct sound of the string reaches both microphones with a fixed phase
+/// relationship, while room reflections and the diffuse field arrive at each
+/// microphone differently. For every FFT bin the magnitude-squared coherence
+/// of the two channels is estimated from their auto- and cross-spectra,
+/// averaged over recent frames, and the mid (L+R)/2 spectrum is weighted by
+/// it before transforming back. Coherent bins pass through, incoherent bins
+/// are attenuated.
+pub struct CoherenceFilter {
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    cross: Vec<Complex<f32>>, // Averaged L * conj(R)
+    power_left: Vec<f32>,     // Averaged |L|^2
+    power_right: Vec<f32>,    // Averaged |R|^2
+}
+
+impl CoherenceFilter {
+    /// Creates a filter for frames of `frame_size` samples per channel.
+    pub fn new(frame_size: usize) -> Self {
+        let mut planner = FftPlanner::new();
+        Self {
+            forward: planner.plan_fft_forward(frame_size),
+            inverse: planner.plan_fft_inverse(frame_size),
+            cross: vec![Complex::new(0.0, 0.0); frame_size],
+            power_left: vec![0.0; frame_size],
+            power_right: vec![0.0; frame_size],
+        }
+    }
+
+    /// Produces one mono frame from a frame of each channel.
+    ///
+    /// # Arguments
+    /// * `left` - Left channel samples (`frame_size` long)
+    /// * `right` - Right channel samples (`frame_size` long)
+    ///
+    /// # Returns
+    /// * `Vec<f32>` - Coherent component of the two channels
+    pub fn process(&mut self, left: &[f32], right: &[f32]) -> Vec<f32> {
+        let to_complex = |signal: &[f32]| -> Vec<Complex<f32>> {
+            signal.iter().map(|&s| Complex::new(s, 0.0)).collect()
+        };
+        let mut left_spectrum = to_complex(left);
+        let mut right_spectrum = to_complex(right);
+        self.forward.process(&mut left_spectrum);
+        self.forward.process(&mut right_spectrum);
+
+        let a = COHERENCE_SMOOTHING;
+        let mut mid: Vec<Complex<f32>> = Vec::with_capacity(left_spectrum.len());
+        for (i, (l, r)) in left_spectrum.iter().zip(&right_spectrum).enumerate() {
+            self.cross[i] = self.cross[i] * a + l * r.conj() * (1.0 - a);
+            self.power_left[i] = self.power_left[i] * a + l.norm_sqr() * (1.0 - a);
+            self.power_right[i] = self.power_right[i] * a + r.norm_sqr() * (1.0 - a);
+
+            let denominator = self.power_left[i] * self.power_right[i];
+            let coherence = if denominator > f32::EPSILON {
+                (self.cross[i].norm_sqr() / denominator).min(1.0)
+            } else {
+                0.0
+            };
+            mid.push((l + r) * 0.5 * coherence);
+        }
+
+        self.inverse.process(&mut mid);
+        let scale = 1.0 / mid.len() as f32;
+        mid.iter().map(|c| c.re * scale).collect()
+    }
+}
+
+/// How the channels of the input stream are turned into the mono analysis signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelMode {
+    /// The device delivers a single channel, used as is
+    Mono,
+    /// All channels are averaged; used when the device has no mono format
+    Average,
+    /// Two channels combined by `CoherenceFilter`
+    Coherence,
+}
+
+/// Input stream actually opened by `start_audio_capture`.
+///
+/// Lets the user confirm which device and channels the app is listening to,
+/// which may not be what was asked for (e.g. no stereo input for coherence).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputInfo {
+    /// Name of the input device
+    pub device_name: String,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Number of channels the stream delivers
+    pub channels: u16,
+    /// How those channels become the mono analysis signal
+    pub channel_mode: ChannelMode,
+    /// Samples in each frame sent for analysis
+    pub frame_size: usize,
+    /// Samples between the starts of consecutive frames sent for analysis
+    pub hop_size: usize,
+}
+
+impl fmt::Display for InputInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channels = match self.channel_mode {
+            ChannelMode::Mono => "mono".to_string(),
+            ChannelMode::Average => format!("{} channels averaged", self.channels),
+            ChannelMode::Coherence => "stereo coherence".to_string(),
+        };
+        write!(f, "{}, {}, {} Hz", self.device_name, channels, self.sample_rate)?;
+        if self.frame_size != BUFFER_SIZE {
+            write!(f, ", {}-sample frames", self.frame_size)?;
+        }
+        if self.hop_size < self.frame_size {
+            write!(f, ", {:.0}% overlap", 100.0 * (1.0 - self.hop_size as f32 / self.frame_size as f32))?;
+        }
+        Ok(())
+    }
+}
+
+/// Starts audio capture from the configured input device.
 /// 
 /// This function:
-/// 1. Selects the default audio input device
+/// 1. Selects `audio_config.input_device`, searched on every available host,
+///    or the default input device of the default host
 /// 2. Configures the audio stream for optimal piano tuning
 /// 3. Sets up a callback to stream audio data to the analysis pipeline
 /// 
 /// # Arguments
-/// * `sender` - Channel sender for streaming audio data to the analysis thread
+/// * `sender` - Channel sender for streaming audio data to the analysis thread.
+///   Frames that don't fit are dropped, so with a bounded channel a slow
+///   consumer loses frames instead of making the queue grow without limit
+/// * `errors` - Receives every error the running stream reports, e.g.
+///   `cpal::StreamError::DeviceNotAvailable` when the device is unplugged
+///   (see the module documentation for reconnecting). Errors that don't fit are dropped
+/// * `audio_config` - Preferred sample rate and device buffer size
 /// 
 /// # Returns
-/// * `Ok((stream, sample_rate))` - Audio stream handle and sample rate
+/// * `Ok((stream, info))` - Audio stream handle and the input actually in use.
+///   `info.sample_rate` may differ from the requested rate if the device does
+///   not support it, so callers must use the returned value for all analysis.
 /// * `Err(e)` - Error if audio setup fails
 /// 
 /// # Audio Configuration
-/// - Sample Rate: 44.1 kHz (CD quality)
-/// - Format: 32-bit float
-/// - Channels: Mono (1 channel)
-/// - Buffer Size: 2048 samples (~46ms at 44.1kHz)
-pub fn start_audio_capture(sender: Sender<Vec<f32>>) -> Result<(cpal::Stream, u32)> {
+/// - Sample Rate: closest supported rate to `audio_config.sample_rate`
+/// - Format: 32-bit float, or 16-bit integer converted to float
+/// - Channels: Mono (1 channel), or stereo combined by `CoherenceFilter`
+///   when `audio_config.stereo_coherence` is set. Devices without a mono
+///   format are opened with their fewest channels, averaged to mono.
+/// - Device Buffer: `audio_config.stream_buffer`, clamped to the device's range
+/// - Analysis Frame: `audio_config.frame_size` samples (2048 by default,
+///   ~46ms at 44.1kHz), one every `audio_config.hop_size` samples (back to
+///   back when unset)
+pub fn start_audio_capture(
+    sender: Sender<Vec<f32>>,
+    errors: Sender<cpal::StreamError>,
+    audio_config: &AudioConfig,
+) -> Result<(cpal::Stream, InputInfo)> {
+    let target_sample_rate = audio_config.sample_rate;
     // ... (device and config selection code is the same)
-    let host = cpal::default_host();
-    let device = host.default_input_device()
-        .ok_or_else(|| anyhow!("No input device available"))?;
+    let device = match &audio_config.input_device {
+        Some(name) => all_input_devices()
+            .find(|device| device.name().is_ok_and(|n| &n == name))
+            .ok_or_else(|| anyhow!("Input device \"{}\" not found", name))?,
+        None => cpal::default_host().default_input_device()
+            .ok_or_else(|| anyhow!("No input device available"))?,
+    };
 
-    println!("Using audio input device: {}", device.name()?);
+    let device_name = device.name()?;
+    println!("Using audio input device: {}", device_name);
 
     let configs = device.supported_input_configs()?.collect::<Vec<_>>();
-    let supported_config = find_supported_config(configs, 44100)
-        .ok_or_else(|| anyhow!("No suitable f32 input format found"))?;
+    let stereo_config = if audio_config.stereo_coherence {
+        let config = find_supported_config(configs.clone(), target_sample_rate, 2);
+        if config.is_none() {
+            println!("No stereo input available, falling back to mono");
+        }
+        config
+    } else {
+        None
+    };
+    let stereo = stereo_config.is_some();
+    let supported_config = stereo_config
+        .or_else(|| find_supported_config(configs.clone(), target_sample_rate, 1))
+        .or_else(|| {
+            // Multi-channel interfaces often have no mono format at all
+            let max_channels = configs.iter().map(|c| c.channels()).max().unwrap_or(0);
+            (2..=max_channels).find_map(|channels| find_supported_config(configs.clone(), target_sample_rate, channels))
+        })
+        .ok_or_else(|| anyhow!("No suitable input format found (need f32, i16 or u16 samples)"))?;
+    let sample_format = supported_config.sample_format();
+    let channels = supported_config.channels();
+    let channel_mode = match (stereo, channels) {
+        (true, _) => ChannelMode::Coherence,
+        (false, 1) => ChannelMode::Mono,
+        (false, _) => ChannelMode::Average,
+    };
 
-    let sample_rate = cpal::SampleRate(44100);
+    // The chosen range may not contain the target (e.g. a 48 kHz-only interface),
+    // and requesting an unsupported rate would fail, so clamp into the range.
+    let sample_rate = cpal::SampleRate(target_sample_rate.clamp(
+        supported_config.min_sample_rate().0,
+        supported_config.max_sample_rate().0,
+    ));
+    let buffer_size = match (audio_config.stream_buffer, supported_config.buffer_size()) {
+        (Some(frames), cpal::SupportedBufferSize::Range { min, max }) => {
+            cpal::BufferSize::Fixed(frames.clamp(*min, *max))
+        }
+        (Some(frames), cpal::SupportedBufferSize::Unknown) => cpal::BufferSize::Fixed(frames),
+        (None, _) => cpal::BufferSize::Default,
+    };
     let config = supported_config.with_sample_rate(sample_rate);
     
     let sample_rate_val = config.sample_rate().0;
-    let config: cpal::StreamConfig = config.into();
+    let mut config: cpal::StreamConfig = config.into();
+    config.buffer_size = buffer_size;
 
+    if sample_rate_val != target_sample_rate {
+        println!("Requested {} Hz is not supported by the device", target_sample_rate);
+    }
+    validate_sample_rate(sample_rate_val)?;
     println!("Selected sample rate: {} Hz", sample_rate_val);
+    println!("Selected stream buffer: {:?}", config.buffer_size);
+    println!("Selected sample format: {}", sample_format);
+    match channel_mode {
+        ChannelMode::Mono => println!("Capturing 1 channel"),
+        ChannelMode::Average => println!("No mono input available, averaging {} channels", channels),
+        ChannelMode::Coherence => println!("Combining stereo input with the coherence filter"),
+    }
 
-    let err_fn = |err| eprintln!("An error occurred on the audio stream: {}", err);
+    let (frame_size, hop_size) = audio_config.frame_and_hop();
+    println!("Selected analysis frame: {} samples, hop: {} samples", frame_size, hop_size);
 
     // This buffer will accumulate audio data from the callback.
-    let mut audio_buffer = Vec::with_capacity(BUFFER_SIZE * 2);
+    let mut audio_buffer = Vec::with_capacity(frame_size * 2);
+    // Per-channel buffers for stereo input, combined into mono frames.
+    let mut left_buffer = Vec::with_capacity(frame_size * 2);
+    let mut right_buffer = Vec::with_capacity(frame_size * 2);
+    let mut coherence_filter = stereo.then(|| CoherenceFilter::new(frame_size));
 
-    let stream = device.build_input_stream(
-        &config,
-        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-            // Append new data to our buffer.
+    // Gets f32 samples whatever the device's format, see `build_converting_stream`
+    let mut process = move |data: &[f32]| {
+        if let Some(filter) = coherence_filter.as_mut() {
+            // Samples are interleaved L, R, L, R...
+            for pair in data.chunks_exact(2) {
+                left_buffer.push(pair[0]);
+                right_buffer.push(pair[1]);
+            }
+            while left_buffer.len() >= frame_size {
+                let frame_to_send = filter.process(&left_buffer[..frame_size], &right_buffer[..frame_size]);
+                let _ = sender.try_send(frame_to_send);
+                left_buffer.drain(..hop_size);
+                right_buffer.drain(..hop_size);
+            }
+            return;
+        }
+
+        // Append new data to our buffer, averaging interleaved channels to mono.
+        if channels == 1 {
             audio_buffer.extend_from_slice(data);
+        } else {
+            audio_buffer.extend(
+                data.chunks_exact(channels as usize)
+                    .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+            );
+        }
 
-            // While we have enough data for a full frame, process it.
-            while audio_buffer.len() >= BUFFER_SIZE {
-                // Take the first BUFFER_SIZE samples for processing.
-                let frame_to_send = audio_buffer[..BUFFER_SIZE].to_vec();
+        // While we have enough data for a full frame, process it.
+        while audio_buffer.len() >= frame_size {
+            // Take the first frame_size samples for processing.
+            let frame_to_send = audio_buffer[..frame_size].to_vec();
 
-                // Send the frame, ignoring errors if the channel is full.
-                let _ = sender.try_send(frame_to_send);
+            // Send the frame, ignoring errors if the channel is full.
+            let _ = sender.try_send(frame_to_send);
 
-                // Remove the processed samples from the front of the buffer.
-                audio_buffer.drain(..BUFFER_SIZE);
-            }
+            // Advance by one hop; the rest of the frame starts the next one.
+            audio_buffer.drain(..hop_size);
+        }
+    };
+    let stream = match sample_format {
+        cpal::SampleFormat::I16 => build_converting_stream::<i16>(&device, &config, process, errors)?,
+        cpal::SampleFormat::U16 => build_converting_stream::<u16>(&device, &config, process, errors)?,
+        _ => device.build_input_stream(
+            &config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| process(data),
+            stream_error(errors),
+            None,
+        )?,
+    };
+
+    stream.play()?;
+
+    let info = InputInfo {
+        device_name,
+        sample_rate: sample_rate_val,
+        channels,
+        channel_mode,
+        frame_size,
+        hop_size,
+    };
+    Ok((stream, info))
+}
+
+/// Builds an input stream for integer samples that hands `process` f32 samples in -1.0..1.0.
+///
+/// The conversion buffer is reused between callbacks, so the analysis sees
+/// exactly the frames a float input would have produced.
+fn build_converting_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut process: impl FnMut(&[f32]) + Send + 'static,
+    errors: Sender<cpal::StreamError>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let mut converted: Vec<f32> = Vec::with_capacity(BUFFER_SIZE);
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            converted.clear();
+            converted.extend(data.iter().map(|&s| s.to_sample::<f32>()));
+            process(&converted);
         },
-        err_fn,
-        None
+        stream_error(errors),
+        None,
     )?;
+    Ok(stream)
+}
 
-    stream.play()?;
+/// Returns the error callback of a capture stream, which logs each error and passes it on to `errors`.
+fn stream_error(errors: Sender<cpal::StreamError>) -> impl FnMut(cpal::StreamError) + Send + 'static {
+    move |err| {
+        eprintln!("An error occurred on the audio stream: {}", err);
+        let _ = errors.try_send(err);
+    }
+}
+
+/// Returns how long to wait before an attempt to reopen a lost input.
+///
+/// # Arguments
+/// * `attempt` - Number of the attempt, starting at 1
+///
+/// # Returns
+/// * `Some(delay)` - `RECONNECT_BACKOFF`, doubled for every earlier attempt
+/// * `None` - All `RECONNECT_ATTEMPTS` have been made
+pub fn reconnect_delay(attempt: u32) -> Option<Duration> {
+    (1..=RECONNECT_ATTEMPTS)
+        .contains(&attempt)
+        .then(|| RECONNECT_BACKOFF * 2u32.pow(attempt - 1))
+}
+
+/// Makes one attempt to reopen an input after its device was lost.
+///
+/// The configured device is tried first, in case it is back (e.g. the USB
+/// cable was only briefly loose); if it isn't, the default input device
+/// takes its place. Everything else in `audio_config` is kept.
+///
+/// # Arguments
+/// * `sender`, `errors`, `audio_config` - As for `start_audio_capture`
+///
+/// # Returns
+/// * `Ok((stream, info))` - The new stream; `info` tells which device it is
+/// * `Err(e)` - Neither device could be opened
+pub fn reopen_audio_capture(
+    sender: Sender<Vec<f32>>,
+    errors: Sender<cpal::StreamError>,
+    audio_config: &AudioConfig,
+) -> Result<(cpal::Stream, InputInfo)> {
+    match start_audio_capture(sender.clone(), errors.clone(), audio_config) {
+        Ok(started) => Ok(started),
+        Err(e) if audio_config.input_device.is_some() => {
+            println!("Input device unavailable ({}), trying the default device", e);
+            let default_config = AudioConfig { input_device: None, ..audio_config.clone() };
+            start_audio_capture(sender, errors, &default_config)
+        }
+        Err(e) => Err(e),
+    }
+}
 
-    Ok((stream, sample_rate_val))
+/// Reads a WAV file as the back-to-back frames `start_audio_capture` would send.
+///
+/// A shorthand for [`wav::read_frames`](crate::wav::read_frames) with the hop
+/// equal to the frame, for tests and tools that replay a recording in place
+/// of a device. Use `read_frames` directly for overlapping frames.
+///
+/// # Arguments
+/// * `path` - Path of the `.wav` file
+/// * `buffer_size` - Samples per frame (e.g. `BUFFER_SIZE`)
+///
+/// # Returns
+/// * `Ok((frames, sample_rate))` - Mono frames in order, and the file's rate in Hz
+/// * `Err(e)` - The file could not be read, or `buffer_size` is zero
+pub fn wav_frame_iterator(path: &str, buffer_size: usize) -> Result<(impl Iterator<Item = Vec<f32>>, u32)> {
+    crate::wav::read_frames(std::path::Path::new(path), buffer_size, buffer_size)
+}
+
+/// Lists the names of the input devices of every available host.
+///
+/// The default host comes first. Devices whose name can't be read are left
+/// out, as are hosts that can't enumerate their devices, and a device that
+/// several hosts offer under the same name is listed once.
+pub fn input_device_names() -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in all_input_devices().filter_map(|device| device.name().ok()) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Iterates over the input devices of every available host, the default host's first.
+fn all_input_devices() -> impl Iterator<Item = cpal::Device> {
+    let default_id = cpal::default_host().id();
+    let mut host_ids = cpal::available_hosts();
+    host_ids.sort_by_key(|&id| id != default_id);
+    host_ids
+        .into_iter()
+        .filter_map(|id| cpal::host_from_id(id).ok())
+        .filter_map(|host| host.input_devices().ok())
+        .flatten()
+}
+
+/// Checks that the analysis can work at a sample rate.
+///
+/// Every frequency computed by the analysis (bin spacing, YIN lags, filter
+/// coefficients) is derived from the rate passed to `analyze_frame`, so any
+/// rate works as long as it is the one the samples were actually recorded at
+/// and lies within `MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE`.
+///
+/// # Arguments
+/// * `sample_rate` - Sample rate in Hz
+///
+/// # Returns
+/// * `Ok(())` - The rate is usable
+/// * `Err(e)` - The rate is outside the supported range
+pub fn validate_sample_rate(sample_rate: u32) -> Result<()> {
+    if (MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&sample_rate) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Sample rate {} Hz is outside the supported {}-{} Hz",
+            sample_rate,
+            MIN_SAMPLE_RATE,
+            MAX_SAMPLE_RATE
+        ))
+    }
 }
 
 /// Finds the best supported audio configuration for the target sample rate.
 /// 
 /// This function searches through available audio configurations and selects
 /// the one that best matches our requirements:
-/// - Mono channel (1 channel)
-/// - 32-bit float format
-/// - Closest sample rate to target
+/// - The requested channel count
+/// - A sample format the capture can convert, 32-bit float preferred
+/// - Sample rate range containing the target, or the closest one to it
 /// 
 /// # Arguments
 /// * `configs` - List of supported audio configurations from the device
 /// * `target_rate` - Desired sample rate in Hz
+/// * `channels` - Required number of input channels
 /// 
 /// # Returns
 /// * `Some(config)` - Best matching configuration
@@ -110,13 +597,152 @@ pub fn start_audio_capture(sender: Sender<Vec<f32>>) -> Result<(cpal::Stream, u3
 fn find_supported_config(
     configs: Vec<SupportedStreamConfigRange>,
     target_rate: u32,
+    channels: u16,
 ) -> Option<SupportedStreamConfigRange> {
     configs
         .into_iter()
-        .filter(|c| c.channels() == 1 && c.sample_format() == cpal::SampleFormat::F32)
+        .filter(|c| c.channels() == channels && SAMPLE_FORMATS.contains(&c.sample_format()))
         .min_by_key(|c| {
-            let min_diff = (c.min_sample_rate().0 as i32 - target_rate as i32).abs();
-            let max_diff = (c.max_sample_rate().0 as i32 - target_rate as i32).abs();
-            min_diff.min(max_diff)
+            // A float format wins over integer ones; among equals, the closest rate
+            let format_rank = SAMPLE_FORMATS.iter().position(|&f| f == c.sample_format()).unwrap_or(SAMPLE_FORMATS.len());
+            let (min, max) = (c.min_sample_rate().0, c.max_sample_rate().0);
+            let distance = if (min..=max).contains(&target_rate) {
+                0
+            } else {
+                (min as i64 - target_rate as i64).abs().min((max as i64 - target_rate as i64).abs())
+            };
+            (format_rank, distance)
         })
-}
\ No newline at end of file
+}
+
+/// Length of the fade applied to both ends of a synthesized tone, in milliseconds.
+const TONE_FADE_MS: u64 = 10;
+
+/// Peak amplitude of synthesized tones (leaves headroom on the output device).
+const TONE_AMPLITUDE: f32 = 0.5;
+
+/// Synthesizes a sine tone with short linear fades to avoid clicks.
+///
+/// # Arguments
+/// * `frequency` - Tone frequency in Hz
+/// * `sample_rate` - Sample rate in Hz
+/// * `duration_ms` - Tone length in milliseconds
+///
+/// # Returns
+/// * `Vec<f32>` - Mono samples in the range -`TONE_AMPLITUDE`..=`TONE_AMPLITUDE`
+pub fn synthesize_tone(frequency: f32, sample_rate: u32, duration_ms: u64) -> Vec<f32> {
+    let total = (sample_rate as u64 * duration_ms / 1000) as usize;
+    let fade = ((sample_rate as u64 * TONE_FADE_MS / 1000) as usize).min(total / 2).max(1);
+    let step = 2.0 * std::f32::consts::PI * frequency / sample_rate as f32;
+
+    (0..total)
+        .map(|i| {
+            let envelope = (i.min(total - 1 - i) as f32 / fade as f32).min(1.0);
+            TONE_AMPLITUDE * envelope * (step * i as f32).sin()
+        })
+        .collect()
+}
+
+/// Plays a reference tone on the default output device.
+///
+/// The output stream is built and owned by a dedicated thread, so it is
+/// completely independent of the capture stream returned by
+/// `start_audio_capture`. The thread drops the stream once the tone has
+/// finished playing. Note that the microphone will still hear the tone, so
+/// callers should avoid capturing measurements while it plays.
+///
+/// # Arguments
+/// * `freq` - Frequency of the note in the standard (A4 = 440 Hz) note table
+/// * `duration_ms` - Tone length in milliseconds
+/// * `a4_hz` - Concert pitch to play at; `freq` is scaled by `a4_hz / 440`
+///
+/// # Returns
+/// * `Ok(handle)` - Handle to the playback thread, finished once the tone has stopped
+/// * `Err(e)` - Error if no output device is available or the stream could not start
+pub fn play_reference_tone(freq: f32, duration_ms: u64, a4_hz: f32) -> Result<JoinHandle<()>> {
+    let frequency = freq * a4_hz / crate::tuning::REFERENCE_A4_HZ;
+    let (ready_tx, ready_rx) = crossbeam_channel::bounded::<Result<()>>(1);
+
+    let handle = thread::spawn(move || {
+        let stream = match build_tone_stream(frequency, duration_ms) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        let _ = ready_tx.send(Ok(()));
+
+        // Let the whole tone (and a little slack for the device buffer) play out.
+        thread::sleep(Duration::from_millis(duration_ms + 100));
+        let _ = stream.pause();
+        drop(stream);
+    });
+
+    match ready_rx.recv() {
+        Ok(Ok(())) => Ok(handle),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(anyhow!("Reference tone thread exited unexpectedly")),
+    }
+}
+
+/// Opens the default output device and starts playing a synthesized tone.
+///
+/// # Arguments
+/// * `frequency` - Tone frequency in Hz
+/// * `duration_ms` - Tone length in milliseconds
+///
+/// # Returns
+/// * `Ok(stream)` - The playing output stream; dropping it stops playback
+/// * `Err(e)` - Error if output setup fails
+fn build_tone_stream(frequency: f32, duration_ms: u64) -> Result<cpal::Stream> {
+    let host = cpal::default_host();
+    let device = host.default_output_device()
+        .ok_or_else(|| anyhow!("No output device available"))?;
+    let supported_config = device.default_output_config()?;
+
+    let sample_format = supported_config.sample_format();
+    let config: cpal::StreamConfig = supported_config.into();
+    let samples = synthesize_tone(frequency, config.sample_rate.0, duration_ms);
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => build_output_stream::<f32>(&device, &config, samples)?,
+        cpal::SampleFormat::I16 => build_output_stream::<i16>(&device, &config, samples)?,
+        cpal::SampleFormat::U16 => build_output_stream::<u16>(&device, &config, samples)?,
+        format => return Err(anyhow!("Unsupported output sample format: {}", format)),
+    };
+    stream.play()?;
+
+    Ok(stream)
+}
+
+/// Builds an output stream that plays `samples` on every channel, then silence.
+fn build_output_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    samples: Vec<f32>,
+) -> Result<cpal::Stream>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let mut position = 0;
+    let err_fn = |err| eprintln!("An error occurred on the output stream: {}", err);
+
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            for frame in data.chunks_mut(channels) {
+                let sample = samples.get(position).copied().unwrap_or(0.0);
+                position += 1;
+                for out in frame.iter_mut() {
+                    *out = T::from_sample(sample);
+                }
+            }
+        },
+        err_fn,
+        None
+    )?;
+
+    Ok(stream)
+}
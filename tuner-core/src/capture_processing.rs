@@ -5,16 +5,151 @@
 
 use crate::{
     inharmonicity::{KeyMeasurement, Partial},
-    tuning,
+    tracking::EnergyTrend,
+    tuning::{self, OctaveConvention},
+    AnalysisResult,
 };
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+/// Default minimum confidence of the frame a measurement is taken from.
+///
+/// Stability already requires every frame to exceed `DEFAULT_STABLE_CONFIDENCE`,
+/// so this only rejects captures where even the best frame is marginal.
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 0.95;
+
+/// Default time a note must stay stable before it is captured (~20 frames at 44.1 kHz).
+pub const DEFAULT_STABILITY_WINDOW: Duration = Duration::from_millis(880);
+
+/// Default confidence every frame of a stable note must exceed.
+pub const DEFAULT_STABLE_CONFIDENCE: f32 = 0.9;
+
+/// Default highest spectral flux of a steady (sustaining) frame.
+pub const DEFAULT_MAX_STABLE_FLUX: f32 = 0.15;
+
+/// Default time to ignore frames after a capture starts.
+///
+/// Long enough to skip the hammer attack, short enough to keep most of the sustain.
+pub const DEFAULT_SETTLE_TIME: Duration = Duration::from_millis(150);
+
+/// Default minimum number of partials, fundamental included, a measurement needs.
+///
+/// Three points is the least the 'B' regression accepts. High treble notes
+/// often have fewer usable partials; lowering this to 2 lets them be measured
+/// with a less reliable two-partial estimate.
+pub const DEFAULT_MIN_PARTIALS: usize = 3;
 
 /// Different processing operations that can be performed on captured frames
 #[derive(Debug, Clone, PartialEq)]
 pub enum ProcessingOperation {
     /// Find the frame with the highest confidence (default strategy)
     BestConfidence,
-    /// Average all frames (future implementation)
-    Average
+    /// Each partial's frequency is the mean over all frames of the key
+    Average,
+    /// Like `Average`, but each frame counts in proportion to its pitch
+    /// confidence, so marginal frames at the edge of the stability window
+    /// pull the result less
+    WeightedAverage,
+    /// Best-confidence measurement plus the amplitude envelope of every
+    /// partial across the whole capture window
+    Envelope,
+    /// Each partial's frequency is taken from the frame where that partial
+    /// is strongest, since higher partials peak earlier and decay faster
+    PartialPeak,
+}
+
+/// Settings controlling how a capture is taken and which captures produce a measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureConfig {
+    /// Minimum pitch confidence (0.0-1.0) of the best frame in the capture
+    pub min_confidence: f32,
+    /// Frames arriving within this time of the capture starting are ignored,
+    /// so the attack transient never reaches the stability buffer
+    pub settle_time: Duration,
+    /// Minimum partials, fundamental included, a measurement must have;
+    /// values below 2 act as 2 since 'B' can't be calculated from fewer
+    pub min_partials: usize,
+    /// How long a note must stay stable before it is captured
+    pub stability_window: Duration,
+    /// Confidence every frame of a stable note must exceed
+    pub stable_confidence: f32,
+    /// Highest spectral flux a frame of a stable note may have
+    pub max_stable_flux: f32,
+    /// Reject frames whose energy is still rising, so a blooming attack is
+    /// never taken for a stable note
+    pub reject_rising_energy: bool,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+            settle_time: DEFAULT_SETTLE_TIME,
+            min_partials: DEFAULT_MIN_PARTIALS,
+            stability_window: DEFAULT_STABILITY_WINDOW,
+            stable_confidence: DEFAULT_STABLE_CONFIDENCE,
+            max_stable_flux: DEFAULT_MAX_STABLE_FLUX,
+            reject_rising_energy: true,
+        }
+    }
+}
+
+/// Reasons a capture did not produce a measurement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureError {
+    /// The capture buffer contained no frames
+    NoFrames,
+    /// The best frame had no detected note
+    NoNoteData,
+    /// Even the best frame was below `CaptureConfig::min_confidence`
+    TooWeak { confidence: f32, min_confidence: f32 },
+    /// The note is outside the 88 keys a profile can store (e.g. C0 on an extended keyboard)
+    OutsideProfileRange(String),
+    /// Fewer partials were found than `CaptureConfig::min_partials`
+    NotEnoughPartials { found: usize, required: usize },
+    /// The partials found did not give a usable 'B' value
+    NoInharmonicity,
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::NoFrames => write!(f, "no frames captured"),
+            CaptureError::NoNoteData => write!(f, "no stable note in capture"),
+            CaptureError::TooWeak { confidence, min_confidence } => write!(
+                f,
+                "capture too weak ({:.0}% < {:.0}%)",
+                confidence * 100.0,
+                min_confidence * 100.0
+            ),
+            CaptureError::OutsideProfileRange(note) => write!(f, "{} is outside the 88-key profile range", note),
+            CaptureError::NotEnoughPartials { found, required } => {
+                write!(f, "not enough partials ({} < {})", found, required)
+            }
+            CaptureError::NoInharmonicity => write!(f, "partials give no B value"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// The amplitude of a single partial in each frame of a capture window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialEnvelope {
+    pub number: u32,          // The partial number (n=1, 2, 3...)
+    pub magnitudes: Vec<f32>, // Spectrum magnitude per frame (0.0 where not detected)
+}
+
+/// How each partial's amplitude evolved over the frames of a capture.
+///
+/// Frames are in capture order and evenly spaced by the analysis frame
+/// interval, so the magnitudes trace the decay of each partial after a strike.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvelopeMeasurement {
+    pub frame_count: usize,
+    pub partials: Vec<PartialEnvelope>,
 }
 
 /// Processes captured frames using the specified operation strategy.
@@ -28,26 +163,100 @@ pub enum ProcessingOperation {
 /// # Arguments
 /// * `buffer` - The buffer of stable analysis results to process
 /// * `operation` - The processing operation to perform
+/// * `config` - Quality thresholds the capture must meet
 ///
 /// # Returns
-/// * `Option<KeyMeasurement>` - The processed measurement if successful, None otherwise
-pub fn process(buffer: Vec<crate::AnalysisResult>, operation: ProcessingOperation) -> Option<KeyMeasurement> {
-    match operation {
-        ProcessingOperation::BestConfidence => process_best_confidence(buffer),
-        ProcessingOperation::Average => {
-            eprintln!("[CAPTURE] Average processing not yet implemented");
-            None
+/// * `Ok(KeyMeasurement)` - The processed measurement
+/// * `Err(CaptureError)` - Why nothing should be stored (e.g. the capture was too weak)
+///
+/// An empty buffer is rejected with `CaptureError::NoFrames` before any
+/// operation runs, so operations may assume at least one frame.
+pub fn process(
+    buffer: Vec<crate::AnalysisResult>,
+    operation: ProcessingOperation,
+    config: &CaptureConfig,
+) -> Result<KeyMeasurement, CaptureError> {
+    if buffer.is_empty() {
+        eprintln!("[CAPTURE] Process skipped: {}", CaptureError::NoFrames);
+        return Err(CaptureError::NoFrames);
+    }
+
+    let result = match operation {
+        ProcessingOperation::BestConfidence => process_best_confidence(&buffer, config),
+        ProcessingOperation::Average => process_average(&buffer, config, |_| 1.0),
+        ProcessingOperation::WeightedAverage => {
+            process_average(&buffer, config, |frame| frame.confidence.unwrap_or(0.0))
         }
+        ProcessingOperation::Envelope => process_envelope(&buffer, config),
+        ProcessingOperation::PartialPeak => process_partial_peak(&buffer, config),
+    };
+    if let Err(e) = &result {
+        eprintln!("[CAPTURE] Process failed: {}", e);
     }
+    result
+}
+
+/// Checks if all AnalysisResult frames in the buffer are "stable."
+///
+/// Stability is defined as:
+/// 1. The buffer is not empty.
+/// 2. All frames have a `note_name` that is `Some` and is the *same* note.
+/// 3. All frames have a `confidence` that is `Some` and is above `config.stable_confidence`.
+/// 4. All frames have a `spectral_flux` that is `Some` and at most `config.max_stable_flux`,
+///    so the spectrum has stopped evolving and the sustain, not the attack, is measured.
+/// 5. If `config.reject_rising_energy` is set, no frame has a `Rising` energy trend.
+/// 6. No frame is `out_of_range` of the keyboard.
+///
+/// How long the buffer must span is up to the caller (see `CaptureConfig::stability_window`).
+pub fn check_stability(buffer: &VecDeque<AnalysisResult>, config: &CaptureConfig) -> bool {
+    // 1. Check that there is anything to judge
+    if buffer.is_empty() {
+        return false;
+    }
+
+    // Get the note name from the first frame. If it's None, it's not stable.
+    let first_note = match &buffer[0].note_name {
+        Some(n) => n,
+        None => return false,
+    };
+
+    // Use `iter().all()` to efficiently check every frame against the criteria.
+    buffer.iter().all(|frame| {
+        // 2. Check for matching note name
+        let matching_note = frame.note_name.as_ref().is_some_and(|n| n == first_note);
+
+        // 3. Check confidence
+        let high_confidence = frame
+            .confidence
+            .is_some_and(|c| c > config.stable_confidence);
+
+        // 4. Check that the spectrum is steady
+        let steady_spectrum = frame
+            .spectral_flux
+            .is_some_and(|flux| flux <= config.max_stable_flux);
+
+        // 5. Check that the note is sustaining or decaying, not still rising
+        let not_rising = !config.reject_rising_energy || frame.energy_trend != Some(EnergyTrend::Rising);
+
+        // 6. Check that the pitch is on the keyboard at all (such frames also have no note name)
+        let in_range = !frame.out_of_range;
+
+        matching_note && high_confidence && steady_spectrum && not_rising && in_range
+    })
 }
 
 /// Processes frames using the "Best-Confidence" strategy.
 ///
-/// This is the default and currently only implemented strategy:
+/// This is the default strategy, and the others start from its result:
 /// 1. Finds the single `AnalysisResult` with the highest confidence in the buffer
-/// 2. Uses that `best_frame` to create a `KeyMeasurement`
-/// 3. Calculates the 'B' value for the measurement
-fn process_best_confidence(buffer: Vec<crate::AnalysisResult>) -> Option<KeyMeasurement> {
+/// 2. Rejects the capture if that frame is below the minimum confidence
+/// 3. Uses that `best_frame` to create a `KeyMeasurement`, rejecting it if it
+///    has fewer than `CaptureConfig::min_partials` partials
+/// 4. Calculates the 'B' value for the measurement, rejecting it if there is none
+fn process_best_confidence(
+    buffer: &[crate::AnalysisResult],
+    config: &CaptureConfig,
+) -> Result<KeyMeasurement, CaptureError> {
     // 1. Find the frame with the highest confidence
     let best_frame = buffer
         .iter()
@@ -60,49 +269,307 @@ fn process_best_confidence(buffer: Vec<crate::AnalysisResult>) -> Option<KeyMeas
         });
 
     if let Some(best_frame) = best_frame {
-        // 2. Use this frame to perform the capture logic
+        // 2. Reject captures where even the best frame is unreliable
+        let confidence = best_frame.confidence.unwrap_or(0.0);
+        if confidence < config.min_confidence {
+            return Err(CaptureError::TooWeak {
+                confidence,
+                min_confidence: config.min_confidence,
+            });
+        }
+
+        // 3. Use this frame to perform the capture logic
         if let (Some(note_name), Some(freq)) =
             (&best_frame.note_name, best_frame.detected_frequency)
         {
-            let key_index = tuning::get_key_index_from_name(note_name);
+            let key_index = tuning::key_index_from_name_in(note_name, OctaveConvention::Scientific)
+                .ok_or_else(|| CaptureError::OutsideProfileRange(note_name.clone()))?;
 
-            // Create the fundamental partial (n=1)
-            let mut all_partials = vec![Partial {
-                number: 1,
-                frequency: freq,
-            }];
+            // Create the fundamental partial (n=1), unless it was only derived
+            // from the 2nd partial with an assumed 'B' that would bias the fit
+            let mut all_partials = if best_frame.derived_fundamental {
+                Vec::new()
+            } else {
+                vec![Partial {
+                    number: 1,
+                    frequency: freq,
+                }]
+            };
 
-            // Create the overtone partials (n=2, 3, 4...)
+            // Create the overtone partials (n=2, 3, 4...), keeping the harmonic
+            // number reported by find_partials so gaps don't shift the numbering
             let overtone_partials = best_frame
                 .partials
                 .iter()
-                .enumerate()
-                .map(|(i, &freq)| Partial {
-                    number: (i + 2) as u32, // find_partials starts at the 2nd partial
-                    frequency: freq,
+                .map(|partial| Partial {
+                    number: partial.number,
+                    frequency: partial.frequency,
                 });
             all_partials.extend(overtone_partials);
 
-            // 3. Create and 4. Calculate 'B' value
+            let required = config.min_partials.max(2);
+            if all_partials.len() < required {
+                return Err(CaptureError::NotEnoughPartials {
+                    found: all_partials.len(),
+                    required,
+                });
+            }
+
+            // 4. Create the measurement and calculate its 'B' value
             let mut measurement = KeyMeasurement {
                 key_index,
                 partials: all_partials,
                 calculated_b: None,
+                fit_r_squared: None,
+                envelope: None,
+                low_confidence: false,
+                confidence: Some(confidence),
+                reliability: None,
             };
-            measurement.calculate_b_value();
+            if measurement.calculate_b_value().is_none() {
+                return Err(CaptureError::NoInharmonicity);
+            }
 
             eprintln!(
                 "[CAPTURE] Processed measurement for {}: B={:?}",
                 note_name, measurement.calculated_b
             );
 
-            Some(measurement)
+            Ok(measurement)
         } else {
-            eprintln!("[CAPTURE] Process failed: Best frame had no stable note data.");
-            None
+            Err(CaptureError::NoNoteData)
         }
     } else {
-        eprintln!("[CAPTURE] Process failed: No best frame found in buffer.");
-        None
+        Err(CaptureError::NoFrames)
+    }
+}
+
+/// Processes frames using the "Envelope" strategy.
+///
+/// The measurement itself (partials and 'B' value) comes from the
+/// best-confidence frame, and the per-frame magnitude of every partial
+/// across the buffer is attached as an `EnvelopeMeasurement`.
+fn process_envelope(
+    buffer: &[crate::AnalysisResult],
+    config: &CaptureConfig,
+) -> Result<KeyMeasurement, CaptureError> {
+    let mut measurement = process_best_confidence(buffer, config)?;
+    let envelope = build_envelope(buffer);
+
+    eprintln!(
+        "[CAPTURE] Recorded envelope of {} partials over {} frames",
+        envelope.partials.len(),
+        envelope.frame_count
+    );
+
+    measurement.envelope = Some(envelope);
+    Ok(measurement)
+}
+
+/// Processes frames using the "Partial-Peak" strategy.
+///
+/// The best-confidence frame decides the key and whether the capture is
+/// good enough. Every partial seen in a frame of that key is then measured
+/// in the frame where its magnitude is highest, so fast-decaying upper
+/// partials are read near their peak instead of from a single compromise
+/// frame, and the 'B' value is recalculated from these frequencies.
+fn process_partial_peak(
+    buffer: &[crate::AnalysisResult],
+    config: &CaptureConfig,
+) -> Result<KeyMeasurement, CaptureError> {
+    let best = process_best_confidence(buffer, config)?;
+
+    // Only frames of the same key; a neighbouring note's partials would skew the fit
+    let frames: Vec<&AnalysisResult> = buffer
+        .iter()
+        .filter(|frame| {
+            frame.note_name.as_deref().and_then(|name| {
+                tuning::key_index_from_name_in(name, OctaveConvention::Scientific)
+            }) == Some(best.key_index)
+        })
+        .collect();
+
+    // (magnitude, frequency) at each partial's strongest frame, indexed by number
+    let mut peaks: Vec<Option<(f32, f32)>> = Vec::new();
+    let mut keep_peak = |number: u32, magnitude: f32, frequency: f32| {
+        let index = number as usize - 1;
+        if peaks.len() <= index {
+            peaks.resize(index + 1, None);
+        }
+        if peaks[index].is_none_or(|(peak, _)| magnitude > peak) {
+            peaks[index] = Some((magnitude, frequency));
+        }
+    };
+    for frame in &frames {
+        if let (Some(magnitude), Some(frequency), false) = (frame.fundamental_magnitude, frame.detected_frequency, frame.derived_fundamental) {
+            keep_peak(1, magnitude, frequency);
+        }
+        for partial in frame.partials.iter().filter(|p| p.number >= 2) {
+            keep_peak(partial.number, partial.magnitude, partial.frequency);
+        }
+    }
+
+    let partials: Vec<Partial> = peaks
+        .iter()
+        .zip(1..)
+        .filter_map(|(peak, number)| peak.map(|(_, frequency)| Partial { number, frequency }))
+        .collect();
+
+    let mut measurement = KeyMeasurement { partials, ..best };
+    if measurement.calculate_b_value().is_none() {
+        return Err(CaptureError::NoInharmonicity);
+    }
+
+    eprintln!(
+        "[CAPTURE] Measured {} partials at their peak frames: B={:?}",
+        measurement.partials.len(),
+        measurement.calculated_b
+    );
+
+    Ok(measurement)
+}
+
+/// Processes frames using the "Average" and "Weighted-Average" strategies.
+///
+/// The best-confidence frame decides the key and whether the capture is
+/// good enough. Every partial seen in a frame of that key is then averaged
+/// over the frames that contain it, each frame weighted by `weight`:
+/// `sum(frequency * weight) / sum(weight)`. A partial whose frames all
+/// weigh nothing is left out, and the 'B' value is recalculated from the
+/// averaged frequencies.
+fn process_average(
+    buffer: &[crate::AnalysisResult],
+    config: &CaptureConfig,
+    weight: impl Fn(&AnalysisResult) -> f32,
+) -> Result<KeyMeasurement, CaptureError> {
+    let best = process_best_confidence(buffer, config)?;
+
+    // (weighted frequency sum, weight sum) of each partial, indexed by number
+    let mut sums: Vec<(f64, f64)> = Vec::new();
+    let mut add = |number: u32, frequency: f32, weight: f32| {
+        let index = number as usize - 1;
+        if sums.len() <= index {
+            sums.resize(index + 1, (0.0, 0.0));
+        }
+        sums[index].0 += frequency as f64 * weight as f64;
+        sums[index].1 += weight as f64;
+    };
+    // Only frames of the same key; a neighbouring note's partials would skew the average
+    let frames = buffer.iter().filter(|frame| {
+        frame.note_name.as_deref().and_then(|name| {
+            tuning::key_index_from_name_in(name, OctaveConvention::Scientific)
+        }) == Some(best.key_index)
+    });
+    for frame in frames {
+        let frame_weight = weight(frame).max(0.0);
+        if let (Some(frequency), false) = (frame.detected_frequency, frame.derived_fundamental) {
+            add(1, frequency, frame_weight);
+        }
+        for partial in frame.partials.iter().filter(|p| p.number >= 2) {
+            add(partial.number, partial.frequency, frame_weight);
+        }
+    }
+
+    let partials: Vec<Partial> = sums
+        .iter()
+        .zip(1..)
+        .filter(|&(&(_, total), _)| total > 0.0)
+        .map(|(&(weighted, total), number)| Partial { number, frequency: (weighted / total) as f32 })
+        .collect();
+
+    let mut measurement = KeyMeasurement { partials, ..best };
+    if measurement.calculate_b_value().is_none() {
+        return Err(CaptureError::NoInharmonicity);
+    }
+
+    eprintln!(
+        "[CAPTURE] Averaged {} partials over the capture: B={:?}",
+        measurement.partials.len(),
+        measurement.calculated_b
+    );
+
+    Ok(measurement)
+}
+
+/// Collects the magnitude of each partial in every frame of the buffer.
+fn build_envelope(buffer: &[crate::AnalysisResult]) -> EnvelopeMeasurement {
+    let frame_count = buffer.len();
+    let highest_partial = buffer
+        .iter()
+        .flat_map(|frame| frame.partials.iter().map(|p| p.number))
+        .max()
+        .unwrap_or(1);
+
+    // Partial 1 is the fundamental; overtones follow from find_partials (n=2, 3, 4...)
+    let mut partials: Vec<PartialEnvelope> = (1..=highest_partial)
+        .map(|number| PartialEnvelope {
+            number,
+            magnitudes: vec![0.0; frame_count],
+        })
+        .collect();
+
+    for (frame_index, frame) in buffer.iter().enumerate() {
+        if let Some(magnitude) = frame.fundamental_magnitude {
+            partials[0].magnitudes[frame_index] = magnitude;
+        }
+        for partial in frame.partials.iter().filter(|p| p.number >= 2) {
+            partials[partial.number as usize - 1].magnitudes[frame_index] = partial.magnitude;
+        }
+    }
+
+    EnvelopeMeasurement { frame_count, partials }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pitch::DetectedPartial;
+
+    /// A frame of A2 with the given confidence, fundamental and overtones.
+    fn frame(confidence: f32, fundamental: f32, overtones: &[(u32, f32)]) -> AnalysisResult {
+        AnalysisResult {
+            detected_frequency: Some(fundamental),
+            confidence: Some(confidence),
+            note_name: Some("A2".to_string()),
+            partials: overtones
+                .iter()
+                .map(|&(number, frequency)| DetectedPartial { number, frequency, magnitude: 1.0 })
+                .collect(),
+            ..AnalysisResult::default()
+        }
+    }
+
+    fn frequency(measurement: &KeyMeasurement, number: u32) -> Option<f32> {
+        measurement.partials.iter().find(|p| p.number == number).map(|p| p.frequency)
+    }
+
+    #[test]
+    fn weighted_average_weighs_partials_by_frame_confidence() {
+        // The 5th partial only shows up in a frame of zero confidence
+        let buffer = vec![
+            frame(1.0, 110.0, &[(2, 220.2), (3, 330.9), (4, 441.6)]),
+            frame(0.5, 110.3, &[(2, 220.8), (3, 331.2)]),
+            frame(0.0, 111.0, &[(2, 221.0), (5, 552.0)]),
+        ];
+        let measurement = process(buffer.clone(), ProcessingOperation::WeightedAverage, &CaptureConfig::default()).unwrap();
+
+        // sum(frequency * confidence) / sum(confidence)
+        let expected = [
+            (1, (110.0 + 110.3 * 0.5) / 1.5),
+            (2, (220.2 + 220.8 * 0.5) / 1.5),
+            (3, (330.9 + 331.2 * 0.5) / 1.5),
+            (4, 441.6),
+        ];
+        for (number, mean) in expected {
+            let measured = frequency(&measurement, number).unwrap();
+            assert!((measured - mean).abs() < 1e-3, "partial {number}: {measured} Hz, expected {mean} Hz");
+        }
+        assert_eq!(frequency(&measurement, 5), None);
+        assert!(measurement.calculated_b.is_some());
+
+        // Unweighted, every frame counts, the last one included
+        let plain = process(buffer, ProcessingOperation::Average, &CaptureConfig::default()).unwrap();
+        assert!((frequency(&plain, 1).unwrap() - (110.0 + 110.3 + 111.0) / 3.0).abs() < 1e-3);
+        assert_eq!(frequency(&plain, 5), Some(552.0));
     }
 }
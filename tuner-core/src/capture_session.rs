@@ -0,0 +1,209 @@
+//! # Capture Session Module
+//!
+//! The stability-gated capture flow: frames are collected once the attack
+//! has settled, and as soon as they stay stable for the whole stability
+//! window they are turned into a `KeyMeasurement` with
+//! `capture_processing::process`. The GUI drives one session from its
+//! analysis results; scripts and tests can drive one from synthesized or
+//! recorded frames in exactly the same way.
+//!
+//! ## Features
+//! - Settle time that keeps the hammer attack out of the stability buffer
+//! - Sliding stability window measured on frame timestamps, not frame counts
+//! - Auto-measure: every new stable note is captured, without re-capturing
+//!   the note that is still ringing
+
+use std::collections::VecDeque;
+use std::time::Instant;
+use crate::{
+    capture_processing::{self, CaptureConfig, CaptureError, ProcessingOperation},
+    inharmonicity::KeyMeasurement,
+    tuning::{self, OctaveConvention},
+    AnalysisResult,
+};
+
+/// Collects frames for one capture at a time and processes them once stable.
+///
+/// Call [`start`](Self::start) to begin a capture and pass every analysis
+/// result, in order, to [`push`](Self::push). Time is taken from each
+/// result's `timestamp`, so frames fed faster than real time (e.g. from a
+/// file) must carry the times they would have been analyzed at.
+#[derive(Debug, Default)]
+pub struct CaptureSession {
+    /// Settle time, stability window and quality thresholds
+    pub config: CaptureConfig,
+    buffer: VecDeque<AnalysisResult>,
+    capturing: bool,
+    auto_measure: bool,
+    settle_from: Option<Instant>,
+    last_note: Option<String>,
+    last_capture_key: Option<u8>,
+}
+
+impl CaptureSession {
+    /// Creates an idle session.
+    pub fn new(config: CaptureConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// Starts a capture; the settle time runs from the next frame pushed.
+    pub fn start(&mut self) {
+        self.capturing = true;
+        self.buffer.clear();
+        self.settle_from = None;
+    }
+
+    /// Abandons the current capture, if any.
+    pub fn stop(&mut self) {
+        self.capturing = false;
+        self.buffer.clear();
+    }
+
+    /// Returns true between [`start`](Self::start) and the end of the capture.
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    /// Drops the frames collected so far, e.g. while the microphone hears
+    /// something that must not be measured. The capture itself goes on.
+    pub fn interrupt(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Turns auto-measure on or off and forgets the key captured last.
+    ///
+    /// In auto-measure a new note can be struck at any time, so each new note
+    /// restarts the settle time, and a capture of the key captured last is
+    /// skipped while it keeps ringing. The session still has to be started.
+    pub fn set_auto_measure(&mut self, enabled: bool) {
+        self.auto_measure = enabled;
+        self.last_capture_key = None;
+        self.buffer.clear();
+    }
+
+    /// Returns the key captured last in auto-measure, which is not captured again in a row.
+    pub fn last_capture_key(&self) -> Option<u8> {
+        self.last_capture_key
+    }
+
+    /// Allows the key captured last to be captured again straight away.
+    pub fn forget_last_capture(&mut self) {
+        self.last_capture_key = None;
+    }
+
+    /// Adds the next analysis result and captures once the buffer is stable.
+    ///
+    /// A capture ends the session until it is started again, whether it was
+    /// stored or rejected.
+    ///
+    /// # Arguments
+    /// * `result` - The frame just analyzed and tracked
+    /// * `operation` - How the stable frames are turned into a measurement
+    ///
+    /// # Returns
+    /// * `Some(Ok(measurement))` - A capture completed
+    /// * `Some(Err(e))` - The frames were stable, but `capture_processing::process` rejected them
+    /// * `None` - Not capturing, or not stable for long enough yet
+    pub fn push(&mut self, result: &AnalysisResult, operation: ProcessingOperation) -> Option<Result<KeyMeasurement, CaptureError>> {
+        let now = result.timestamp.unwrap_or_else(Instant::now);
+        let new_note = result.note_name.is_some() && result.note_name != self.last_note;
+        self.last_note.clone_from(&result.note_name);
+        if !self.capturing {
+            return None;
+        }
+
+        // The attack of a newly struck note restarts the settle time, just like starting a capture does
+        if self.settle_from.is_none() || (self.auto_measure && new_note) {
+            self.settle_from = Some(now);
+        }
+        if self.settle_from.is_some_and(|from| now.saturating_duration_since(from) < self.config.settle_time) {
+            // Still inside the attack: don't let these frames into the stability buffer
+            self.buffer.clear();
+            return None;
+        }
+
+        self.buffer.push_back(result.clone());
+
+        // Keep the shortest run of frames that still spans the stability window
+        let frame_time = |frame: &AnalysisResult| frame.timestamp.unwrap_or(now);
+        let window = self.config.stability_window;
+        while self.buffer.get(1).is_some_and(|f| now.saturating_duration_since(frame_time(f)) >= window) {
+            self.buffer.pop_front();
+        }
+        let covers_window = self.buffer
+            .front()
+            .is_some_and(|f| now.saturating_duration_since(frame_time(f)) >= window);
+        if !covers_window {
+            return None;
+        }
+
+        // In auto-measure mode the note that was just captured is usually
+        // still ringing, so only a different stable note triggers a capture.
+        let repeats_last_capture = self.auto_measure
+            && self.last_capture_key.is_some()
+            && self.buffer[0]
+                .note_name
+                .as_deref()
+                .and_then(|name| tuning::key_index_from_name_in(name, OctaveConvention::Scientific))
+                == self.last_capture_key;
+        if repeats_last_capture || !capture_processing::check_stability(&self.buffer, &self.config) {
+            return None; // Keep sliding the window until a (new) note settles
+        }
+
+        self.capturing = false;
+        let frames: Vec<AnalysisResult> = self.buffer.drain(..).collect();
+        let outcome = capture_processing::process(frames, operation, &self.config);
+        if let (true, Ok(measurement)) = (self.auto_measure, &outcome) {
+            self.last_capture_key = Some(measurement.key_index);
+        }
+        Some(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::{self, AnalysisConfig}, audio::BUFFER_SIZE, demo};
+    use std::time::Duration;
+
+    /// Time between the frames pushed.
+    const FRAME_INTERVAL: Duration = Duration::from_millis(40);
+
+    /// Pushes identical stable frames of A2 until a capture happens and
+    /// returns how many frames after the first it took.
+    fn frames_until_capture(config: CaptureConfig) -> usize {
+        let samples = demo::synthesize_note(24, 48000);
+        let mut frame = analysis::analyze_frame(&samples[BUFFER_SIZE..2 * BUFFER_SIZE], 48000, &AnalysisConfig::default());
+        frame.spectral_flux = Some(0.0);
+        let start = Instant::now();
+
+        let mut session = CaptureSession::new(config);
+        session.start();
+        for i in 0..100 {
+            frame.timestamp = Some(start + FRAME_INTERVAL * i as u32);
+            if let Some(outcome) = session.push(&frame, ProcessingOperation::BestConfidence) {
+                assert_eq!(outcome.map(|m| m.key_index), Ok(24));
+                return i;
+            }
+        }
+        panic!("no capture within 100 frames");
+    }
+
+    #[test]
+    fn capture_waits_for_configured_stability_window() {
+        let config = |stability_window| CaptureConfig { settle_time: Duration::ZERO, stability_window, ..CaptureConfig::default() };
+        assert_eq!(frames_until_capture(config(Duration::from_millis(200))), 5);
+        assert_eq!(frames_until_capture(config(Duration::from_millis(400))), 10);
+        assert_eq!(frames_until_capture(config(capture_processing::DEFAULT_STABILITY_WINDOW)), 22);
+    }
+
+    #[test]
+    fn settle_time_delays_the_stability_window() {
+        let config = CaptureConfig {
+            settle_time: Duration::from_millis(120),
+            stability_window: Duration::from_millis(200),
+            ..CaptureConfig::default()
+        };
+        assert_eq!(frames_until_capture(config), 3 + 5);
+    }
+}
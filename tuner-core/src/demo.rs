@@ -0,0 +1,126 @@
+//! # Demo Signal Module
+//!
+//! Stands in for the microphone when there is no piano (or no working
+//! input) at hand, e.g. for screenshots, demos and UI development. A thread
+//! plays a slow walk up the keyboard of synthesized, slightly mistuned
+//! piano-like notes and sends it in analysis frames exactly as
+//! `audio::start_audio_capture` does, so every frame still goes through
+//! the real analysis pipeline.
+//!
+//! ## Features
+//! - Notes stepping up the keyboard, each with its own small mistuning
+//! - Partials stretched by a preset 'B' curve, so the stretch is measurable
+//! - Decaying envelope, so energy trends and captures behave as with a real note
+//! - Frames delivered in real time at the configured hop size
+
+use anyhow::Result;
+use crossbeam_channel::{Sender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::audio::{self, AudioConfig, ChannelMode, InputInfo};
+use crate::inharmonicity::{self, PianoType};
+use crate::tuning;
+
+/// Length of each demo note in milliseconds.
+const NOTE_MS: u64 = 3000;
+
+/// Semitones between consecutive demo notes.
+const KEY_STEP: usize = 5;
+
+/// Most partials synthesized per note (fewer for treble notes, below Nyquist).
+const PARTIALS: u32 = 12;
+
+/// Time constant of the exponential decay of each note, in seconds.
+const DECAY_SECONDS: f32 = 1.5;
+
+/// Piano whose 'B' curve stretches the demo partials.
+const PIANO: PianoType = PianoType::BabyGrand;
+
+/// Synthesizes one demo note from stretched sine partials.
+///
+/// Each partial is a `audio::synthesize_tone` weighted by `1 / n`, so the
+/// sum keeps the test tone's headroom; the whole note then decays.
+///
+/// # Arguments
+/// * `key_index` - Piano key index (0-87)
+/// * `sample_rate` - Sample rate in Hz
+///
+/// # Returns
+/// * `Vec<f32>` - `NOTE_MS` of mono samples
+pub fn synthesize_note(key_index: u8, sample_rate: u32) -> Vec<f32> {
+    let (_, target) = tuning::find_nearest_note_by_index(key_index);
+    // A repeatable mistuning of -10..=+10 cents, different for neighbouring notes
+    let detune_cents = ((key_index as u32 * 7) % 21) as f32 - 10.0;
+    let f1 = target * 2.0_f32.powf(detune_cents / 1200.0);
+    let b = PIANO.preset_b(key_index);
+
+    let partials: Vec<(f32, f32)> = (1..=PARTIALS)
+        .map(|n| (inharmonicity::partial_frequency(f1, n, b), 1.0 / n as f32))
+        .take_while(|&(freq, _)| freq < sample_rate as f32 / 2.0)
+        .collect();
+    let total_weight: f32 = partials.iter().map(|&(_, weight)| weight).sum();
+
+    let mut note = vec![0.0; (sample_rate as u64 * NOTE_MS / 1000) as usize];
+    for (freq, weight) in partials {
+        let tone = audio::synthesize_tone(freq, sample_rate, NOTE_MS);
+        for (sample, t) in note.iter_mut().zip(tone) {
+            *sample += t * weight / total_weight;
+        }
+    }
+    for (i, sample) in note.iter_mut().enumerate() {
+        *sample *= (-(i as f32) / sample_rate as f32 / DECAY_SECONDS).exp();
+    }
+    note
+}
+
+/// Starts sending the demo signal in place of an input stream.
+///
+/// The thread runs until `sender` is disconnected, i.e. until the receiving
+/// audio thread exits.
+///
+/// # Arguments
+/// * `sender` - Channel sender for streaming audio data to the analysis thread
+/// * `audio_config` - Sample rate, frame size and hop size; the device settings are ignored
+///
+/// # Returns
+/// * `Ok((handle, info))` - Handle to the generator thread and the signal it sends
+/// * `Err(e)` - The configured sample rate is out of range
+pub fn start_demo_signal(sender: Sender<Vec<f32>>, audio_config: &AudioConfig) -> Result<(JoinHandle<()>, InputInfo)> {
+    let sample_rate = audio_config.sample_rate;
+    audio::validate_sample_rate(sample_rate)?;
+    let (frame_size, hop_size) = audio_config.frame_and_hop();
+    let hop_duration = Duration::from_secs_f64(hop_size as f64 / sample_rate as f64);
+
+    let handle = thread::spawn(move || {
+        let mut frame: Vec<f32> = vec![0.0; frame_size];
+        let mut next_frame_at = Instant::now();
+        for key_index in (0..88u8).step_by(KEY_STEP).cycle() {
+            let note = synthesize_note(key_index, sample_rate);
+            for hop in note.chunks(hop_size) {
+                // Slide the frame along by one hop, as the capture callback does
+                frame.drain(..hop.len());
+                frame.extend_from_slice(hop);
+
+                // Pace the frames in real time, without drifting
+                next_frame_at += hop_duration;
+                if let Some(wait) = next_frame_at.checked_duration_since(Instant::now()) {
+                    thread::sleep(wait);
+                }
+                // Like the capture callback, drop a frame the analysis has no room for
+                if let Err(TrySendError::Disconnected(_)) = sender.try_send(frame.clone()) {
+                    return;
+                }
+            }
+        }
+    });
+
+    let info = InputInfo {
+        device_name: "Demo signal".to_string(),
+        sample_rate,
+        channels: 1,
+        channel_mode: ChannelMode::Mono,
+        frame_size,
+        hop_size,
+    };
+    Ok((handle, info))
+}
@@ -8,10 +8,14 @@
 //! - High-performance FFT using RustFFT
 //! - Hann windowing for reduced spectral leakage
 //! - DC offset removal for accurate analysis
+//! - Bin/frequency conversion helpers
 //! - Optimized for real-time processing
 
+use anyhow::{Result, anyhow};
 use rustfft::{num_complex::Complex, FftPlanner};
-use crate::audio::BUFFER_SIZE;
+
+/// Shortest frame `perform_fft` accepts; shorter ones have too few bins to find a peak in.
+pub const MIN_FFT_SIZE: usize = 64;
 
 /// Removes the DC offset from a signal by making its average value zero.
 /// 
@@ -59,16 +63,19 @@ fn apply_hann_window(buffer: &mut [f32]) {
 /// 3. Forward FFT transformation
 /// 
 /// # Arguments
-/// * `signal` - Input audio signal (must be exactly BUFFER_SIZE samples)
+/// * `signal` - Input audio signal; the FFT size is its length, so the
+///   spectrum has one bin per sample and `spectrum_to_magnitudes` half as many
 /// 
 /// # Returns
-/// * `Vec<Complex<f32>>` - Complex frequency spectrum
-/// 
-/// # Panics
-/// * If signal length is not equal to BUFFER_SIZE
-pub fn perform_fft(signal: &[f32]) -> Vec<Complex<f32>> {
-    if signal.len() != BUFFER_SIZE {
-        panic!("Input frame size must be equal to BUFFER_SIZE");
+/// * `Ok(spectrum)` - Complex frequency spectrum
+/// * `Err(e)` - The signal is shorter than `MIN_FFT_SIZE` samples
+pub fn perform_fft(signal: &[f32]) -> Result<Vec<Complex<f32>>> {
+    if signal.len() < MIN_FFT_SIZE {
+        return Err(anyhow!(
+            "Frame of {} samples can't be analyzed; need at least {}",
+            signal.len(),
+            MIN_FFT_SIZE
+        ));
     }
 
     let mut processed_signal = signal.to_vec();
@@ -76,7 +83,7 @@ pub fn perform_fft(signal: &[f32]) -> Vec<Complex<f32>> {
     apply_hann_window(&mut processed_signal);
 
     let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(BUFFER_SIZE);
+    let fft = planner.plan_fft_forward(signal.len());
 
     let mut buffer: Vec<Complex<f32>> = processed_signal
         .into_iter()
@@ -84,7 +91,7 @@ pub fn perform_fft(signal: &[f32]) -> Vec<Complex<f32>> {
         .collect();
 
     fft.process(&mut buffer);
-    buffer
+    Ok(buffer)
 }
 
 /// Calculates the magnitude vector from a complex spectrum for spectrogram display.
@@ -101,7 +108,120 @@ pub fn perform_fft(signal: &[f32]) -> Vec<Complex<f32>> {
 pub fn spectrum_to_magnitudes(spectrum: &[Complex<f32>]) -> Vec<f32> {
     spectrum
         .iter()
-        .take(BUFFER_SIZE / 2)
+        .take(spectrum.len() / 2)
         .map(|c| c.norm()) // .norm() is sqrt(re^2 + im^2)
         .collect()
-}
\ No newline at end of file
+}
+
+/// Calculates magnitudes only for the bins inside a frequency band.
+///
+/// The result has the same length as `spectrum_to_magnitudes` so bin indices
+/// keep their meaning, but bins outside the band are left at zero and are
+/// never computed.
+///
+/// # Arguments
+/// * `spectrum` - Complex frequency spectrum from FFT
+/// * `band` - Lowest and highest frequency to keep, in Hz
+/// * `sample_rate` - Sample rate in Hz
+///
+/// # Returns
+/// * `Vec<f32>` - Magnitude spectrum, zero outside the band
+pub fn spectrum_to_magnitudes_in_band(spectrum: &[Complex<f32>], band: (f32, f32), sample_rate: u32) -> Vec<f32> {
+    let fft_size = spectrum.len();
+    let bins = fft_size / 2;
+    let start = (frequency_to_bin(band.0, sample_rate, fft_size).floor().max(0.0) as usize).min(bins);
+    let end = (frequency_to_bin(band.1, sample_rate, fft_size).ceil().max(0.0) as usize + 1).min(bins).max(start);
+
+    let mut magnitudes = vec![0.0; bins];
+    for (magnitude, c) in magnitudes[start..end].iter_mut().zip(&spectrum[start..end]) {
+        *magnitude = c.norm();
+    }
+    magnitudes
+}
+
+/// Returns the width of one FFT bin in Hz.
+///
+/// # Arguments
+/// * `sample_rate` - Sample rate in Hz
+/// * `fft_size` - Number of samples the FFT was computed over (not the number of magnitude bins)
+pub fn bin_resolution(sample_rate: u32, fft_size: usize) -> f32 {
+    sample_rate as f32 / fft_size as f32
+}
+
+/// Converts an FFT bin index to the frequency at the center of that bin.
+///
+/// # Arguments
+/// * `bin` - Bin index (0 = DC)
+/// * `sample_rate` - Sample rate in Hz
+/// * `fft_size` - Number of samples the FFT was computed over (not the number of magnitude bins)
+///
+/// # Returns
+/// * Frequency in Hz
+pub fn bin_to_frequency(bin: usize, sample_rate: u32, fft_size: usize) -> f32 {
+    bin as f32 * bin_resolution(sample_rate, fft_size)
+}
+
+/// Converts a frequency to a fractional FFT bin position.
+///
+/// This is the inverse of `bin_to_frequency`. The result is left fractional so
+/// callers can choose how to round it (nearest bin, window edges, etc.).
+///
+/// # Arguments
+/// * `frequency` - Frequency in Hz
+/// * `sample_rate` - Sample rate in Hz
+/// * `fft_size` - Number of samples the FFT was computed over (not the number of magnitude bins)
+///
+/// # Returns
+/// * Fractional bin position
+pub fn frequency_to_bin(frequency: f32, sample_rate: u32, fft_size: usize) -> f32 {
+    frequency / bin_resolution(sample_rate, fft_size)
+}
+
+/// Returns how many cents one FFT bin spans at a given frequency.
+///
+/// Bins are evenly spaced in Hz, so their width in cents grows towards the
+/// bass. This shows how much the spectrum alone can resolve a note before
+/// peak interpolation.
+///
+/// # Arguments
+/// * `frequency` - Frequency in Hz at which to measure the bin width
+/// * `sample_rate` - Sample rate in Hz
+/// * `fft_size` - Number of samples the FFT was computed over
+///
+/// # Returns
+/// * Width of one bin in cents (infinite at or below 0 Hz)
+pub fn cents_per_bin(frequency: f32, sample_rate: u32, fft_size: usize) -> f32 {
+    if frequency <= 0.0 {
+        return f32::INFINITY;
+    }
+    1200.0 * ((frequency + bin_resolution(sample_rate, fft_size)) / frequency).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bin_round_trips_through_frequency() {
+        for &(sample_rate, fft_size) in &[(44100, 2048), (48000, 2048), (48000, 4096), (96000, 16384)] {
+            for bin in [0, 1, 17, fft_size / 4, fft_size / 2] {
+                let freq = bin_to_frequency(bin, sample_rate, fft_size);
+                let back = frequency_to_bin(freq, sample_rate, fft_size);
+                assert!((back - bin as f32).abs() < 1e-3, "{sample_rate} Hz, {fft_size}: bin {bin} -> {freq} Hz -> {back}");
+            }
+        }
+    }
+
+    #[test]
+    fn frequency_round_trips_through_fractional_bin() {
+        for &(sample_rate, fft_size) in &[(44100, 2048), (48000, 8192)] {
+            for freq in [27.5, 261.63, 440.0, 4186.0] {
+                let bin = frequency_to_bin(freq, sample_rate, fft_size);
+                let back = bin * bin_resolution(sample_rate, fft_size);
+                assert!((back - freq).abs() < 1e-3, "{sample_rate} Hz, {fft_size}: {freq} Hz -> bin {bin} -> {back} Hz");
+            }
+        }
+        // Nyquist lands on the last bin of the half spectrum
+        assert_eq!(frequency_to_bin(24000.0, 48000, 2048), 1024.0);
+    }
+}
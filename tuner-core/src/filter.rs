@@ -0,0 +1,84 @@
+//! # Filter Module
+//!
+//! This module provides time-domain filters used to condition audio frames
+//! before pitch detection.
+//!
+//! ## Features
+//! - Biquad band-pass filter (RBJ cookbook, constant 0 dB peak gain)
+//! - Zero-phase (forward-backward) filtering of a single frame
+
+/// Coefficients of a normalized biquad filter section.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    /// Designs a band-pass biquad with a constant 0 dB peak gain.
+    ///
+    /// # Arguments
+    /// * `center_freq` - Center frequency of the pass band in Hz
+    /// * `q` - Quality factor (center frequency divided by bandwidth)
+    /// * `sample_rate` - Sample rate in Hz
+    fn bandpass(center_freq: f32, q: f32, sample_rate: u32) -> Self {
+        let w0 = 2.0 * std::f32::consts::PI * center_freq / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * q);
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: alpha / a0,
+            b1: 0.0,
+            b2: -alpha / a0,
+            a1: -2.0 * w0.cos() / a0,
+            a2: (1.0 - alpha) / a0,
+        }
+    }
+
+    /// Runs the filter over a buffer in place (Direct Form I).
+    fn process(&self, buffer: &mut [f32]) {
+        let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+        for sample in buffer.iter_mut() {
+            let x0 = *sample;
+            let y0 = self.b0 * x0 + self.b1 * x1 + self.b2 * x2 - self.a1 * y1 - self.a2 * y2;
+            x2 = x1;
+            x1 = x0;
+            y2 = y1;
+            y1 = y0;
+            *sample = y0;
+        }
+    }
+}
+
+/// Band-pass filters a frame around a center frequency.
+///
+/// The filter is applied forward and then backward over the frame, which
+/// cancels its phase shift so the waveform period seen by the pitch detector
+/// is not distorted. Frequencies at or above Nyquist leave the frame unchanged.
+///
+/// # Arguments
+/// * `signal` - Input audio frame
+/// * `sample_rate` - Sample rate in Hz
+/// * `center_freq` - Center of the pass band in Hz (e.g. the expected fundamental)
+/// * `q` - Quality factor; higher values give a narrower pass band
+///
+/// # Returns
+/// * `Vec<f32>` - The filtered frame, the same length as the input
+pub fn bandpass_filter(signal: &[f32], sample_rate: u32, center_freq: f32, q: f32) -> Vec<f32> {
+    let mut filtered = signal.to_vec();
+    if center_freq <= 0.0 || q <= 0.0 || center_freq >= sample_rate as f32 / 2.0 {
+        return filtered;
+    }
+
+    let biquad = Biquad::bandpass(center_freq, q, sample_rate);
+
+    biquad.process(&mut filtered);
+    filtered.reverse();
+    biquad.process(&mut filtered);
+    filtered.reverse();
+
+    filtered
+}
@@ -1,7 +1,22 @@
 use serde::{Serialize, Deserialize};
+use std::cell::Cell;
 use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use anyhow::Result;
 use linreg::linear_regression;
+use crate::capture_processing::EnvelopeMeasurement;
+use crate::tuning::{self, StretchStrategy};
 
+/// Reliability below which a measurement is flagged as untrustworthy and
+/// left out of the interpolation between measured keys.
+pub const MIN_RELIABILITY: f32 = 0.3;
+
+/// Usable partials, fundamental included, at which a measurement gets full
+/// credit for coverage; fewer scale its reliability down.
+const RELIABLE_PARTIAL_COUNT: usize = 5;
+
+/// Fit quality credited to a two-partial estimate, which has no regression to judge.
+const TWO_PARTIAL_FIT: f32 = 0.6;
 
 /// Represents a single measured partial of a note.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,29 +31,742 @@ pub struct KeyMeasurement {
     pub key_index: u8,   // The piano key index (0-87)
     pub partials: Vec<Partial>,
     pub calculated_b: Option<f32>, // Store the B value after calculation
+    #[serde(default)]
+    pub fit_r_squared: Option<f32>, // How well the B regression fits the partials (1.0 = exactly)
+    #[serde(default)]
+    pub envelope: Option<EnvelopeMeasurement>, // Partial amplitude decay, if recorded
+    #[serde(default)]
+    pub low_confidence: bool, // 'B' was solved from just two partials instead of fitted
+    #[serde(default)]
+    pub confidence: Option<f32>, // Pitch confidence of the frame the partials were taken from
+    #[serde(default)]
+    pub reliability: Option<f32>, // Overall trust in 'B' (0-1), from confidence, partial count and fit
 }
 
 /// Represents the complete inharmonicity profile for a specific piano.
 /// This is the top-level object you will save to and load from a file.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+///
+/// The stretch curve derived from the measurements is cached, since the
+/// cent meter needs it for every frame. Change `measurements` through
+/// [`insert_measurement`](Self::insert_measurement) and
+/// [`remove_measurement`](Self::remove_measurement), or call
+/// [`invalidate_targets`](Self::invalidate_targets) after editing it directly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InharmonicityProfile {
+    // Format version the profile was written in; missing in files from before versioning
+    #[serde(default)]
+    pub version: u32,
     // A BTreeMap is great here because it keeps the keys sorted automatically.
     // It maps a key_index (u8) to its measurement data.
     pub measurements: BTreeMap<u8, KeyMeasurement>,
+    // Stretch curve for the preset it was built with, rebuilt lazily
+    #[serde(skip)]
+    pub(crate) stretch_cache: Cell<Option<(Option<PianoType>, [f32; 88])>>,
+}
+
+impl Default for InharmonicityProfile {
+    /// An empty profile in the current format.
+    fn default() -> Self {
+        Self {
+            version: ProfileVersion::CURRENT as u32,
+            measurements: BTreeMap::new(),
+            stretch_cache: Cell::new(None),
+        }
+    }
+}
+
+/// Versions of the profile file format, oldest first.
+///
+/// Loading a profile upgrades it step by step to `CURRENT`, so each new
+/// version only needs a migration from the one before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProfileVersion {
+    /// Written before profiles were versioned: no `version` field, and
+    /// measurements may lack `fit_r_squared`
+    V0 = 0,
+    /// Carries its version; every measurement with a 'B' has its fit quality
+    V1 = 1,
+    /// Every measurement with a 'B' has its reliability
+    V2 = 2,
+}
+
+impl ProfileVersion {
+    /// Version profiles are written in.
+    pub const CURRENT: ProfileVersion = ProfileVersion::V2;
+
+    /// Returns the version with the given number, if this build knows it.
+    pub fn from_number(number: u32) -> Option<Self> {
+        match number {
+            0 => Some(ProfileVersion::V0),
+            1 => Some(ProfileVersion::V1),
+            2 => Some(ProfileVersion::V2),
+            _ => None,
+        }
+    }
+}
+
+/// Named profiles kept in one file, e.g. one per piano a technician services.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileLibrary {
+    // Sorted by name, so the library lists pianos alphabetically
+    pub profiles: BTreeMap<String, InharmonicityProfile>,
+}
+
+/// Why a profile or profile library could not be saved or loaded.
+#[derive(Debug)]
+pub enum ProfileError {
+    /// The file could not be read or written (missing, no permission, ...)
+    Io(std::io::Error),
+    /// The profile could not be turned into JSON
+    Serialization(serde_json::Error),
+    /// The file is not valid JSON or not shaped like a profile
+    Deserialization(serde_json::Error),
+    /// The file was written by a newer version of the profile format
+    VersionMismatch { found: u32, supported: u32 },
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => write!(f, "file not found"),
+            ProfileError::Io(e) => write!(f, "file could not be accessed: {}", e),
+            ProfileError::Serialization(e) => write!(f, "profile could not be written as JSON: {}", e),
+            ProfileError::Deserialization(e) => write!(f, "file is not a valid profile ({})", e),
+            ProfileError::VersionMismatch { found, supported } => write!(
+                f,
+                "profile format version {} is newer than this version supports ({}); update the tuner",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProfileError::Io(e) => Some(e),
+            ProfileError::Serialization(e) | ProfileError::Deserialization(e) => Some(e),
+            ProfileError::VersionMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ProfileError {
+    fn from(e: std::io::Error) -> Self {
+        ProfileError::Io(e)
+    }
+}
+
+/// Writes any profile type as pretty-printed JSON.
+fn save_json<T: Serialize>(value: &T, path: &str) -> Result<(), ProfileError> {
+    let json = serde_json::to_string_pretty(value).map_err(ProfileError::Serialization)?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+/// Reads any profile type from a JSON file.
+fn load_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T, ProfileError> {
+    let json = std::fs::read_to_string(path)?;
+    serde_json::from_str(&json).map_err(ProfileError::Deserialization)
+}
+
+impl ProfileLibrary {
+    /// Writes the library as pretty-printed JSON.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the `.json` file to create
+    pub fn save(&self, path: &str) -> Result<(), ProfileError> {
+        save_json(self, path)
+    }
+
+    /// Reads a library written by [`ProfileLibrary::save`], upgrading every
+    /// profile in it to the current format.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the `.json` file to read
+    pub fn load(path: &str) -> Result<Self, ProfileError> {
+        let json: serde_json::Value = load_json(path)?;
+        let profiles = match json.get("profiles").and_then(|p| p.as_object()) {
+            Some(profiles) => profiles
+                .iter()
+                .map(|(name, profile)| Ok((name.clone(), InharmonicityProfile::from_json(profile.clone())?)))
+                .collect::<Result<_, ProfileError>>()?,
+            // Let serde explain what is missing
+            None => serde_json::from_value::<ProfileLibrary>(json).map_err(ProfileError::Deserialization)?.profiles,
+        };
+        Ok(ProfileLibrary { profiles })
+    }
+}
+
+/// Piano size used to estimate inharmonicity before anything is measured.
+///
+/// Shorter strings are stiffer relative to their length, so smaller pianos
+/// have a higher 'B' across the whole scale, most noticeably in the bass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PianoType {
+    /// Concert grand, around 9'
+    ConcertGrand,
+    /// Baby grand, around 5'
+    BabyGrand,
+    /// Spinet or small upright
+    Spinet,
 }
 
+impl PianoType {
+    /// All presets, in the order they are offered in a UI.
+    pub const ALL: [PianoType; 3] = [PianoType::ConcertGrand, PianoType::BabyGrand, PianoType::Spinet];
+
+    /// Returns a human-readable name for the preset.
+    pub fn label(self) -> &'static str {
+        match self {
+            PianoType::ConcertGrand => "Concert grand",
+            PianoType::BabyGrand => "Baby grand",
+            PianoType::Spinet => "Spinet",
+        }
+    }
+
+    /// Typical (key index, 'B') points of the preset curve.
+    ///
+    /// 'B' is lowest around the bass/tenor break and rises towards both ends:
+    /// slowly into the wound bass strings, exponentially into the treble.
+    fn curve_points(self) -> [(u8, f32); 4] {
+        match self {
+            PianoType::ConcertGrand => [(0, 2.0e-4), (24, 6.0e-5), (48, 3.5e-4), (87, 1.0e-2)],
+            PianoType::BabyGrand => [(0, 5.0e-4), (24, 1.2e-4), (48, 6.0e-4), (87, 1.5e-2)],
+            PianoType::Spinet => [(0, 1.2e-3), (24, 2.5e-4), (48, 1.0e-3), (87, 2.0e-2)],
+        }
+    }
+
+    /// Returns the preset 'B' value for a key, interpolated in the log domain.
+    ///
+    /// # Arguments
+    /// * `key_index` - Piano key index (0-87)
+    pub fn preset_b(self, key_index: u8) -> f32 {
+        let points = self.curve_points();
+        let key_index = key_index.min(87);
+        let segment = points
+            .windows(2)
+            .find(|pair| key_index <= pair[1].0)
+            .unwrap_or(&points[points.len() - 2..]);
+        let ((low_key, low_b), (high_key, high_b)) = (segment[0], segment[1]);
+        let t = (key_index - low_key) as f32 / (high_key - low_key) as f32;
+        (low_b.ln() + t * (high_b.ln() - low_b.ln())).exp()
+    }
+}
+
+/// Calculates the frequency of partial `n` of a stiff string.
+///
+/// Uses the standard inharmonicity model `f_n = n * f0 * sqrt(1 + B * n^2)`,
+/// expressed relative to the first partial `f1` (what a tuner actually
+/// measures as the note's pitch) rather than the theoretical `f0`.
+///
+/// # Arguments
+/// * `f1` - Frequency of the first partial (the fundamental) in Hz
+/// * `n` - Partial number (1 = fundamental)
+/// * `b` - Inharmonicity constant of the string
+pub fn partial_frequency(f1: f32, n: u32, b: f32) -> f32 {
+    let n = n as f32;
+    n * f1 * ((1.0 + b * n * n) / (1.0 + b)).sqrt()
+}
+
+/// Calculates the first partial of a stiff string from its partial `n`.
+///
+/// The inverse of [`partial_frequency`], for notes whose fundamental is too
+/// weak to measure while an overtone is strong.
+///
+/// # Arguments
+/// * `partial_freq` - Measured frequency of partial `n` in Hz
+/// * `n` - Partial number (1 = fundamental)
+/// * `b` - Inharmonicity constant of the string
+pub fn fundamental_from_partial(partial_freq: f32, n: u32, b: f32) -> f32 {
+    partial_freq / partial_frequency(1.0, n, b)
+}
+
+impl InharmonicityProfile {
+    /// Writes the profile (every measured partial and 'B' value) as pretty-printed JSON.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the `.json` file to create (e.g., "tuning_profile.json")
+    ///
+    /// # Returns
+    /// * `Ok(())` - Profile saved successfully
+    /// * `Err(ProfileError)` - The file could not be written, or the profile encoded
+    pub fn save(&self, path: &str) -> Result<(), ProfileError> {
+        save_json(self, path)
+    }
+
+    /// Reads a profile written by [`InharmonicityProfile::save`].
+    ///
+    /// Profiles written in an older format are upgraded to the current one
+    /// (see [`ProfileVersion`]); saving them again writes the current format.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the `.json` file to read (e.g., "tuning_profile.json")
+    ///
+    /// # Returns
+    /// * `Ok(InharmonicityProfile)` - Successfully loaded profile
+    /// * `Err(ProfileError)` - The file is missing or unreadable, not a valid
+    ///   profile, or written by a newer version of the format
+    pub fn load(path: &str) -> Result<Self, ProfileError> {
+        Self::from_json(load_json(path)?)
+    }
+
+    /// Builds a profile from parsed JSON in any known format version.
+    ///
+    /// The version is checked before the rest is parsed, so a newer file is
+    /// reported as such rather than as malformed. A missing version means V0;
+    /// one that isn't a whole number that fits a `u32` is rejected as malformed.
+    fn from_json(json: serde_json::Value) -> Result<Self, ProfileError> {
+        let number = match json.get("version") {
+            None => 0,
+            Some(value) => value
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .ok_or_else(|| {
+                    ProfileError::Deserialization(serde::de::Error::custom(format!("invalid version {}", value)))
+                })?,
+        };
+        let version = ProfileVersion::from_number(number).ok_or(ProfileError::VersionMismatch {
+            found: number,
+            supported: ProfileVersion::CURRENT as u32,
+        })?;
+        let mut profile: Self = serde_json::from_value(json).map_err(ProfileError::Deserialization)?;
+        profile.migrate(version);
+        Ok(profile)
+    }
+
+    /// Upgrades a profile read in an older format version to the current one.
+    fn migrate(&mut self, from: ProfileVersion) {
+        if from < ProfileVersion::V1 {
+            // V0 measurements predate the fit quality; the same partials give the same 'B'
+            for measurement in self.measurements.values_mut() {
+                if measurement.calculated_b.is_some() && measurement.fit_r_squared.is_none() {
+                    measurement.calculate_b_value();
+                }
+            }
+            self.invalidate_targets();
+        }
+        if from < ProfileVersion::V2 {
+            // Captured before reliability was scored; the capture confidence is lost, so it counts as full
+            for measurement in self.measurements.values_mut() {
+                if measurement.calculated_b.is_some() && measurement.reliability.is_none() {
+                    measurement.calculate_b_value();
+                }
+            }
+            self.invalidate_targets();
+        }
+        self.version = ProfileVersion::CURRENT as u32;
+    }
+
+    /// Returns the 'B' value for any key, interpolating between measured keys.
+    ///
+    /// 'B' grows roughly exponentially along the scale of a piano, so values
+    /// are interpolated linearly in the log domain. Keys outside the measured
+    /// range take the 'B' of the nearest measured key. Measurements below
+    /// [`MIN_RELIABILITY`] are skipped, so one bad key doesn't bend the curve
+    /// for its neighbours.
+    ///
+    /// # Arguments
+    /// * `key_index` - Piano key index (0-87)
+    ///
+    /// # Returns
+    /// * `Some(b)` - Measured or interpolated 'B' value
+    /// * `None` - The profile has no measurement with a positive 'B' value
+    pub fn interpolated_b(&self, key_index: u8) -> Option<f32> {
+        let measured: Vec<(u8, f32)> = self.measurements
+            .values()
+            .filter(|m| m.is_reliable())
+            .filter_map(|m| m.calculated_b.filter(|&b| b > 0.0).map(|b| (m.key_index, b)))
+            .collect();
+
+        let below = measured.iter().rev().find(|(key, _)| *key <= key_index);
+        let above = measured.iter().find(|(key, _)| *key >= key_index);
+
+        match (below, above) {
+            (Some(&(low_key, low_b)), Some(&(high_key, high_b))) => {
+                if low_key == high_key {
+                    return Some(low_b);
+                }
+                let t = (key_index - low_key) as f32 / (high_key - low_key) as f32;
+                Some((low_b.ln() + t * (high_b.ln() - low_b.ln())).exp())
+            }
+            (Some(&(_, b)), None) | (None, Some(&(_, b))) => Some(b),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the 'B' value to use for a key, preferring real measurements.
+    ///
+    /// A key's own measurement always wins. Otherwise the preset curve is used
+    /// when one is given, and the value interpolated from measured keys when not.
+    ///
+    /// # Arguments
+    /// * `key_index` - Piano key index (0-87)
+    /// * `preset` - Piano size to fall back on for unmeasured keys
+    ///
+    /// # Returns
+    /// * `Some(b)` - Measured, preset or interpolated 'B' value
+    /// * `None` - No measurement, no preset and nothing to interpolate from
+    pub fn b_for_key(&self, key_index: u8, preset: Option<PianoType>) -> Option<f32> {
+        let measured = self.measurements
+            .get(&key_index)
+            .and_then(|m| m.calculated_b)
+            .filter(|&b| b > 0.0);
+        measured
+            .or_else(|| preset.map(|p| p.preset_b(key_index)))
+            .or_else(|| self.interpolated_b(key_index))
+    }
+
+    /// Stores a measurement, replacing any earlier one of the same key.
+    ///
+    /// # Returns
+    /// * `Option<KeyMeasurement>` - The measurement it replaced, if any
+    pub fn insert_measurement(&mut self, measurement: KeyMeasurement) -> Option<KeyMeasurement> {
+        self.invalidate_targets();
+        self.measurements.insert(measurement.key_index, measurement)
+    }
+
+    /// Removes the measurement of a key.
+    ///
+    /// # Returns
+    /// * `Option<KeyMeasurement>` - The measurement removed, if the key had one
+    pub fn remove_measurement(&mut self, key_index: u8) -> Option<KeyMeasurement> {
+        self.invalidate_targets();
+        self.measurements.remove(&key_index)
+    }
+
+    /// Discards the cached stretch curve so it is rebuilt on next use.
+    pub fn invalidate_targets(&mut self) {
+        self.stretch_cache.set(None);
+    }
+
+    /// Returns the stretch of every key in cents, as from [`tuning::stretch_curve`]
+    /// with the default strategy.
+    ///
+    /// The curve is built on first use and kept until the measurements change
+    /// or a different preset is asked for.
+    ///
+    /// # Arguments
+    /// * `preset` - Piano size used for keys without a measurement
+    ///
+    /// # Returns
+    /// * `[f32; 88]` - Offset from equal temperament in cents for each key (0 = A0)
+    pub fn stretch_cents(&self, preset: Option<PianoType>) -> [f32; 88] {
+        match self.stretch_cache.get() {
+            Some((cached_preset, curve)) if cached_preset == preset => curve,
+            _ => {
+                let curve = tuning::stretch_curve(self, StretchStrategy::default(), preset);
+                self.stretch_cache.set(Some((preset, curve)));
+                curve
+            }
+        }
+    }
+
+    /// Recalculates 'B' and its fit quality for every stored measurement.
+    ///
+    /// Stored values are whatever was computed when the key was captured or
+    /// the profile was saved; this brings them in line with the current
+    /// partials and regression.
+    ///
+    /// # Returns
+    /// * `usize` - Number of measurements that now have a 'B' value
+    pub fn recalculate(&mut self) -> usize {
+        self.invalidate_targets();
+        self.measurements
+            .values_mut()
+            .filter_map(|measurement| measurement.calculate_b_value())
+            .count()
+    }
+
+    /// Finds the stored partial closest in pitch to a frequency.
+    ///
+    /// Every partial of every measurement is compared, so a ringing frequency
+    /// can be traced back to the string (and partial) most likely producing it.
+    ///
+    /// # Arguments
+    /// * `freq` - Frequency to match in Hz
+    ///
+    /// # Returns
+    /// * `Some((key_index, partial_number, cents_error))` - Closest partial; the
+    ///   error is positive when `freq` is above it
+    /// * `None` - `freq` is not positive or no partials are stored
+    pub fn nearest_partial(&self, freq: f32) -> Option<(u8, u32, f32)> {
+        if freq <= 0.0 {
+            return None;
+        }
+        self.measurements
+            .values()
+            .flat_map(|m| m.partials.iter().map(move |p| (m.key_index, p)))
+            .filter(|(_, p)| p.frequency > 0.0)
+            .map(|(key_index, p)| (key_index, p.number, 1200.0 * (freq / p.frequency).log2()))
+            .min_by(|a, b| a.2.abs().total_cmp(&b.2.abs()))
+    }
+}
+
+/// One key of the tuning that a profile produces.
+#[derive(Debug, Clone, Serialize)]
+pub struct TuningTableRow {
+    pub key_index: u8,            // The piano key index (0-87)
+    pub note_name: String,        // Scientific note name (e.g., "A4")
+    pub equal_tempered_hz: f32,   // Equal temperament frequency at A4 = 440 Hz
+    pub target_hz: f32,           // Inharmonicity-stretched target frequency
+    pub cents: f32,               // Stretch from equal temperament in cents
+}
+
+impl InharmonicityProfile {
+    /// Builds the tuning of all 88 keys from this profile's stretch curve.
+    ///
+    /// # Arguments
+    /// * `preset` - Piano size used for keys without a measurement
+    ///
+    /// # Returns
+    /// * `Vec<TuningTableRow>` - One row per key, from A0 to C8
+    pub fn tuning_table(&self, preset: Option<PianoType>) -> Vec<TuningTableRow> {
+        let curve = self.stretch_cents(preset);
+        (0..88u8)
+            .map(|key_index| {
+                let (note_name, equal_tempered_hz) = tuning::find_nearest_note_by_index(key_index);
+                let cents = curve[key_index as usize];
+                TuningTableRow {
+                    key_index,
+                    note_name,
+                    equal_tempered_hz,
+                    target_hz: equal_tempered_hz * 2.0_f32.powf(cents / 1200.0),
+                    cents,
+                }
+            })
+            .collect()
+    }
+}
+
+/// How one key changed between two profiles of the same piano.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyDiff {
+    pub key_index: u8,            // The piano key index (0-87)
+    pub note_name: String,        // Scientific note name (e.g., "A4")
+    pub b_before: Option<f32>,    // 'B' in the earlier profile
+    pub b_after: Option<f32>,     // 'B' in the later profile
+    pub target_cents: f32,        // Later stretched target minus earlier one, in cents
+}
+
+impl KeyDiff {
+    /// Change of 'B' between the profiles, if both have one.
+    pub fn b_change(&self) -> Option<f32> {
+        Some(self.b_after? - self.b_before?)
+    }
+}
+
+/// Compares two profiles of the same piano, e.g. from consecutive visits.
+///
+/// Only keys measured in both profiles are compared. Target differences come
+/// from each profile's own stretch curve, so they also reflect changes in
+/// neighbouring keys that shape the octaves.
+///
+/// # Arguments
+/// * `before` - Earlier profile
+/// * `after` - Later profile
+///
+/// # Returns
+/// * `Vec<KeyDiff>` - One entry per key present in both, from low to high
+pub fn profile_diff(before: &InharmonicityProfile, after: &InharmonicityProfile) -> Vec<KeyDiff> {
+    let curve_before = before.stretch_cents(None);
+    let curve_after = after.stretch_cents(None);
+    before
+        .measurements
+        .iter()
+        .filter_map(|(&key_index, earlier)| {
+            let later = after.measurements.get(&key_index)?;
+            let key = key_index as usize;
+            Some(KeyDiff {
+                key_index,
+                note_name: tuning::find_nearest_note_by_index(key_index).0,
+                b_before: earlier.calculated_b,
+                b_after: later.calculated_b,
+                target_cents: curve_after.get(key)? - curve_before.get(key)?,
+            })
+        })
+        .collect()
+}
+
+/// Writes a profile comparison as CSV with a header row.
+///
+/// Missing 'B' values are left empty.
+///
+/// # Arguments
+/// * `path` - Path of the `.csv` file to create
+/// * `diffs` - Compared keys, as returned by [`profile_diff`]
+pub fn save_profile_diff_csv(path: &str, diffs: &[KeyDiff]) -> Result<()> {
+    let optional = |value: Option<f32>| value.map(|v| format!("{:e}", v)).unwrap_or_default();
+    let mut contents = String::from("key_index,note,b_before,b_after,b_change,target_cents\n");
+    for diff in diffs {
+        writeln!(
+            contents,
+            "{},{},{},{},{},{:.2}",
+            diff.key_index,
+            diff.note_name,
+            optional(diff.b_before),
+            optional(diff.b_after),
+            optional(diff.b_change()),
+            diff.target_cents
+        )?;
+    }
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Writes a tuning table as CSV with a header row.
+///
+/// # Arguments
+/// * `path` - Path of the `.csv` file to create
+/// * `rows` - Table rows, as returned by [`InharmonicityProfile::tuning_table`]
+pub fn save_tuning_table_csv(path: &str, rows: &[TuningTableRow]) -> Result<()> {
+    let mut contents = String::from("key_index,note,equal_tempered_hz,target_hz,cents\n");
+    for row in rows {
+        writeln!(
+            contents,
+            "{},{},{:.3},{:.3},{:.2}",
+            row.key_index, row.note_name, row.equal_tempered_hz, row.target_hz, row.cents
+        )?;
+    }
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Writes a tuning table as a JSON array of rows.
+///
+/// # Arguments
+/// * `path` - Path of the `.json` file to create
+/// * `rows` - Table rows, as returned by [`InharmonicityProfile::tuning_table`]
+pub fn save_tuning_table_json(path: &str, rows: &[TuningTableRow]) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(rows)?)?;
+    Ok(())
+}
 
 impl KeyMeasurement {
+    /// Predicts the frequency of partial `n` from this key's measured
+    /// fundamental and calculated 'B' value.
+    ///
+    /// Returns `None` if the measurement has no fundamental or no 'B' value.
+    pub fn predict_partial(&self, n: u32) -> Option<f32> {
+        let b = self.calculated_b?;
+        let f1 = self.partials.iter().find(|p| p.number == 1)?.frequency;
+        Some(partial_frequency(f1, n, b))
+    }
+
+    /// Compares every measured partial with the pure harmonic series and with
+    /// the 'B' model, in cents.
+    ///
+    /// A partial that sits far from the model while its neighbours fit well
+    /// was most likely mis-detected. Without a 'B' value the model is the pure
+    /// harmonic series, so both deviations are equal.
+    ///
+    /// # Returns
+    /// * `Vec<(number, cents_from_pure, cents_from_model)>` - One entry per partial,
+    ///   in the order stored; empty if the fundamental was not measured
+    pub fn partial_deviations(&self) -> Vec<(u32, f32, f32)> {
+        let Some(f1) = self.partials.iter().find(|p| p.number == 1).map(|p| p.frequency) else {
+            return Vec::new();
+        };
+        let b = self.calculated_b.unwrap_or(0.0);
+        let cents = |freq: f32, reference: f32| 1200.0 * (freq / reference).log2();
+
+        self.partials
+            .iter()
+            .filter(|p| p.number > 0 && p.frequency > 0.0)
+            .map(|p| {
+                let pure = p.number as f32 * f1;
+                let model = partial_frequency(f1, p.number, b);
+                (p.number, cents(p.frequency, pure), cents(p.frequency, model))
+            })
+            .collect()
+    }
+
+    /// Estimates 'B' from each overtone on its own, relative to the fundamental.
+    ///
+    /// Solving the stiff-string formula for a single partial gives
+    /// `B = (r² - 1) / (n² - r²)` with `r = f_n / (n · f_1)`. The values should
+    /// agree along the partial series; one that stands out points to a
+    /// mis-detected partial. Overtones flatter than harmonic give no value.
+    ///
+    /// # Returns
+    /// * `Vec<(number, b)>` - One entry per overtone (n ≥ 2), in the order stored;
+    ///   empty if the fundamental was not measured
+    pub fn partial_b_values(&self) -> Vec<(u32, f32)> {
+        let Some(fundamental) = self.partials.iter().find(|p| p.number == 1) else {
+            return Vec::new();
+        };
+
+        self.partials
+            .iter()
+            .filter(|p| p.number > 1)
+            .filter_map(|p| estimate_b_from_two(fundamental, p).map(|b| (p.number, b)))
+            .collect()
+    }
+
     /// Calculates the inharmonicity constant 'B' for this key's measurements.
+    ///
+    /// Also stores the coefficient of determination of the regression in
+    /// `fit_r_squared`, and the resulting `reliability`. All are cleared when
+    /// 'B' can't be calculated, so calling this again after the partials
+    /// change never leaves stale values.
+    ///
+    /// With exactly two usable partials there is nothing to fit, so 'B' is
+    /// solved from them with [`estimate_b_from_two`] instead; the measurement
+    /// is then marked `low_confidence` and has no `fit_r_squared`.
     pub fn calculate_b_value(&mut self) -> Option<f32> {
-        if self.partials.len() < 3 {
-            return None; // Need at least 3 points for a meaningful regression
+        let b = self.fit_b_value();
+        self.reliability = b.map(|_| self.score_reliability());
+        b
+    }
+
+    /// Returns false if the measurement's reliability is below [`MIN_RELIABILITY`].
+    ///
+    /// Measurements without a score (no 'B' value) count as reliable; whether
+    /// they have a 'B' at all is up to the caller.
+    pub fn is_reliable(&self) -> bool {
+        self.reliability.is_none_or(|r| r >= MIN_RELIABILITY)
+    }
+
+    /// Combines capture confidence, partial count and fit quality into one 0-1 score.
+    ///
+    /// The three are multiplied, so any one of them being poor is enough to
+    /// make the measurement untrustworthy. A measurement without a recorded
+    /// confidence is not penalized for it.
+    fn score_reliability(&self) -> f32 {
+        let usable = self.partials.iter().filter(|p| p.number > 0 && p.frequency > 0.0).count();
+        let coverage = (usable as f32 / RELIABLE_PARTIAL_COUNT as f32).min(1.0);
+        let fit = match self.fit_r_squared {
+            Some(r2) => r2.clamp(0.0, 1.0),
+            None if self.low_confidence => TWO_PARTIAL_FIT,
+            None => 0.0,
+        };
+        let confidence = self.confidence.unwrap_or(1.0).clamp(0.0, 1.0);
+        confidence * coverage * fit
+    }
+
+    /// Fits 'B' (and `fit_r_squared`) as described for [`calculate_b_value`](Self::calculate_b_value).
+    fn fit_b_value(&mut self) -> Option<f32> {
+        self.calculated_b = None;
+        self.fit_r_squared = None;
+        self.low_confidence = false;
+        let usable: Vec<&Partial> = self.partials
+            .iter()
+            .filter(|p| p.number > 0 && p.frequency > 0.0)
+            .collect();
+        match usable[..] {
+            [first, second] => {
+                self.calculated_b = estimate_b_from_two(first, second);
+                self.low_confidence = self.calculated_b.is_some();
+                return self.calculated_b;
+            }
+            _ if usable.len() < 3 => return None, // Need at least 3 points for a meaningful regression
+            _ => {}
         }
         
         // Prepare the (x, y) data points for linear regression
         // x = n^2, y = (f_n / n)^2
-        let (xs, ys) : (Vec<f64>, Vec<f64>) = self.partials.iter()
-            .filter(|p| p.number > 0 && p.frequency > 0.0)
+        let (xs, ys) : (Vec<f64>, Vec<f64>) = usable
+            .iter()
             .map(|p| {
                 let n = p.number as f64;
                 let f_n = p.frequency as f64;
@@ -52,10 +780,144 @@ impl KeyMeasurement {
             if intercept.abs() > 1e-6 {
                 let b_value = slope / intercept;
                 self.calculated_b = Some(b_value as f32);
+                self.fit_r_squared = Some(r_squared(&xs, &ys, slope, intercept) as f32);
                 return self.calculated_b;
             }
         }
         
         None
     }
-}
\ No newline at end of file
+}
+
+/// Solves the stiff-string formula for 'B' from just two partials.
+///
+/// With `f_n = n · f_0 · √(1 + B·n²)`, the ratio of `(f_n / n)²` for two
+/// partials no longer depends on `f_0`, which leaves one equation in 'B'.
+/// Usually `p1` is the fundamental and `pn` an overtone, but any two
+/// different partials work. A single pair is at the mercy of the error in
+/// either frequency, so prefer [`KeyMeasurement::calculate_b_value`] whenever
+/// three or more partials were measured.
+///
+/// # Arguments
+/// * `p1` - One measured partial, usually the fundamental
+/// * `pn` - Another measured partial
+///
+/// # Returns
+/// * `Some(b)` - The 'B' value that puts both partials where they were measured
+/// * `None` - The partials share a number, a frequency is not positive, or
+///   the upper partial is flatter than harmonic, which no stiff string explains
+pub fn estimate_b_from_two(p1: &Partial, pn: &Partial) -> Option<f32> {
+    if p1.number == 0 || pn.number == 0 || p1.number == pn.number || p1.frequency <= 0.0 || pn.frequency <= 0.0 {
+        return None;
+    }
+    let (lower, upper) = if p1.number < pn.number { (p1, pn) } else { (pn, p1) };
+    let (m, n) = (lower.number as f64, upper.number as f64);
+    let a = (lower.frequency as f64 / m).powi(2);
+    let c = (upper.frequency as f64 / n).powi(2);
+    // A flat upper partial would need a negative 'B'
+    if c < a {
+        return None;
+    }
+    // Equals f_0² · (n² - m²) for consistent measurements
+    let denominator = a * n * n - c * m * m;
+    if denominator <= 0.0 {
+        return None;
+    }
+    Some(((c - a) / denominator) as f32)
+}
+
+/// Coefficient of determination of the line `y = slope · x + intercept`.
+///
+/// Returns 1.0 when every point lies on the line, including when all `y`
+/// are equal and the line is flat.
+fn r_squared(xs: &[f64], ys: &[f64], slope: f64, intercept: f64) -> f64 {
+    let mean = ys.iter().sum::<f64>() / ys.len() as f64;
+    let total: f64 = ys.iter().map(|y| (y - mean) * (y - mean)).sum();
+    let residual: f64 = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| {
+            let error = y - (slope * x + intercept);
+            error * error
+        })
+        .sum();
+    if total > 0.0 { 1.0 - residual / total } else { 1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A profile as written before versioning: no `version`, and a measurement
+    /// of A2 with exactly stretched partials but no fit quality or reliability.
+    fn unversioned_profile() -> serde_json::Value {
+        let partials: Vec<serde_json::Value> = (1..=6)
+            .map(|n| json!({ "number": n, "frequency": partial_frequency(110.0, n, 4.0e-4) }))
+            .collect();
+        json!({
+            "measurements": {
+                "24": { "key_index": 24, "partials": partials, "calculated_b": 3.9e-4 }
+            }
+        })
+    }
+
+    #[test]
+    fn unversioned_profile_is_migrated_to_current() {
+        let profile = InharmonicityProfile::from_json(unversioned_profile()).unwrap();
+        assert_eq!(profile.version, ProfileVersion::CURRENT as u32);
+
+        let measurement = &profile.measurements[&24];
+        let b = measurement.calculated_b.unwrap();
+        assert!((b - 4.0e-4).abs() < 1.0e-5, "B = {b}");
+        let fit = measurement.fit_r_squared.expect("fit quality recomputed");
+        assert!(fit > 0.999, "R^2 = {fit}");
+        assert!(measurement.reliability.is_some());
+    }
+
+    #[test]
+    fn newer_profile_version_is_rejected() {
+        let mut json = unversioned_profile();
+        json["version"] = json!(ProfileVersion::CURRENT as u32 + 1);
+        match InharmonicityProfile::from_json(json) {
+            Err(ProfileError::VersionMismatch { found, supported }) => {
+                assert_eq!((found, supported), (ProfileVersion::CURRENT as u32 + 1, ProfileVersion::CURRENT as u32));
+            }
+            other => panic!("expected a version mismatch, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn malformed_profile_version_is_rejected() {
+        for version in [json!(-1), json!(1.5), json!("1"), json!(null), json!(u32::MAX as u64 + 1)] {
+            let mut json = unversioned_profile();
+            json["version"] = version.clone();
+            assert!(
+                matches!(InharmonicityProfile::from_json(json), Err(ProfileError::Deserialization(_))),
+                "version {version}"
+            );
+        }
+    }
+
+    #[test]
+    fn two_partials_recover_b_in_either_order() {
+        let (f1, b) = (110.0, 4.0e-4);
+        let partial = |number| Partial { number, frequency: partial_frequency(f1, number, b) };
+        for (lower, upper) in [(1, 2), (1, 6), (2, 5)] {
+            for (first, second) in [(partial(lower), partial(upper)), (partial(upper), partial(lower))] {
+                let estimate = estimate_b_from_two(&first, &second).unwrap();
+                assert!((estimate - b).abs() < 1e-6, "partials {lower} and {upper}: B = {estimate:e}");
+            }
+        }
+    }
+
+    #[test]
+    fn two_partials_without_stiff_string_solution_give_no_b() {
+        let fundamental = Partial { number: 1, frequency: 110.0 };
+        assert_eq!(estimate_b_from_two(&fundamental, &Partial { number: 1, frequency: 110.5 }), None);
+        // Flatter than the harmonic 330 Hz
+        let flat = Partial { number: 3, frequency: 329.0 };
+        assert_eq!(estimate_b_from_two(&fundamental, &flat), None);
+        assert_eq!(estimate_b_from_two(&flat, &fundamental), None);
+    }
+}
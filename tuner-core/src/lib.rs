@@ -5,16 +5,25 @@
 //! and inharmonicity calculations. It is completely headless
 //! and contains no GUI code.
 
+pub mod analysis;
 pub mod audio;
 pub mod fft;
+pub mod filter;
 pub mod pitch;
 pub mod tuning;
 pub mod inharmonicity;
 pub mod capture_processing;
+pub mod capture_session;
+pub mod temperament;
+pub mod tracking;
+pub mod wav;
+pub mod demo;
+
+use std::time::Instant;
 
 /// Represents the result of a single audio analysis frame.
 // This derive is necessary for the struct to be used in the `CustomEvent` enum.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct AnalysisResult {
     /// The primary detected frequency in Hz.
     pub detected_frequency: Option<f32>,
@@ -24,8 +33,76 @@ pub struct AnalysisResult {
     pub cents_deviation: Option<f32>,
     /// The name of the nearest note.
     pub note_name: Option<String>,
+    /// True if the detected frequency lies more than half a semitone outside
+    /// the keyboard. `detected_frequency` is kept, but `note_name` and
+    /// `cents_deviation` are `None` since no key matches it.
+    pub out_of_range: bool,
+    /// True if the fundamental was not detected but derived from the 2nd
+    /// partial (see `AnalysisConfig::second_partial`).
+    pub derived_fundamental: bool,
     /// Data for the spectrogram visualization.
     pub spectrogram_data: Vec<f32>,
-    /// Frequencies of the detected partials.
-    pub partials: Vec<f32>,
-}
\ No newline at end of file
+    /// Spectrum magnitude at the detected fundamental.
+    pub fundamental_magnitude: Option<f32>,
+    /// Simultaneous notes as (note name, frequency in Hz, cents deviation),
+    /// lowest first. Only filled in when `AnalysisConfig::polyphonic` is set.
+    pub detected_notes: Vec<(String, f32, f32)>,
+    /// The detected partials (overtones), starting at the 2nd harmonic.
+    pub partials: Vec<pitch::DetectedPartial>,
+    /// Peak absolute sample value of the frame after input gain (1.0 = full scale).
+    pub input_level: f32,
+    /// True if any sample of the frame reached full scale after input gain.
+    pub clipped: bool,
+    /// RMS level of the frame after input gain (1.0 = full-scale square wave).
+    pub rms: f32,
+    /// Sample rate the frame was analyzed at, in Hz (0 if not analyzed).
+    pub sample_rate: u32,
+    /// Number of samples the spectrum was computed over, so `spectrogram_data`
+    /// bins map to frequencies with `fft::bin_to_frequency` (0 if not analyzed).
+    pub fft_size: usize,
+    /// Samples between the start of this frame and the next. Equal to the
+    /// frame length unless the capture overlaps frames (0 if not analyzed).
+    pub hop_size: usize,
+    /// When the frame was analyzed. Time windows over consecutive results
+    /// (smoothing, stability) are measured with this rather than by counting
+    /// frames, so they don't depend on the frame or polling rate.
+    pub timestamp: Option<Instant>,
+    /// Rate of amplitude modulation (false beat) of the sustained fundamental, in Hz.
+    /// Filled in by `tracking::FrameTracker`; `None` when the amplitude is steady.
+    pub modulation_hz: Option<f32>,
+    /// Peak-to-peak depth of that modulation, in dB.
+    pub modulation_depth_db: Option<f32>,
+    /// Growth of the spectrum since the previous frame, relative to its total
+    /// (0.0 = steady). Filled in by `tracking::FrameTracker`.
+    pub spectral_flux: Option<f32>,
+    /// Whether the RMS level is rising, steady or falling over the last few
+    /// frames. Filled in by `tracking::FrameTracker`; `None` until it has enough history.
+    pub energy_trend: Option<tracking::EnergyTrend>,
+}
+
+impl AnalysisResult {
+    /// Returns the measured frequency of partial `n` (1 = fundamental), if detected.
+    pub fn partial_frequency(&self, n: u32) -> Option<f32> {
+        match n {
+            0 => None,
+            1 => self.detected_frequency,
+            n => self.partials.iter().find(|p| p.number == n).map(|p| p.frequency),
+        }
+    }
+
+    /// Drops everything derived from the detected pitch, leaving the frame as
+    /// if no note had been found. Levels and the spectrum are kept.
+    pub fn clear_pitch(&mut self) {
+        self.detected_frequency = None;
+        self.confidence = None;
+        self.cents_deviation = None;
+        self.note_name = None;
+        self.out_of_range = false;
+        self.derived_fundamental = false;
+        self.fundamental_magnitude = None;
+        self.detected_notes.clear();
+        self.partials.clear();
+        self.modulation_hz = None;
+        self.modulation_depth_db = None;
+    }
+}
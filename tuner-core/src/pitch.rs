@@ -10,6 +10,216 @@
 //! - Noise rejection and clarity checking
 //! - Parabolic interpolation for sub-sample accuracy
 //! - Spectrum refinement for improved precision
+//! - Guided partial search with a configurable peak threshold, optionally
+//!   centered on the stretched positions of a known inharmonicity
+//! - Multi-pitch detection for a few simultaneous notes (polyphonic mode)
+//! - Signal-to-noise estimate of a detected pitch from the magnitude spectrum
+//! - Least-squares fit of a stiff string's partial series, for bass notes
+//!   whose period is too long for YIN within one frame
+
+use crate::{fft, inharmonicity};
+
+/// A partial (overtone) found in a magnitude spectrum.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DetectedPartial {
+    /// Harmonic number of the partial (2 = first overtone)
+    pub number: u32,
+    /// Refined frequency of the partial in Hz
+    pub frequency: f32,
+    /// Spectrum magnitude at the partial's peak bin
+    pub magnitude: f32,
+}
+
+/// Pitch detection algorithm used to find the fundamental of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PitchAlgorithm {
+    /// YIN: takes the first dip below a fixed threshold (see `detect_pitch_yin`)
+    Yin,
+    /// pYIN: takes the best dip across the whole lag range (see `detect_pitch_pyin`)
+    #[default]
+    Pyin,
+}
+
+impl PitchAlgorithm {
+    /// Returns the next algorithm, for cycling through them in a UI.
+    pub fn next(self) -> Self {
+        match self {
+            PitchAlgorithm::Yin => PitchAlgorithm::Pyin,
+            PitchAlgorithm::Pyin => PitchAlgorithm::Yin,
+        }
+    }
+}
+
+/// Rule deciding whether a spectral peak is strong enough to count as a partial.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartialThreshold {
+    /// The peak must exceed this fraction of the fundamental's magnitude.
+    /// Works well when the fundamental is strong; too permissive when it is weak.
+    RelativeToFundamental(f32),
+    /// The peak must exceed the local noise floor (median magnitude around the
+    /// expected partial) multiplied by this factor. Independent of how strong
+    /// the fundamental is.
+    AboveNoiseFloor(f32),
+    /// The peak must exceed `fraction` of the strongest of the first
+    /// `REFERENCE_PARTIALS` partials, where partial `n` is weighted by
+    /// `overtone_weight^(n-1)`. A weight of 1.0 treats all of them equally,
+    /// 0.0 is the same as `RelativeToFundamental`; values in between still
+    /// favor the fundamental but keep the threshold sensible when the 2nd
+    /// partial is stronger than a weak fundamental.
+    RelativeToStrongest { fraction: f32, overtone_weight: f32 },
+}
+
+impl Default for PartialThreshold {
+    /// 5% of the strongest of the first few partials, overtones weighted by half.
+    fn default() -> Self {
+        Self::RelativeToStrongest { fraction: 0.05, overtone_weight: 0.5 }
+    }
+}
+
+/// Default highest YIN difference value a dip may have to count as a pitch.
+/// Lower values reject more noise but also more weak or inharmonic notes.
+pub const DEFAULT_DIP_THRESHOLD: f32 = 0.1;
+
+/// Number of low partials (including the fundamental) considered when
+/// choosing the reference magnitude for `PartialThreshold::RelativeToStrongest`.
+const REFERENCE_PARTIALS: u32 = 3;
+
+/// Minimum half-width, in bins, of the neighbourhood used to estimate the local noise floor.
+const NOISE_FLOOR_HALF_WIDTH_BINS: usize = 16;
+
+/// Width of each partial search window around a harmonic position, as a fraction of f0.
+/// Wide enough to catch partials stretched by an unknown inharmonicity.
+const HARMONIC_SEARCH_WIDTH: f32 = 0.5;
+
+/// Width of each partial search window around a stretched position, as a fraction of f0.
+/// The known 'B' already accounts for most of the stretch, leaving less room for neighbours.
+const STRETCHED_SEARCH_WIDTH: f32 = 0.25;
+
+/// Partials of the string modelled by `fit_partial_series`.
+const SERIES_FIT_PARTIALS: u32 = 12;
+
+/// Step of the coarse search of `fit_partial_series`, in cents. The fit
+/// peaks over tens of cents for bass notes, so the best step is then refined.
+const SERIES_FIT_STEP_CENTS: f32 = 5.0;
+
+/// Lowest frequency considered by `detect_multiple_pitches`, in Hz.
+const POLYPHONIC_MIN_HZ: f32 = 25.0;
+
+/// Highest fundamental considered by `detect_multiple_pitches`, in Hz.
+const POLYPHONIC_MAX_HZ: f32 = 4500.0;
+
+/// Spectral peaks weaker than this fraction of the strongest peak are ignored.
+const POLYPHONIC_PEAK_FRACTION: f32 = 0.05;
+
+/// Harmonics summed into each candidate's salience.
+const POLYPHONIC_HARMONICS: u32 = 6;
+
+/// How far (relative) a peak may sit from `h * f0` and still count as harmonic `h`.
+/// Wide enough for the stretched partials of a piano string.
+const POLYPHONIC_HARMONIC_TOLERANCE: f32 = 0.03;
+
+/// Further notes must reach this fraction of the strongest note's salience.
+const POLYPHONIC_MIN_RELATIVE_SALIENCE: f32 = 0.2;
+
+/// Detects several simultaneous pitches from a magnitude spectrum.
+///
+/// Spectral peaks are the candidate fundamentals. Each candidate is scored by
+/// the summed magnitude of the peaks at its first few harmonics (with lower
+/// harmonics weighted more), and the strongest is taken. Its harmonics are
+/// then removed from the peaks, keeping only what sticks out above a smooth
+/// harmonic envelope, so a note an octave or a fifth above that shares those
+/// partials can still be found. This repeats until `max_pitches` notes are
+/// found or the next candidate is much weaker than the first.
+///
+/// Two notes closer together than a couple of FFT bins (about 43 Hz for a
+/// 2048-sample frame at 44.1 kHz) merge into one peak, so close intervals in
+/// the bass are not separated.
+///
+/// # Arguments
+/// * `spectrum_magnitudes` - Magnitude spectrum from FFT
+/// * `sample_rate` - Sample rate in Hz
+/// * `fft_size` - Number of samples the FFT was computed over
+/// * `max_pitches` - Maximum number of pitches to return
+///
+/// # Returns
+/// * `Vec<(frequency, salience)>` - Detected fundamentals in Hz, strongest first,
+///   with salience relative to the strongest (1.0 for the first)
+pub fn detect_multiple_pitches(
+    spectrum_magnitudes: &[f32],
+    sample_rate: u32,
+    fft_size: usize,
+    max_pitches: usize,
+) -> Vec<(f32, f32)> {
+    let strongest = spectrum_magnitudes.iter().fold(0.0f32, |max, &m| max.max(m));
+    if strongest <= 0.0 || spectrum_magnitudes.len() < 3 {
+        return Vec::new();
+    }
+
+    // Local maxima above the peak threshold, as (frequency, remaining magnitude)
+    let min_bin = fft::frequency_to_bin(POLYPHONIC_MIN_HZ, sample_rate, fft_size).ceil().max(1.0) as usize;
+    let mut peaks: Vec<(f32, f32)> = (min_bin..spectrum_magnitudes.len() - 1)
+        .filter(|&bin| {
+            let m = spectrum_magnitudes[bin];
+            m >= strongest * POLYPHONIC_PEAK_FRACTION
+                && m > spectrum_magnitudes[bin - 1]
+                && m >= spectrum_magnitudes[bin + 1]
+        })
+        .map(|bin| {
+            let freq = interpolate_peak_frequency(spectrum_magnitudes, bin, sample_rate, fft_size)
+                .unwrap_or_else(|| fft::bin_to_frequency(bin, sample_rate, fft_size));
+            (freq, spectrum_magnitudes[bin])
+        })
+        .collect();
+
+    // Index of the peak closest to `target`, if it is within the harmonic tolerance
+    let harmonic_peak = |peaks: &[(f32, f32)], target: f32| {
+        peaks
+            .iter()
+            .enumerate()
+            .filter(|(_, (freq, _))| (freq / target - 1.0).abs() <= POLYPHONIC_HARMONIC_TOLERANCE)
+            .min_by(|(_, a), (_, b)| (a.0 - target).abs().total_cmp(&(b.0 - target).abs()))
+            .map(|(i, _)| i)
+    };
+
+    let mut found: Vec<(f32, f32)> = Vec::new();
+    let mut first_salience = None;
+    while found.len() < max_pitches {
+        // Score every remaining peak as a fundamental
+        let best = peaks
+            .iter()
+            .filter(|&&(freq, magnitude)| freq <= POLYPHONIC_MAX_HZ && magnitude > 0.0)
+            .map(|&(f0, _)| {
+                let salience: f32 = (1..=POLYPHONIC_HARMONICS)
+                    .filter_map(|h| harmonic_peak(&peaks, f0 * h as f32).map(|i| peaks[i].1 / (h as f32).sqrt()))
+                    .sum();
+                (f0, salience)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((f0, salience)) = best else { break };
+        let first = *first_salience.get_or_insert(salience);
+        if salience <= 0.0 || salience < first * POLYPHONIC_MIN_RELATIVE_SALIENCE {
+            break;
+        }
+        found.push((f0, salience / first));
+
+        // Remove this note's share of its harmonics: the fundamental entirely, and
+        // each overtone down to the smooth envelope of its neighbours
+        let indices: Vec<Option<usize>> = (1..=POLYPHONIC_HARMONICS + 1)
+            .map(|h| harmonic_peak(&peaks, f0 * h as f32))
+            .collect();
+        let amplitudes: Vec<f32> = indices.iter().map(|i| i.map_or(0.0, |i| peaks[i].1)).collect();
+        for (h, index) in indices.iter().enumerate().take(POLYPHONIC_HARMONICS as usize) {
+            let Some(i) = *index else { continue };
+            let share = if h == 0 {
+                amplitudes[0]
+            } else {
+                amplitudes[h].min((amplitudes[h - 1] + amplitudes[h + 1]) / 2.0)
+            };
+            peaks[i].1 = (peaks[i].1 - share).max(0.0);
+        }
+    }
+    found
+}
 
 /// A robust implementation of the pYIN pitch detection algorithm (stateless).
 ///
@@ -21,6 +231,7 @@
 /// * `signal` - Input audio signal
 /// * `sample_rate` - Sample rate in Hz
 /// * `amplitude_threshold` - Minimum amplitude for pitch detection
+/// * `dip_threshold` - Highest difference value the best dip may have (see `DEFAULT_DIP_THRESHOLD`)
 ///
 /// # Returns
 /// * `Some(frequency)` - Detected frequency in Hz
@@ -29,6 +240,7 @@ pub fn detect_pitch_pyin(
     signal: &[f32],
     sample_rate: u32,
     amplitude_threshold: f32,
+    dip_threshold: f32,
 ) -> Option<(f32, f32)> {
     let frame_size = signal.len();
     if frame_size < 4 { return None; } // Need at least a few samples
@@ -68,8 +280,7 @@ pub fn detect_pitch_pyin(
     
     // --- Clarity Check ---
     // If no clear dip was found, it's likely noise.
-    const CLARITY_THRESHOLD: f32 = 0.1;
-    if best_period == 0 || lowest_yin_val > CLARITY_THRESHOLD {
+    if best_period == 0 || lowest_yin_val > dip_threshold {
         return None;
     }
 
@@ -127,62 +338,92 @@ fn yin_difference(signal: &[f32], frame_size: usize, yin_buffer: &mut [f32]) {
 /// picking up unrelated noise. Each found peak's frequency is then refined
 /// using parabolic interpolation for maximum accuracy.
 ///
+/// High partials of a stiff bass string lie well sharp of `n * f0`. When an
+/// estimate of the string's 'B' is given, each window is centered on the
+/// stretched position `n * f0 * sqrt((1 + B * n^2) / (1 + B))` instead, and
+/// narrowed, so the partial is caught without also catching its neighbours.
+///
 /// # Arguments
 /// * `spectrum_magnitudes` - Magnitude spectrum from an FFT.
 /// * `fundamental_freq` - The fundamental frequency ($f_0$) of the note, used to guide the search.
 /// * `sample_rate` - The sample rate of the original audio.
+/// * `fft_size` - The number of samples the FFT was computed over.
 /// * `max_partials` - The maximum number of partials (overtones) to search for.
+/// * `threshold` - How strong a peak must be to be accepted as a partial.
+/// * `stretch_b` - Inharmonicity estimate the windows are centered by (None = harmonic positions).
 ///
 /// # Returns
-/// * `Vec<f32>` - A vector containing the refined frequencies of the detected partials.
+/// * `Vec<DetectedPartial>` - The harmonic number, refined frequency and peak magnitude of
+///   each detected partial. Partials that are not found are skipped, so the numbers may
+///   have gaps; always use `number` rather than the position in the vector.
 pub fn find_partials(
     spectrum_magnitudes: &[f32],
     fundamental_freq: f32,
     sample_rate: u32,
+    fft_size: usize,
     max_partials: u32,
-) -> Vec<f32> {
-    if fundamental_freq <= 0.0 {
+    threshold: PartialThreshold,
+    stretch_b: Option<f32>,
+) -> Vec<DetectedPartial> {
+    if fundamental_freq <= 0.0 || spectrum_magnitudes.len() < 2 {
         return vec![];
     }
 
     let mut partial_freqs = Vec::new();
-    let buffer_size = spectrum_magnitudes.len() * 2;
 
-    // A relative threshold to ignore noise. A peak must be at least 5% of the
-    // magnitude of the fundamental's peak to be considered a partial.
-    let fundamental_bin = (fundamental_freq * buffer_size as f32) / sample_rate as f32;
-    let peak_threshold = if let Some(mag) = spectrum_magnitudes.get(fundamental_bin.round() as usize) {
-        mag * 0.05
-    } else {
-        0.0 // No fundamental found, so we can't find partials
+    // Define a search window in Hz around each expected frequency.
+    // Without a 'B' estimate it must be wide enough for higher, more inharmonic partials.
+    let stretch_b = stretch_b.filter(|&b| b > 0.0);
+    let expected_freq = |n: u32| match stretch_b {
+        Some(b) => inharmonicity::partial_frequency(fundamental_freq, n, b),
+        None => fundamental_freq * n as f32,
+    };
+    let search_width_hz = fundamental_freq * if stretch_b.is_some() { STRETCHED_SEARCH_WIDTH } else { HARMONIC_SEARCH_WIDTH };
+    let bin_width = fft::frequency_to_bin(search_width_hz, sample_rate, fft_size);
+
+    // A relative threshold to ignore noise. In the relative modes a peak must be a
+    // fraction of a reference magnitude (the fundamental's, or the strongest of the
+    // first few weighted partials) to be considered a partial.
+    let fundamental_bin = fft::frequency_to_bin(fundamental_freq, sample_rate, fft_size);
+    let fundamental_magnitude = spectrum_magnitudes.get(fundamental_bin.round() as usize).copied();
+    let fundamental_threshold = match threshold {
+        PartialThreshold::RelativeToFundamental(fraction) => {
+            match fundamental_magnitude {
+                Some(mag) => mag * fraction,
+                None => 0.0, // No fundamental found, so we can't find partials
+            }
+        }
+        PartialThreshold::RelativeToStrongest { fraction, overtone_weight } => {
+            let reference = (2..=REFERENCE_PARTIALS)
+                .filter_map(|n| {
+                    let target_bin = fft::frequency_to_bin(expected_freq(n), sample_rate, fft_size);
+                    let (start_bin, end_bin) = search_window(target_bin, bin_width, spectrum_magnitudes.len())?;
+                    let peak = spectrum_magnitudes[start_bin..=end_bin].iter().copied().fold(0.0f32, f32::max);
+                    Some(peak * overtone_weight.powi(n as i32 - 1))
+                })
+                .fold(fundamental_magnitude.unwrap_or(0.0), f32::max);
+            reference * fraction
+        }
+        PartialThreshold::AboveNoiseFloor(_) => f32::NAN, // Computed per partial below
     };
 
-    if peak_threshold == 0.0 { return vec![]; }
+    if fundamental_threshold == 0.0 { return vec![]; }
 
     // Start the loop at n=2 to find the first overtone (2nd harmonic) and go up from there.
     // To still find `max_partials` number of overtones, we loop to `max_partials + 1`.
     for n in 2..=(max_partials + 1) {
-        let expected_freq = fundamental_freq * n as f32;
+        let expected_freq = expected_freq(n);
         
         // Stop if we go past the Nyquist frequency
         if expected_freq > sample_rate as f32 / 2.0 {
             break;
         }
 
-        // Define a search window in Hz around the expected frequency.
-        // A wider window is needed for higher, more inharmonic partials.
-        let search_width_hz = fundamental_freq * 0.5;
-
         // Convert frequency window to bin indices
-        let target_bin = (expected_freq * buffer_size as f32) / sample_rate as f32;
-        let bin_width = (search_width_hz * buffer_size as f32) / sample_rate as f32;
-        let start_bin = ((target_bin - bin_width / 2.0).max(0.0) as usize)
-            .min(spectrum_magnitudes.len() -1);
-        let end_bin = ((target_bin + bin_width / 2.0)
-            .min((spectrum_magnitudes.len() - 1) as f32) as usize)
-            .max(start_bin);
-
-        if start_bin >= end_bin { continue; }
+        let target_bin = fft::frequency_to_bin(expected_freq, sample_rate, fft_size);
+        let Some((start_bin, end_bin)) = search_window(target_bin, bin_width, spectrum_magnitudes.len()) else {
+            continue;
+        };
 
         // Find the bin with the highest magnitude within our search window
         // FIX: The closure in `max_by` now correctly handles the Option returned by `partial_cmp`.
@@ -192,12 +433,26 @@ pub fn find_partials(
             .enumerate()
             .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Less));
 
+        let peak_threshold = match threshold {
+            PartialThreshold::RelativeToFundamental(_) | PartialThreshold::RelativeToStrongest { .. } => {
+                fundamental_threshold
+            }
+            PartialThreshold::AboveNoiseFloor(factor) => {
+                let half_width = (bin_width as usize).max(NOISE_FLOOR_HALF_WIDTH_BINS);
+                local_noise_floor(spectrum_magnitudes, target_bin.round() as usize, half_width) * factor
+            }
+        };
+
         if let Some((offset, &magnitude)) = peak_in_window {
             // Check if the peak is strong enough to be considered a partial
             if magnitude > peak_threshold {
                 let peak_bin = start_bin + offset;
-                if let Some(refined_freq) = interpolate_peak_frequency(spectrum_magnitudes, peak_bin, sample_rate) {
-                    partial_freqs.push(refined_freq);
+                if let Some(refined_freq) = interpolate_peak_frequency(spectrum_magnitudes, peak_bin, sample_rate, fft_size) {
+                    partial_freqs.push(DetectedPartial {
+                        number: n,
+                        frequency: refined_freq,
+                        magnitude,
+                    });
                 }
             }
         }
@@ -206,6 +461,42 @@ pub fn find_partials(
     partial_freqs
 }
 
+/// Returns the inclusive bin range searched for a partial.
+///
+/// # Arguments
+/// * `target_bin` - Bin of the expected partial frequency.
+/// * `bin_width` - Total width of the window in bins.
+/// * `len` - Number of bins in the spectrum.
+///
+/// # Returns
+/// * `Some((start_bin, end_bin))` - Window clamped to the spectrum
+/// * `None` - The window is empty after clamping
+fn search_window(target_bin: f32, bin_width: f32, len: usize) -> Option<(usize, usize)> {
+    let start_bin = ((target_bin - bin_width / 2.0).max(0.0) as usize).min(len - 1);
+    let end_bin = ((target_bin + bin_width / 2.0).min((len - 1) as f32) as usize).max(start_bin);
+    (start_bin < end_bin).then_some((start_bin, end_bin))
+}
+
+/// Estimates the noise floor around a bin as the median magnitude of its neighbourhood.
+///
+/// A partial only occupies a few bins, so the median of a wider window is
+/// dominated by the background noise rather than the peak itself.
+///
+/// # Arguments
+/// * `spectrum_magnitudes` - Magnitude spectrum from an FFT.
+/// * `center_bin` - Bin the neighbourhood is centered on.
+/// * `half_width` - Number of bins taken on each side of the center.
+fn local_noise_floor(spectrum_magnitudes: &[f32], center_bin: usize, half_width: usize) -> f32 {
+    let last_bin = spectrum_magnitudes.len() - 1;
+    let center = center_bin.min(last_bin);
+    let start = center.saturating_sub(half_width);
+    let end = (center + half_width).min(last_bin);
+
+    let mut window: Vec<f32> = spectrum_magnitudes[start..=end].to_vec();
+    window.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Less));
+    window[window.len() / 2]
+}
+
 /// Refines a frequency estimate using parabolic interpolation on the FFT spectrum.
 ///
 /// This is a private helper function used by `refine_from_spectrum` and `find_partials`.
@@ -216,6 +507,7 @@ pub fn find_partials(
 /// * `spectrum_magnitudes` - Magnitude spectrum from an FFT.
 /// * `peak_bin` - The index of the peak bin to be refined.
 /// * `sample_rate` - The sample rate of the original audio.
+/// * `fft_size` - The number of samples the FFT was computed over.
 ///
 /// # Returns
 /// * `Some(refined_freq)` if successful, otherwise `None`.
@@ -223,6 +515,7 @@ fn interpolate_peak_frequency(
     spectrum_magnitudes: &[f32],
     peak_bin: usize,
     sample_rate: u32,
+    fft_size: usize,
 ) -> Option<f32> {
     // Ensure we have neighbors for interpolation
     if peak_bin == 0 || peak_bin >= spectrum_magnitudes.len() - 1 {
@@ -240,9 +533,8 @@ fn interpolate_peak_frequency(
 
     // Use the new helper function
     if let Some(offset) = parabolic_interpolation_offset(y1, y2, y3) {
-        let interpolated_bin = peak_bin as f32 + offset;
-        let buffer_size = spectrum_magnitudes.len() * 2;
-        let final_freq = (interpolated_bin * sample_rate as f32) / buffer_size as f32;
+        let final_freq = fft::bin_to_frequency(peak_bin, sample_rate, fft_size)
+            + offset * fft::bin_resolution(sample_rate, fft_size);
 
         if final_freq.is_finite() && final_freq > 0.0 {
             Some(final_freq)
@@ -261,22 +553,32 @@ fn interpolate_peak_frequency(
 /// the frequency spectrum around the initial estimate. It uses parabolic
 /// interpolation to achieve sub-bin accuracy.
 /// 
+/// The rough estimate must lie strictly between 0 Hz and the Nyquist
+/// frequency (`sample_rate / 2`); outside that range the spectrum holds no
+/// information about it, so no frequency is returned at all.
+/// 
 /// # Arguments
 /// * `spectrum_magnitudes` - Magnitude spectrum from FFT
 /// * `rough_freq` - Initial frequency estimate in Hz
 /// * `sample_rate` - Sample rate in Hz
+/// * `fft_size` - Number of samples the FFT was computed over
 /// 
 /// # Returns
-/// * `Some(refined_freq)` - Refined frequency estimate
-/// * `None` - Refinement failed, use original estimate
+/// * `Some(refined_freq)` - Refined frequency estimate, or `rough_freq` itself
+///   if no peak could be interpolated around it
+/// * `None` - `rough_freq` is not finite or is outside `(0, Nyquist)`
 pub fn refine_from_spectrum(
     spectrum_magnitudes: &[f32],
     rough_freq: f32,
     sample_rate: u32,
+    fft_size: usize,
 ) -> Option<f32> {
-    if rough_freq <= 0.0 { return Some(rough_freq); }
-    let buffer_size = spectrum_magnitudes.len() * 2;
-    let target_bin = (rough_freq * buffer_size as f32) / sample_rate as f32;
+    let nyquist = sample_rate as f32 / 2.0;
+    if !rough_freq.is_finite() || rough_freq <= 0.0 || rough_freq >= nyquist {
+        return None;
+    }
+    if spectrum_magnitudes.len() < 2 { return Some(rough_freq); }
+    let target_bin = fft::frequency_to_bin(rough_freq, sample_rate, fft_size);
     
     // Search a very small radius since our rough_freq should be close
     let search_radius = 2.0;
@@ -299,10 +601,225 @@ pub fn refine_from_spectrum(
     };
 
     // Use our new helper for the final interpolation
-    interpolate_peak_frequency(spectrum_magnitudes, peak_bin, sample_rate)
+    interpolate_peak_frequency(spectrum_magnitudes, peak_bin, sample_rate, fft_size)
         .or(Some(rough_freq)) // If interpolation fails, fall back to the rough frequency
 }
 
+/// Estimates how far a detected note stands out of the spectral noise floor.
+///
+/// The RMS gate can't tell a loud, noisy room from a loud note. This compares
+/// the peak at the fundamental with the median magnitude around it instead.
+/// The fundamental of a bass string can be weaker than its next partials, so
+/// the best of the first `REFERENCE_PARTIALS` partials is taken, each
+/// against its own neighbourhood.
+///
+/// # Arguments
+/// * `spectrum_magnitudes` - Magnitude spectrum from FFT
+/// * `fundamental_freq` - Detected fundamental in Hz
+/// * `sample_rate` - Sample rate in Hz
+/// * `fft_size` - Number of samples the FFT was computed over
+///
+/// # Returns
+/// * `Some(snr_db)` - Peak-to-noise-floor ratio in dB
+/// * `None` - No partial within the spectrum, or a zero noise floor (e.g. a band-limited spectrum)
+pub fn fundamental_snr_db(
+    spectrum_magnitudes: &[f32],
+    fundamental_freq: f32,
+    sample_rate: u32,
+    fft_size: usize,
+) -> Option<f32> {
+    if fundamental_freq <= 0.0 || spectrum_magnitudes.len() < 2 {
+        return None;
+    }
+    (1..=REFERENCE_PARTIALS)
+        .filter_map(|n| peak_snr_db(spectrum_magnitudes, fundamental_freq * n as f32, sample_rate, fft_size))
+        .reduce(f32::max)
+}
+
+/// Estimates how far the spectral peak at one frequency stands out of the noise floor.
+///
+/// The peak is the strongest bin next to the frequency, the floor the median
+/// magnitude around it.
+///
+/// # Arguments
+/// * `spectrum_magnitudes` - Magnitude spectrum from FFT
+/// * `freq` - Frequency of the peak in Hz
+/// * `sample_rate` - Sample rate in Hz
+/// * `fft_size` - Number of samples the FFT was computed over
+///
+/// # Returns
+/// * `Some(snr_db)` - Peak-to-noise-floor ratio in dB
+/// * `None` - The frequency is outside the spectrum, or the floor or peak is zero
+pub fn peak_snr_db(spectrum_magnitudes: &[f32], freq: f32, sample_rate: u32, fft_size: usize) -> Option<f32> {
+    if freq <= 0.0 || spectrum_magnitudes.len() < 2 {
+        return None;
+    }
+    let bin = fft::frequency_to_bin(freq, sample_rate, fft_size).round() as usize;
+    if bin >= spectrum_magnitudes.len() {
+        return None;
+    }
+    let peak = spectrum_magnitudes[bin.saturating_sub(1)..=(bin + 1).min(spectrum_magnitudes.len() - 1)]
+        .iter()
+        .copied()
+        .fold(0.0f32, f32::max);
+    let floor = local_noise_floor(spectrum_magnitudes, bin, NOISE_FLOOR_HALF_WIDTH_BINS);
+    (floor > 0.0 && peak > 0.0).then(|| 20.0 * (peak / floor).log10())
+}
+
+/// Finds the pitch of a stiff string by fitting its whole partial series to the frame.
+///
+/// A low bass note repeats only once or twice in a short frame, too few times
+/// for YIN, and its partials are closer together than the FFT can separate.
+/// With the string's 'B' known, though, the partials of any candidate first
+/// partial `f1` are known too, and a least-squares fit of sinusoids at all of
+/// them (plus a constant) explains the frame best at the true `f1`. Candidates
+/// are tried in `SERIES_FIT_STEP_CENTS` steps within `search_cents` of
+/// `expected_f1`, and the best step is refined by parabolic interpolation.
+///
+/// # Arguments
+/// * `signal` - Audio frame
+/// * `sample_rate` - Sample rate in Hz
+/// * `expected_f1` - Expected first partial (the key's target) in Hz
+/// * `b` - Inharmonicity constant of the string
+/// * `search_cents` - How far from `expected_f1` to search, in cents
+///
+/// # Returns
+/// * `Some((f1, confidence))` - Best-fitting first partial in Hz, and the fraction
+///   (0.0-1.0) of the frame's variance the fitted partials explain
+/// * `None` - Silent or too short a frame, or an invalid expected pitch
+pub fn fit_partial_series(
+    signal: &[f32],
+    sample_rate: u32,
+    expected_f1: f32,
+    b: f32,
+    search_cents: f32,
+) -> Option<(f32, f32)> {
+    if signal.len() < 4 || !expected_f1.is_finite() || expected_f1 <= 0.0 || sample_rate == 0 {
+        return None;
+    }
+    let samples: Vec<f64> = signal.iter().map(|&s| s as f64).collect();
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    let variance: f64 = samples.iter().map(|s| (s - mean) * (s - mean)).sum();
+    if variance <= f64::EPSILON {
+        return None;
+    }
+
+    let candidate = |step: f32| expected_f1 * 2.0_f32.powf(step * SERIES_FIT_STEP_CENTS / 1200.0);
+    let explained = |f1: f32| {
+        let residual = series_fit_residual(&samples, sample_rate, f1, b);
+        (1.0 - residual / variance) as f32
+    };
+
+    let steps = (search_cents.max(0.0) / SERIES_FIT_STEP_CENTS).ceil() as i32;
+    let scores: Vec<f32> = (-steps..=steps).map(|step| explained(candidate(step as f32))).collect();
+    let (best, &best_score) = scores
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+    // Between the steps, the fit is close to a parabola around its peak
+    let offset = match (best.checked_sub(1).map(|i| scores[i]), scores.get(best + 1)) {
+        (Some(left), Some(&right)) => parabolic_interpolation_offset(left, best_score, right).unwrap_or(0.0).clamp(-0.5, 0.5),
+        _ => 0.0,
+    };
+    let f1 = candidate(best as f32 - steps as f32 + offset);
+    Some((f1, explained(f1).clamp(0.0, 1.0)))
+}
+
+/// Returns the energy left after a least-squares fit of a constant plus a
+/// sinusoid at each partial of `f1` below Nyquist.
+///
+/// The sums of products of the sinusoids have closed forms, so the normal
+/// equations cost a handful of correlations with the frame instead of one
+/// product per pair of basis functions and sample.
+fn series_fit_residual(samples: &[f64], sample_rate: u32, f1: f32, b: f32) -> f64 {
+    let len = samples.len();
+    let nyquist = sample_rate as f32 / 2.0;
+    let omegas: Vec<f64> = (1..=SERIES_FIT_PARTIALS)
+        .map(|n| inharmonicity::partial_frequency(f1, n, b))
+        .take_while(|&freq| freq < nyquist)
+        .map(|freq| std::f64::consts::TAU * freq as f64 / sample_rate as f64)
+        .collect();
+
+    // Sums of cos(theta * i) and sin(theta * i) over the frame
+    let cos_sum = |theta: f64| {
+        let half = (theta / 2.0).sin();
+        if half.abs() < 1e-12 { len as f64 } else { (len as f64 * theta / 2.0).sin() / half * ((len - 1) as f64 * theta / 2.0).cos() }
+    };
+    let sin_sum = |theta: f64| {
+        let half = (theta / 2.0).sin();
+        if half.abs() < 1e-12 { 0.0 } else { (len as f64 * theta / 2.0).sin() / half * ((len - 1) as f64 * theta / 2.0).sin() }
+    };
+
+    // Basis: the constant, then cos and sin of each partial
+    let size = 1 + 2 * omegas.len();
+    let mut gram = vec![vec![0.0f64; size]; size];
+    let mut projection = vec![0.0f64; size];
+    gram[0][0] = len as f64;
+    projection[0] = samples.iter().sum();
+    for (a, &wa) in omegas.iter().enumerate() {
+        let (ca, sa) = (1 + 2 * a, 2 + 2 * a);
+        (gram[0][ca], gram[ca][0]) = (cos_sum(wa), cos_sum(wa));
+        (gram[0][sa], gram[sa][0]) = (sin_sum(wa), sin_sum(wa));
+        for (b, &wb) in omegas.iter().enumerate() {
+            let (cb, sb) = (1 + 2 * b, 2 + 2 * b);
+            gram[ca][cb] = (cos_sum(wa - wb) + cos_sum(wa + wb)) / 2.0;
+            gram[sa][sb] = (cos_sum(wa - wb) - cos_sum(wa + wb)) / 2.0;
+            let cos_sin = (sin_sum(wa + wb) - sin_sum(wa - wb)) / 2.0;
+            (gram[ca][sb], gram[sb][ca]) = (cos_sin, cos_sin);
+        }
+        // Correlation with the frame, rotating a phasor instead of calling cos/sin per sample
+        let (step_re, step_im) = (wa.cos(), wa.sin());
+        let (mut re, mut im, mut cos_acc, mut sin_acc) = (1.0f64, 0.0f64, 0.0, 0.0);
+        for &sample in samples {
+            cos_acc += sample * re;
+            sin_acc += sample * im;
+            (re, im) = (re * step_re - im * step_im, re * step_im + im * step_re);
+        }
+        projection[ca] = cos_acc;
+        projection[sa] = sin_acc;
+    }
+
+    let energy: f64 = samples.iter().map(|s| s * s).sum();
+    match solve_linear_system(gram, projection.clone()) {
+        Some(coefficients) => energy - coefficients.iter().zip(&projection).map(|(c, p)| c * p).sum::<f64>(),
+        None => energy,
+    }
+}
+
+/// Solves `matrix * x = rhs` by Gaussian elimination with partial pivoting.
+///
+/// # Returns
+/// * `Some(x)` - The solution
+/// * `None` - The matrix is (numerically) singular
+fn solve_linear_system(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let size = rhs.len();
+    let scale = matrix.iter().flatten().fold(0.0f64, |max, v| max.max(v.abs()));
+    for col in 0..size {
+        let pivot = (col..size).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))?;
+        if matrix[pivot][col].abs() <= scale * 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        let (upper, lower) = matrix.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for (offset, row) in lower.iter_mut().enumerate() {
+            let factor = row[col] / pivot_row[col];
+            for (value, pivot_value) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * pivot_value;
+            }
+            rhs[col + 1 + offset] -= factor * rhs[col];
+        }
+    }
+    let mut x = vec![0.0f64; size];
+    for row in (0..size).rev() {
+        let known: f64 = ((row + 1)..size).map(|k| matrix[row][k] * x[k]).sum();
+        x[row] = (rhs[row] - known) / matrix[row][row];
+    }
+    Some(x)
+}
+
 /// Calculates the offset of a parabola's vertex from a center point.
 ///
 /// Given three equidistant points (y_left, y_center, y_right), this function
@@ -343,15 +860,18 @@ fn parabolic_interpolation_offset(y_left: f32, y_center: f32, y_right: f32) -> O
 /// * `signal` - Input audio signal
 /// * `sample_rate` - Sample rate in Hz
 /// * `amplitude_threshold` - Minimum amplitude for pitch detection
+/// * `dip_threshold` - The first dip below this value is taken (see `DEFAULT_DIP_THRESHOLD`)
 /// 
 /// # Returns
-/// * `Some(frequency)` - Detected frequency in Hz
+/// * `Some((frequency, confidence))` - Detected frequency in Hz and confidence (0.0-1.0),
+///   in the same form as `detect_pitch_pyin`
 /// * `None` - No pitch detected (silence, noise, or invalid signal)
 pub fn detect_pitch_yin(
     signal: &[f32],
     sample_rate: u32,
     amplitude_threshold: f32,
-) -> Option<f32> {
+    dip_threshold: f32,
+) -> Option<(f32, f32)> {
     let frame_size = signal.len();
     let mut yin_buffer = vec![0.0; frame_size / 2];
 
@@ -366,10 +886,9 @@ pub fn detect_pitch_yin(
 
     // --- Step 4 & 5: Find the first significant dip to avoid octave errors ---
     let mut period = 0;
-    let threshold = 0.10; // Fixed threshold
 
     for tau in 2..(frame_size / 2) {
-        if yin_buffer[tau] < threshold {
+        if yin_buffer[tau] < dip_threshold {
             // Now check if this is a local minimum
             if yin_buffer[tau] < yin_buffer[tau-1] {
                 period = tau;
@@ -397,8 +916,97 @@ pub fn detect_pitch_yin(
     let frequency = sample_rate as f32 / period_float;
 
     if frequency.is_finite() && frequency > 20.0 {
-        Some(frequency)
+        let confidence = 1.0 - y2;
+        Some((frequency, confidence))
     } else {
         None
     }   
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+    const FFT_SIZE: usize = 2048;
+    const NOISE: f32 = 0.01;
+
+    /// Rippled noise spectrum with a three-bin peak of each given magnitude at each given bin.
+    fn spectrum_with_peaks(peaks: &[(usize, f32)]) -> Vec<f32> {
+        let mut spectrum: Vec<f32> = (0..FFT_SIZE / 2)
+            .map(|bin| NOISE * (1.0 + ((bin * 7) % 5) as f32 / 8.0))
+            .collect();
+        for &(bin, magnitude) in peaks {
+            spectrum[bin - 1] = magnitude * 0.5;
+            spectrum[bin] = magnitude;
+            spectrum[bin + 1] = magnitude * 0.5;
+        }
+        spectrum
+    }
+
+    fn numbers(partials: &[DetectedPartial]) -> Vec<u32> {
+        partials.iter().map(|p| p.number).collect()
+    }
+
+    #[test]
+    fn relative_threshold_drops_weak_partial_that_clears_noise_floor() {
+        // Fundamental on bin 20, a strong 2nd partial and a 3rd that is weak
+        // next to the fundamental but still well above the noise.
+        let f0 = fft::bin_to_frequency(20, SAMPLE_RATE, FFT_SIZE);
+        let spectrum = spectrum_with_peaks(&[(20, 1.0), (40, 0.5), (60, 0.05)]);
+
+        let relative = find_partials(&spectrum, f0, SAMPLE_RATE, FFT_SIZE, 8, PartialThreshold::RelativeToFundamental(0.1), None);
+        let floor = find_partials(&spectrum, f0, SAMPLE_RATE, FFT_SIZE, 8, PartialThreshold::AboveNoiseFloor(3.0), None);
+
+        assert_eq!(numbers(&relative), vec![2]);
+        assert_eq!(numbers(&floor), vec![2, 3]);
+        assert!((floor[0].frequency - 2.0 * f0).abs() < 1.0, "{}", floor[0].frequency);
+        assert!((floor[1].frequency - 3.0 * f0).abs() < 1.0, "{}", floor[1].frequency);
+    }
+
+    #[test]
+    fn relative_threshold_accepts_noise_when_fundamental_is_weak() {
+        // The fundamental barely clears the noise, so a fraction of it is below
+        // the noise floor and every window yields a "partial".
+        let f0 = fft::bin_to_frequency(20, SAMPLE_RATE, FFT_SIZE);
+        let spectrum = spectrum_with_peaks(&[(20, 0.02), (40, 0.5), (60, 0.2)]);
+
+        let relative = find_partials(&spectrum, f0, SAMPLE_RATE, FFT_SIZE, 8, PartialThreshold::RelativeToFundamental(0.1), None);
+        let floor = find_partials(&spectrum, f0, SAMPLE_RATE, FFT_SIZE, 8, PartialThreshold::AboveNoiseFloor(3.0), None);
+
+        assert_eq!(numbers(&relative), (2..=9).collect::<Vec<_>>());
+        assert_eq!(numbers(&floor), vec![2, 3]);
+    }
+
+    #[test]
+    fn refine_rejects_rough_frequency_outside_spectrum() {
+        let spectrum = spectrum_with_peaks(&[(20, 1.0)]);
+        let nyquist = SAMPLE_RATE as f32 / 2.0;
+        for rough in [0.0, -440.0, nyquist, nyquist + 1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(refine_from_spectrum(&spectrum, rough, SAMPLE_RATE, FFT_SIZE), None, "{rough} Hz");
+        }
+
+        let f0 = fft::bin_to_frequency(20, SAMPLE_RATE, FFT_SIZE);
+        let refined = refine_from_spectrum(&spectrum, f0 * 1.01, SAMPLE_RATE, FFT_SIZE).unwrap();
+        assert!((refined - f0).abs() < 1.0, "{refined}");
+    }
+
+    #[test]
+    fn partial_series_fit_finds_stiff_string_in_short_frame() {
+        // A0 held 20 cents sharp: under two periods in a default frame
+        let b = 0.0004;
+        let f1 = 27.5 * 2.0_f32.powf(20.0 / 1200.0);
+        let frame: Vec<f32> = (0..FFT_SIZE)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                (1..=8u32)
+                    .map(|n| (2.0 * std::f32::consts::PI * inharmonicity::partial_frequency(f1, n, b) * t + n as f32).sin() / n as f32)
+                    .sum::<f32>()
+            })
+            .collect();
+
+        let (fitted, confidence) = fit_partial_series(&frame, SAMPLE_RATE, 27.5, b, 100.0).unwrap();
+        assert!((fitted / f1).log2().abs() * 1200.0 < 1.0, "fitted {fitted} Hz for {f1} Hz");
+        assert!(confidence > 0.99);
+        assert_eq!(fit_partial_series(&vec![0.0; FFT_SIZE], SAMPLE_RATE, 27.5, b, 100.0), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! # Temperament Module
+//!
+//! This module computes how the temperament octave of a real piano should be
+//! set so that its intervals beat as they would on an ideal (harmonic) piano
+//! tuned in equal temperament.
+//!
+//! On a stiff string every partial is sharp of `n * f1`, so the partials that
+//! a tuner listens to in a fifth or a third do not sit where equal temperament
+//! assumes. Tuning the fundamentals to equal temperament therefore gives the
+//! wrong beat rates; instead the fundamentals are offset so the coincident
+//! partials beat at the equal-temperament rates.
+//!
+//! ## Features
+//! - Coincident-partial beat rates from measured inharmonicity
+//! - Least-squares offsets for the F3-E4 temperament octave, with A4 fixed
+//! - Expected vs. measured beat rates of an interval, for aural practice
+
+use anyhow::{Result, anyhow};
+use crate::inharmonicity::{self, InharmonicityProfile};
+use crate::tuning;
+
+/// Key index of F3, the first note of the temperament octave.
+pub const TEMPERAMENT_START_KEY: u8 = 32;
+
+/// Key index of A4, the reference pitch the temperament is built around.
+const A4_KEY_INDEX: u8 = 48;
+
+/// Intervals checked in the temperament region, as
+/// (name, semitones, lower note's partial, upper note's partial, weight).
+///
+/// Octaves and fifths/fourths are weighted more heavily because their beats
+/// are slow and any error is easily heard.
+const INTERVALS: [(&str, u8, u32, u32, f32); 6] = [
+    ("minor third", 3, 6, 5, 1.0),
+    ("major third", 4, 5, 4, 1.0),
+    ("fourth", 5, 4, 3, 2.0),
+    ("fifth", 7, 3, 2, 2.0),
+    ("major sixth", 9, 5, 3, 1.0),
+    ("octave", 12, 4, 2, 4.0), // 4:2 octave
+];
+
+/// Beat rate of an interval on an ideal piano compared with the measured one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalBeat {
+    /// Interval name, e.g. "major third"
+    pub name: &'static str,
+    /// Partial of the lower note that beats
+    pub lower_partial: u32,
+    /// Partial of the upper note that beats
+    pub upper_partial: u32,
+    /// Beat rate in equal temperament on a harmonic piano, in Hz
+    pub expected_bps: f32,
+    /// Beat rate between the measured partials, in Hz, if both keys are measured
+    pub measured_bps: Option<f32>,
+}
+
+/// Compares the beat rate of the interval between two keys with equal temperament.
+///
+/// The expected rate is what the interval beats at on a harmonic piano tuned
+/// to equal temperament at A4 = 440 Hz; these are the rates aural tuners
+/// learn (e.g. about 7 beats per second for the F3-A3 major third). The
+/// measured rate comes from the coincident partials stored for both keys,
+/// predicted from the fundamental and 'B' where the partial itself was not
+/// found.
+///
+/// # Arguments
+/// * `profile` - Stored measurements
+/// * `first_key`, `second_key` - Key indices (0-87) in either order
+///
+/// # Returns
+/// * `Some(beat)` - The keys form one of the intervals checked in the temperament
+/// * `None` - Any other interval
+pub fn interval_beat(profile: &InharmonicityProfile, first_key: u8, second_key: u8) -> Option<IntervalBeat> {
+    let (lower_key, upper_key) = (first_key.min(second_key), first_key.max(second_key));
+    let &(name, _, lower_partial, upper_partial, _) = INTERVALS
+        .iter()
+        .find(|interval| interval.1 == upper_key - lower_key)?;
+
+    let lower_freq = tuning::find_nearest_note_by_index(lower_key).1;
+    let upper_freq = tuning::find_nearest_note_by_index(upper_key).1;
+    let expected_bps = beat_rate(lower_freq, 0.0, lower_partial, upper_freq, 0.0, upper_partial);
+
+    let measured_partial = |key: u8, n: u32| {
+        let measurement = profile.measurements.get(&key)?;
+        measurement
+            .partials
+            .iter()
+            .find(|p| p.number == n)
+            .map(|p| p.frequency)
+            .or_else(|| measurement.predict_partial(n))
+    };
+    let measured_bps = measured_partial(lower_key, lower_partial)
+        .zip(measured_partial(upper_key, upper_partial))
+        .map(|(lower, upper)| upper - lower);
+
+    Some(IntervalBeat {
+        name,
+        lower_partial,
+        upper_partial,
+        expected_bps,
+        measured_bps,
+    })
+}
+
+/// Computes the signed beat rate between two coincident partials.
+///
+/// # Arguments
+/// * `lower_freq` - Fundamental of the lower note in Hz
+/// * `lower_b` - Inharmonicity constant of the lower note
+/// * `lower_partial` - Partial of the lower note that is listened to
+/// * `upper_freq` - Fundamental of the upper note in Hz
+/// * `upper_b` - Inharmonicity constant of the upper note
+/// * `upper_partial` - Partial of the upper note that is listened to
+///
+/// # Returns
+/// * Beat rate in Hz (positive = interval wider than pure)
+pub fn beat_rate(
+    lower_freq: f32,
+    lower_b: f32,
+    lower_partial: u32,
+    upper_freq: f32,
+    upper_b: f32,
+    upper_partial: u32,
+) -> f32 {
+    inharmonicity::partial_frequency(upper_freq, upper_partial, upper_b)
+        - inharmonicity::partial_frequency(lower_freq, lower_partial, lower_b)
+}
+
+/// Computes equal-beating offsets for the temperament octave F3-E4.
+///
+/// Every key from F3 up to G#4 is a free variable and A4 is fixed at its
+/// equal temperament frequency. For each interval in that range (minor and
+/// major thirds, fourths, fifths, major sixths and 4:2 octaves) the target is
+/// the beat rate the interval has on a harmonic piano in equal temperament;
+/// the offsets that best reproduce those beat rates with this piano's
+/// inharmonicity are found by weighted least squares. Keys without a
+/// measurement use 'B' interpolated from the measured keys.
+///
+/// # Arguments
+/// * `profile` - Measured inharmonicity; only keys from F3 to A4 are used
+///
+/// # Returns
+/// * `Ok(offsets)` - Offset from equal temperament in cents for F3, F#3, ... E4
+/// * `Err(e)` - No key between F3 and A4 has a measured 'B' value
+pub fn equal_beating_offsets(profile: &InharmonicityProfile) -> Result<[f32; 12]> {
+    let region = InharmonicityProfile {
+        measurements: profile
+            .measurements
+            .range(TEMPERAMENT_START_KEY..=A4_KEY_INDEX)
+            .map(|(&key, m)| (key, m.clone()))
+            .collect(),
+        ..InharmonicityProfile::default()
+    };
+    if region.interpolated_b(A4_KEY_INDEX).is_none() {
+        return Err(anyhow!("No inharmonicity measurements between F3 and A4"));
+    }
+
+    let keys: Vec<u8> = (TEMPERAMENT_START_KEY..=A4_KEY_INDEX).collect();
+    let unknowns = keys.len() - 1; // Every key but A4
+    let equal_tempered: Vec<f32> = keys.iter().map(|&k| tuning::find_nearest_note_by_index(k).1).collect();
+    let b: Vec<f32> = keys.iter().map(|&k| region.interpolated_b(k).unwrap_or(0.0)).collect();
+    // Change in frequency per cent, relative to the frequency
+    let per_cent = std::f32::consts::LN_2 / 1200.0;
+
+    // Normal equations (A^T W A) x = A^T W y of the linearized beat-rate equations
+    let mut normal = vec![vec![0.0f64; unknowns]; unknowns];
+    let mut rhs = vec![0.0f64; unknowns];
+    for (low, _) in keys.iter().enumerate() {
+        for &(_, semitones, lower_partial, upper_partial, weight) in &INTERVALS {
+            let high = low + semitones as usize;
+            if high >= keys.len() {
+                continue;
+            }
+            let lower = inharmonicity::partial_frequency(equal_tempered[low], lower_partial, b[low]);
+            let upper = inharmonicity::partial_frequency(equal_tempered[high], upper_partial, b[high]);
+            let target = beat_rate(equal_tempered[low], 0.0, lower_partial, equal_tempered[high], 0.0, upper_partial);
+
+            // Beat rate is (upper - lower), and each partial moves in proportion to its note's offset
+            let mut row = vec![(low, -lower * per_cent)];
+            if high < unknowns {
+                row.push((high, upper * per_cent));
+            }
+            let residual = target - (upper - lower);
+            for &(i, a_i) in &row {
+                rhs[i] += (weight * a_i * residual) as f64;
+                for &(j, a_j) in &row {
+                    normal[i][j] += (weight * a_i * a_j) as f64;
+                }
+            }
+        }
+    }
+
+    let solution = solve(normal, rhs).ok_or_else(|| anyhow!("Temperament equations are singular"))?;
+    let mut offsets = [0.0f32; 12];
+    for (offset, value) in offsets.iter_mut().zip(&solution) {
+        *offset = *value as f32;
+    }
+    Ok(offsets)
+}
+
+/// Solves a square linear system by Gaussian elimination with partial pivoting.
+fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        for row in (col + 1)..n {
+            let factor = matrix[row][col] / matrix[col][col];
+            let (above, below) = matrix.split_at_mut(row);
+            for (value, pivot_value) in below[0][col..].iter_mut().zip(&above[col][col..]) {
+                *value -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[col];
+        }
+    }
+
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = ((row + 1)..n).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (rhs[row] - sum) / matrix[row][row];
+    }
+    Some(solution)
+}
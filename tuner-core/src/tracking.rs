@@ -0,0 +1,235 @@
+//! # Frame Tracking Module
+//!
+//! `analyze_frame` looks at one frame at a time. Some properties of a note
+//! only show up across many frames, so this module keeps a short history of
+//! consecutive results and fills in the fields of `AnalysisResult` that
+//! depend on it.
+//!
+//! ## Features
+//! - False-beat detection: rate and depth of amplitude modulation of the
+//!   fundamental while the same note sustains
+//! - Spectral flux: how much the spectrum grew since the previous frame,
+//!   to tell a still-evolving attack from a steady sustain
+//! - Energy trend: whether the frame RMS is rising, steady or falling
+
+use std::collections::VecDeque;
+use crate::AnalysisResult;
+
+/// Frames of fundamental magnitude kept for false-beat detection (~3 s at 44.1 kHz).
+const MODULATION_FRAMES: usize = 64;
+
+/// Frames needed before modulation is estimated (~1.1 s at 44.1 kHz).
+const MIN_MODULATION_FRAMES: usize = 24;
+
+/// Slowest modulation reported, in Hz.
+const MIN_MODULATION_HZ: f32 = 0.5;
+
+/// Fastest modulation reported, in Hz.
+const MAX_MODULATION_HZ: f32 = 8.0;
+
+/// Step between candidate modulation rates, in Hz.
+const MODULATION_STEP_HZ: f32 = 0.05;
+
+/// Smallest peak-to-peak modulation reported, in dB.
+const MIN_MODULATION_DEPTH_DB: f32 = 1.0;
+
+/// Fraction of the magnitude fluctuation the modulation must explain.
+const MIN_MODULATION_FIT: f32 = 0.5;
+
+/// Frames of RMS level the energy trend is fitted over (~0.28 s at 44.1 kHz).
+const ENERGY_TREND_FRAMES: usize = 6;
+
+/// Slope of the RMS level, in dB per second, beyond which it is no longer steady.
+///
+/// A sustaining piano note typically loses 3 to 30 dB per second, so a
+/// gentle decay still reads as steady while the hammer attack clearly rises.
+const STEADY_DB_PER_SECOND: f32 = 3.0;
+
+/// Direction the short-term energy of the signal is moving in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnergyTrend {
+    /// Getting louder: a note is being struck or is blooming
+    Rising,
+    /// Level holding within `STEADY_DB_PER_SECOND`: the note sustains
+    Steady,
+    /// Getting quieter: the note is decaying
+    Falling,
+}
+
+/// Keeps the history of consecutive analysis frames.
+///
+/// The audio thread owns one tracker and passes every result through
+/// [`FrameTracker::track`] right after `analyze_frame`. The history starts
+/// over whenever the detected note changes or the fundamental is lost.
+#[derive(Debug, Default)]
+pub struct FrameTracker {
+    note_name: Option<String>,
+    magnitudes_db: VecDeque<f32>,
+    previous_spectrum: Vec<f32>,
+    rms_db: VecDeque<f32>,
+}
+
+impl FrameTracker {
+    /// Creates a tracker with an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a frame to the history and fills in its cross-frame fields.
+    ///
+    /// # Arguments
+    /// * `result` - Result of the frame just analyzed; updated in place
+    /// * `frame_seconds` - Time between consecutive frames in seconds
+    pub fn track(&mut self, result: &mut AnalysisResult, frame_seconds: f32) {
+        let magnitude = result.fundamental_magnitude.filter(|&m| m > 0.0);
+        if result.note_name.is_none() || result.note_name != self.note_name || magnitude.is_none() {
+            self.magnitudes_db.clear();
+            self.note_name = result.note_name.clone();
+        }
+
+        if let Some(magnitude) = magnitude {
+            if self.magnitudes_db.len() == MODULATION_FRAMES {
+                self.magnitudes_db.pop_front();
+            }
+            self.magnitudes_db.push_back(20.0 * magnitude.log10());
+        }
+
+        if let Some((rate, depth)) = self.modulation(frame_seconds) {
+            result.modulation_hz = Some(rate);
+            result.modulation_depth_db = Some(depth);
+        }
+
+        result.spectral_flux = spectral_flux(&self.previous_spectrum, &result.spectrogram_data);
+        self.previous_spectrum.clone_from(&result.spectrogram_data);
+
+        // Silence has no trend and would drag the fit towards -inf
+        if result.rms > 0.0 {
+            if self.rms_db.len() == ENERGY_TREND_FRAMES {
+                self.rms_db.pop_front();
+            }
+            self.rms_db.push_back(20.0 * result.rms.log10());
+        } else {
+            self.rms_db.clear();
+        }
+        result.energy_trend = self.energy_trend(frame_seconds);
+    }
+
+    /// Classifies the slope of a least-squares line through the recent RMS levels.
+    ///
+    /// # Returns
+    /// * `Some(trend)` - The history is full
+    /// * `None` - Fewer than `ENERGY_TREND_FRAMES` frames with signal
+    fn energy_trend(&self, frame_seconds: f32) -> Option<EnergyTrend> {
+        let n = self.rms_db.len();
+        if n < ENERGY_TREND_FRAMES || frame_seconds <= 0.0 {
+            return None;
+        }
+        let mean_x = (n - 1) as f32 / 2.0;
+        let mean_y = self.rms_db.iter().sum::<f32>() / n as f32;
+        let (mut sxy, mut sxx) = (0.0, 0.0);
+        for (i, &y) in self.rms_db.iter().enumerate() {
+            let dx = i as f32 - mean_x;
+            sxy += dx * (y - mean_y);
+            sxx += dx * dx;
+        }
+        let db_per_second = sxy / sxx / frame_seconds;
+        Some(if db_per_second > STEADY_DB_PER_SECOND {
+            EnergyTrend::Rising
+        } else if db_per_second < -STEADY_DB_PER_SECOND {
+            EnergyTrend::Falling
+        } else {
+            EnergyTrend::Steady
+        })
+    }
+
+    /// Finds the dominant amplitude modulation of the tracked fundamental.
+    ///
+    /// The decay of the note is removed with a straight-line fit to the
+    /// magnitude in dB; the residual is then compared with sinusoids across
+    /// the candidate rates, and the best one is reported if it is deep enough
+    /// and explains most of the residual.
+    ///
+    /// # Returns
+    /// * `Some((rate_hz, peak_to_peak_db))` - A false beat was found
+    /// * `None` - Not enough history, or the amplitude is steady
+    fn modulation(&self, frame_seconds: f32) -> Option<(f32, f32)> {
+        let n = self.magnitudes_db.len();
+        if n < MIN_MODULATION_FRAMES || frame_seconds <= 0.0 {
+            return None;
+        }
+
+        // Remove the decay with a least-squares line
+        let mean_x = (n - 1) as f32 / 2.0;
+        let mean_y = self.magnitudes_db.iter().sum::<f32>() / n as f32;
+        let (mut sxy, mut sxx) = (0.0, 0.0);
+        for (i, &y) in self.magnitudes_db.iter().enumerate() {
+            let dx = i as f32 - mean_x;
+            sxy += dx * (y - mean_y);
+            sxx += dx * dx;
+        }
+        let slope = sxy / sxx;
+        let residual: Vec<f32> = self.magnitudes_db
+            .iter()
+            .enumerate()
+            .map(|(i, &y)| y - mean_y - slope * (i as f32 - mean_x))
+            .collect();
+        let variance = residual.iter().map(|r| r * r).sum::<f32>() / n as f32;
+        if variance <= 0.0 {
+            return None;
+        }
+
+        // At least one full cycle must fit in the history, and stay below the frame Nyquist rate
+        let duration = n as f32 * frame_seconds;
+        let lowest = MIN_MODULATION_HZ.max(1.0 / duration);
+        let highest = MAX_MODULATION_HZ.min(0.45 / frame_seconds);
+        let mut best: Option<(f32, f32)> = None;
+        let mut rate = lowest;
+        while rate <= highest {
+            let omega = std::f32::consts::TAU * rate * frame_seconds;
+            let (re, im) = residual.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, &r)| {
+                let phase = omega * i as f32;
+                (re + r * phase.cos(), im - r * phase.sin())
+            });
+            let amplitude = 2.0 * (re * re + im * im).sqrt() / n as f32;
+            if best.is_none_or(|(_, a)| amplitude > a) {
+                best = Some((rate, amplitude));
+            }
+            rate += MODULATION_STEP_HZ;
+        }
+
+        let (rate, amplitude) = best?;
+        let explained = amplitude * amplitude / 2.0 / variance;
+        let depth = 2.0 * amplitude;
+        (depth >= MIN_MODULATION_DEPTH_DB && explained >= MIN_MODULATION_FIT).then_some((rate, depth))
+    }
+}
+
+/// Measures how much a spectrum grew since the previous frame.
+///
+/// Only increases in magnitude are counted (half-wave rectified flux), so the
+/// natural decay of a held note reads as steady while an attack, a new note
+/// or a rising false beat does not. The sum is normalized by the current
+/// spectrum's total magnitude, so the result does not depend on loudness.
+///
+/// # Arguments
+/// * `previous` - Magnitude spectrum of the previous frame
+/// * `current` - Magnitude spectrum of this frame
+///
+/// # Returns
+/// * `Some(flux)` - 0.0 for a steady or decaying spectrum, larger as it changes
+/// * `None` - No comparable previous frame, or a silent spectrum
+pub fn spectral_flux(previous: &[f32], current: &[f32]) -> Option<f32> {
+    if previous.len() != current.len() {
+        return None;
+    }
+    let total: f32 = current.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let growth: f32 = previous
+        .iter()
+        .zip(current)
+        .map(|(&before, &now)| (now - before).max(0.0))
+        .sum();
+    Some(growth / total)
+}
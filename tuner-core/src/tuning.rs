@@ -6,10 +6,17 @@
 //! 
 //! ## Features
 //! - 88-key piano note mapping (A0 to C8)
+//! - Extended keyboards (e.g. 97-key Bösendorfer Imperial) via `KeyboardSpec`
 //! - Equal temperament frequency calculations
 //! - Cent deviation calculations for tuning accuracy
 //! - Note name to frequency conversions
 //! - Key index to note name mappings
+//! - Selectable octave numbering convention for displayed note names
+//! - Inharmonicity-based stretch curve for the whole keyboard
+//! - Piano-size presets for stretch before any key is measured
+//! - Single-call pitch reading (note, target and cents) for auto and manual modes
+//! - Scala (`.scl`) scale import/export
+//! - Naming the interval between two sounding notes, with its consonance
 //! - **Future**: Inharmonicity compensation for professional piano tuning
 //! 
 //! ## Planned Inharmonicity Features
@@ -20,6 +27,40 @@
 
 use once_cell::sync::Lazy;
 use std::collections::BTreeMap;
+use anyhow::{Result, anyhow};
+use crate::inharmonicity::{self, InharmonicityProfile, PianoType};
+
+/// Concert pitch the note table is computed against, in Hz.
+pub const REFERENCE_A4_HZ: f32 = 440.0;
+
+/// Note letters in key order, starting from A (the lowest piano key).
+const NOTE_NAMES: [&str; 12] = [
+    "A", "A#", "B", "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#",
+];
+
+/// How octave numbers are assigned in note names.
+///
+/// Only the names change; frequencies and key indices are the same in every
+/// convention. Names stored in analysis results and profiles are always
+/// `Scientific`, so conversion happens only when names are shown or parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OctaveConvention {
+    /// Scientific pitch notation: middle C is C4, the keyboard spans A0 to C8
+    #[default]
+    Scientific,
+    /// Yamaha convention: middle C is C3, the keyboard spans A-1 to C7
+    Yamaha,
+}
+
+impl OctaveConvention {
+    /// Offset added to the scientific octave number.
+    fn octave_offset(self) -> i32 {
+        match self {
+            OctaveConvention::Scientific => 0,
+            OctaveConvention::Yamaha => -1,
+        }
+    }
+}
 
 /// Represents a single musical note with its name and frequency.
 #[derive(Debug, Clone)]
@@ -30,33 +71,136 @@ pub struct Note {
     pub frequency: f32,
 }
 
+/// Builds the note `semitones` above A0 (negative = below A0).
+fn note_from_a0(semitones: i32) -> Note {
+    // A4 is 48 semitones above A0.
+    // The formula for frequency in equal temperament is f = f0 * 2^(n/12)
+    // Here, f0 is A4 (440Hz) and n is the number of semitones away from A4.
+    let frequency = REFERENCE_A4_HZ * 2.0_f32.powf((semitones - 48) as f32 / 12.0);
+
+    // The note name cycles every 12 keys, starting from A.
+    let note_index = semitones.rem_euclid(12) as usize;
+    // The octave changes at C, nine semitones above A.
+    let octave = (semitones + 9).div_euclid(12);
+    let name = format!("{}{}", NOTE_NAMES[note_index], octave);
+
+    Note { name, frequency }
+}
+
+/// Range of keys on a piano.
+///
+/// Key indices throughout the crate (profiles, stretch curves, manual key
+/// selection) count from A0 on the standard 88-key range. A keyboard spec
+/// describes which keys a particular instrument has, so detection and the
+/// on-screen keyboard can cover keys beyond that range. Those extra keys have
+/// no key index, so they can't be stored in a profile: capturing one fails with
+/// `CaptureError::OutsideProfileRange`, and they can't be tuned in manual mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyboardSpec {
+    /// Lowest key, in semitones from A0 (negative = below A0)
+    pub lowest_key: i32,
+    /// Number of keys
+    pub key_count: u8,
+}
+
+impl KeyboardSpec {
+    /// Standard 88-key piano, A0 to C8.
+    pub const STANDARD: KeyboardSpec = KeyboardSpec { lowest_key: 0, key_count: 88 };
+
+    /// Bösendorfer Imperial, 97 keys from C0 to C8.
+    pub const IMPERIAL: KeyboardSpec = KeyboardSpec { lowest_key: -9, key_count: 97 };
+
+    /// Returns every key of the keyboard, lowest first.
+    pub fn notes(self) -> Vec<Note> {
+        (0..self.key_count as i32).map(|p| note_from_a0(self.lowest_key + p)).collect()
+    }
+
+    /// Returns the key at `position` (0 = lowest key), if the keyboard has one.
+    pub fn note(self, position: usize) -> Option<Note> {
+        (position < self.key_count as usize).then(|| note_from_a0(self.lowest_key + position as i32))
+    }
+
+    /// Returns true if the key at `position` (0 = lowest key) is a black key.
+    pub fn is_black(self, position: usize) -> bool {
+        // A, A#, B, C, C#, D, D#, E, F, F#, G, G#
+        const IS_BLACK: [bool; 12] = [false, true, false, false, true, false, true, false, false, true, false, true];
+        IS_BLACK[(self.lowest_key + position as i32).rem_euclid(12) as usize]
+    }
+
+    /// Converts a position on this keyboard to a standard 88-key index.
+    ///
+    /// # Returns
+    /// * `Some(key_index)` - Key index (0-87)
+    /// * `None` - The key is outside the standard range
+    pub fn key_index(self, position: usize) -> Option<u8> {
+        u8::try_from(self.lowest_key + position as i32).ok().filter(|&k| k < 88)
+    }
+
+    /// Converts a standard 88-key index to a position on this keyboard.
+    pub fn position(self, key_index: u8) -> Option<usize> {
+        usize::try_from(key_index as i32 - self.lowest_key)
+            .ok()
+            .filter(|&p| p < self.key_count as usize)
+    }
+
+    /// Returns the position of a (scientific) note name on this keyboard.
+    pub fn position_of_name(self, name: &str) -> Option<usize> {
+        let split = name.find(|c: char| c == '-' || c.is_ascii_digit())?;
+        let (letter, octave) = name.split_at(split);
+        let octave: i32 = octave.parse().ok()?;
+        let note_index = NOTE_NAMES.iter().position(|&n| n == letter)? as i32;
+        // Inverse of `note_from_a0`: A, A# and B belong to the octave below the next C
+        let semitones = if note_index < 3 { 12 * octave + note_index } else { 12 * (octave - 1) + note_index };
+        usize::try_from(semitones - self.lowest_key)
+            .ok()
+            .filter(|&p| p < self.key_count as usize)
+    }
+
+    /// Finds the key of this keyboard closest to a frequency.
+    ///
+    /// # Returns
+    /// * `(note_name, target_frequency)` - Closest note name and its frequency
+    pub fn nearest_note(self, freq: f32) -> (String, f32) {
+        if self == KeyboardSpec::STANDARD {
+            return find_nearest_note(freq);
+        }
+        let closest = self
+            .notes()
+            .into_iter()
+            .min_by(|a, b| (a.frequency - freq).abs().total_cmp(&(b.frequency - freq).abs()))
+            .unwrap_or_else(|| note_from_a0(self.lowest_key));
+        (closest.name, closest.frequency)
+    }
+
+    /// Checks whether a frequency falls on this keyboard.
+    ///
+    /// # Returns
+    /// * `true` - Within half a semitone of the lowest key and the highest key
+    /// * `false` - Further below or above, so the nearest key would be misleading
+    pub fn contains_frequency(self, freq: f32) -> bool {
+        let lowest = note_from_a0(self.lowest_key).frequency;
+        let highest = note_from_a0(self.lowest_key + self.key_count as i32 - 1).frequency;
+        calculate_cents_deviation(freq, lowest) >= -50.0 && calculate_cents_deviation(freq, highest) <= 50.0
+    }
+
+    /// Returns a short description of the keyboard, e.g. "88 keys".
+    pub fn label(self) -> String {
+        format!("{} keys", self.key_count)
+    }
+}
+
+impl Default for KeyboardSpec {
+    fn default() -> Self {
+        KeyboardSpec::STANDARD
+    }
+}
+
 /// Statically computed notes for a standard 88-key piano (A0 to C8).
 /// 
 /// This lazy static contains all 88 piano keys with their corresponding
 /// frequencies calculated using equal temperament tuning with A4 = 440 Hz.
 /// The notes are computed once at startup for optimal performance.
-static NOTES: Lazy<Vec<Note>> = Lazy::new(|| {
-    const NOTE_NAMES: [&str; 12] = [
-        "A", "A#", "B", "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#",
-    ];
-    let mut notes = Vec::with_capacity(88);
-
-    for i in 0..88 {
-        // A4 is the 49th key, which is index 48 in a 0-indexed loop.
-        // The formula for frequency in equal temperament is f = f0 * 2^(n/12)
-        // Here, f0 is A4 (440Hz) and n is the number of semitones away from A4.
-        let frequency = 440.0 * 2.0_f32.powf((i as f32 - 48.0) / 12.0);
-
-        // A piano starts at A0. The note name cycles every 12 keys.
-        let note_index = i % 12;
-        // The octave changes at C. We can calculate it based on the key index.
-        let octave = (i + 9) / 12;
-        let name = format!("{}{}", NOTE_NAMES[note_index], octave);
-
-        notes.push(Note { name, frequency });
-    }
-    notes
-});
+static NOTES: Lazy<Vec<Note>> = Lazy::new(|| KeyboardSpec::STANDARD.notes());
 
 /// Static map for quick note name to key index lookups.
 /// 
@@ -122,6 +266,63 @@ pub fn get_key_index_from_name(name: &str) -> u8 {
     *NOTE_MAP.get(name).unwrap_or(&0)
 }
 
+/// Returns the name of a piano key in the given octave convention.
+///
+/// # Arguments
+/// * `key_index` - Piano key index (0-87)
+/// * `convention` - Octave numbering convention to use
+///
+/// # Returns
+/// * Note name, e.g. "C4" (Scientific) or "C3" (Yamaha) for middle C
+pub fn key_name(key_index: u8, convention: OctaveConvention) -> String {
+    let i = key_index as i32;
+    let octave = (i + 9) / 12 + convention.octave_offset();
+    format!("{}{}", NOTE_NAMES[(i % 12) as usize], octave)
+}
+
+/// Gets the 88-key piano index from a note name written in the given octave convention.
+///
+/// This is the inverse of `key_name`; with `OctaveConvention::Scientific` it
+/// accepts the same names as `get_key_index_from_name`.
+///
+/// # Arguments
+/// * `name` - Note name (e.g., "A4", "C#3", "A-1")
+/// * `convention` - Octave numbering convention the name is written in
+///
+/// # Returns
+/// * `Some(key_index)` - Piano key index (0-87)
+/// * `None` - The name is malformed or outside the 88-key range
+pub fn key_index_from_name_in(name: &str, convention: OctaveConvention) -> Option<u8> {
+    let split = name.find(|c: char| c == '-' || c.is_ascii_digit())?;
+    let (letter, octave) = name.split_at(split);
+    let octave: i32 = octave.parse().ok()?;
+
+    let scientific = format!("{}{}", letter, octave - convention.octave_offset());
+    NOTE_MAP.get(&scientific).copied()
+}
+
+/// Rewrites a stored (scientific) note name in another octave convention for display.
+///
+/// # Arguments
+/// * `name` - Note name in scientific notation, as found in analysis results
+/// * `convention` - Octave numbering convention to display in
+///
+/// # Returns
+/// * The converted name, or `name` unchanged if it is not a note name
+pub fn display_note_name(name: &str, convention: OctaveConvention) -> String {
+    match NOTE_MAP.get(name) {
+        Some(&key_index) => key_name(key_index, convention),
+        // Keys of extended keyboards (e.g. C0) are not in the 88-key map
+        None => match name.find(|c: char| c == '-' || c.is_ascii_digit()) {
+            Some(split) => match name[split..].parse::<i32>() {
+                Ok(octave) => format!("{}{}", &name[..split], octave + convention.octave_offset()),
+                Err(_) => name.to_string(),
+            },
+            None => name.to_string(),
+        },
+    }
+}
+
 /// Calculates the deviation from a target frequency in cents.
 ///
 /// Cents are a logarithmic unit of pitch measurement where:
@@ -139,33 +340,620 @@ pub fn calculate_cents_deviation(freq: f32, target_freq: f32) -> f32 {
     1200.0 * (freq / target_freq).log2()
 }
 
+/// Cents from a consonant just ratio within which the interval still sounds
+/// consonant; equal-tempered thirds (14 and 16 cents off) just make it.
+pub const CONSONANT_CENTS: f32 = 16.0;
+
+/// Just intervals within an octave, as (name, numerator, denominator, consonant).
+const JUST_INTERVALS: [(&str, u32, u32, bool); 12] = [
+    ("Unison", 1, 1, true),
+    ("Minor 2nd", 16, 15, false),
+    ("Major 2nd", 9, 8, false),
+    ("Minor 3rd", 6, 5, true),
+    ("Major 3rd", 5, 4, true),
+    ("Perfect 4th", 4, 3, true),
+    ("Tritone", 45, 32, false),
+    ("Perfect 5th", 3, 2, true),
+    ("Minor 6th", 8, 5, true),
+    ("Major 6th", 5, 3, true),
+    ("Minor 7th", 9, 5, false),
+    ("Major 7th", 15, 8, false),
+];
+
+/// The named interval closest to the ratio of two frequencies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntervalMatch {
+    /// Interval name within the octave, e.g. "Perfect 5th"
+    pub name: &'static str,
+    /// Just ratio the interval is compared with, as numerator/denominator
+    pub numerator: u32,
+    pub denominator: u32,
+    /// Whole octaves added to `name` (1 for a 12th = octave + 5th, or a plain octave)
+    pub octaves: u32,
+    /// Deviation from the just ratio in cents (positive = wider)
+    pub cents_error: f32,
+    /// The just ratio is consonant and the interval is within `CONSONANT_CENTS` of it
+    pub consonant: bool,
+}
+
+impl std::fmt::Display for IntervalMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unison = self.numerator == self.denominator;
+        match self.octaves {
+            0 => write!(f, "{}", self.name),
+            1 if unison => write!(f, "Octave"),
+            n if unison => write!(f, "{} octaves", n),
+            1 => write!(f, "{} + octave", self.name),
+            n => write!(f, "{} + {} octaves", self.name, n),
+        }
+    }
+}
+
+/// Names the interval between two frequencies.
+///
+/// The ratio is folded into one octave and matched against the just
+/// intervals; the closer a sounding interval is to a simple integer ratio
+/// the smoother it sounds, so consonance is judged by that distance.
+///
+/// # Arguments
+/// * `lower_freq`, `upper_freq` - The two frequencies in Hz, in either order
+///
+/// # Returns
+/// * `Some(interval)` - The nearest named interval
+/// * `None` - A frequency is not positive
+pub fn nearest_interval(lower_freq: f32, upper_freq: f32) -> Option<IntervalMatch> {
+    if lower_freq <= 0.0 || upper_freq <= 0.0 {
+        return None;
+    }
+    let cents = calculate_cents_deviation(lower_freq.max(upper_freq), lower_freq.min(upper_freq));
+    // Fold half a semitone below the octave too, so a flat octave is still an octave
+    let octaves = ((cents + 50.0) / 1200.0).floor();
+    let within_octave = cents - octaves * 1200.0;
+
+    let (name, numerator, denominator, consonant, cents_error) = JUST_INTERVALS
+        .iter()
+        .map(|&(name, numerator, denominator, consonant)| {
+            let just = 1200.0 * (numerator as f32 / denominator as f32).log2();
+            (name, numerator, denominator, consonant, within_octave - just)
+        })
+        .min_by(|a, b| a.4.abs().total_cmp(&b.4.abs()))?;
+
+    Some(IntervalMatch {
+        name,
+        numerator,
+        denominator,
+        octaves: octaves as u32,
+        cents_error,
+        consonant: consonant && cents_error.abs() <= CONSONANT_CENTS,
+    })
+}
+
+/// Which partials are matched when octaves are stretched.
+///
+/// Each variant makes a partial of the lower note coincide with a partial of
+/// the note an octave above. Wider ratios give more stretch because higher
+/// partials are more inharmonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StretchStrategy {
+    /// Lower note's 2nd partial matches the upper note's fundamental
+    #[default]
+    Octave2To1,
+    /// Lower note's 4th partial matches the upper note's 2nd partial
+    Octave4To2,
+    /// Lower note's 6th partial matches the upper note's 3rd partial
+    Octave6To3,
+}
+
+impl StretchStrategy {
+    /// Returns the (lower note, upper note) partial numbers that are matched.
+    fn partials(self) -> (u32, u32) {
+        match self {
+            StretchStrategy::Octave2To1 => (2, 1),
+            StretchStrategy::Octave4To2 => (4, 2),
+            StretchStrategy::Octave6To3 => (6, 3),
+        }
+    }
+}
+
+/// Key index of A4, the reference pitch and fixed point of the stretch curve.
+pub const A4_KEY_INDEX: usize = 48;
+
+/// Key index of A3, the bottom of the temperament octave.
+const A3_KEY_INDEX: usize = 36;
+
+/// Computes the stretch of every key, in cents from equal temperament.
+///
+/// A4 is fixed at 0 cents. A3 is tuned to A4 with the chosen octave, and the
+/// keys between them divide that stretched octave equally (the temperament
+/// octave). Every other key is then tuned by octaves outward: keys above
+/// from the key an octave below, keys below from the key an octave above.
+/// Each key uses its measured 'B' value, then the preset's value if one is
+/// given, then one interpolated from the profile; with no measurements and
+/// no preset the curve is flat (pure equal temperament).
+///
+/// # Arguments
+/// * `profile` - Measured inharmonicity of the piano
+/// * `strategy` - Which partials are matched across each octave
+/// * `preset` - Piano size used for keys without a measurement
+///
+/// # Returns
+/// * `[f32; 88]` - Offset from equal temperament in cents for each key (0 = A0)
+pub fn stretch_curve(profile: &InharmonicityProfile, strategy: StretchStrategy, preset: Option<PianoType>) -> [f32; 88] {
+    let (lower_partial, upper_partial) = strategy.partials();
+    let b: Vec<f32> = (0..88u8).map(|k| profile.b_for_key(k, preset).unwrap_or(0.0)).collect();
+    // Ratio of partial n to the first partial for a given key
+    let ratio = |key: usize, n: u32| inharmonicity::partial_frequency(1.0, n, b[key]);
+
+    let mut frequency = [0.0f32; 88];
+    frequency[A4_KEY_INDEX] = NOTES[A4_KEY_INDEX].frequency;
+
+    // Each step sets the upper key so its partial matches the lower key's, or vice versa
+    let tune_up = |frequency: &mut [f32; 88], key: usize| {
+        let low = key - 12;
+        frequency[key] = frequency[low] * ratio(low, lower_partial) / ratio(key, upper_partial);
+    };
+    let tune_down = |frequency: &mut [f32; 88], key: usize| {
+        let high = key + 12;
+        frequency[key] = frequency[high] * ratio(high, upper_partial) / ratio(key, lower_partial);
+    };
+
+    // Temperament octave: A3 from A4, then equal division of the stretched octave
+    tune_down(&mut frequency, A3_KEY_INDEX);
+    let octave_cents = calculate_cents_deviation(frequency[A4_KEY_INDEX], frequency[A3_KEY_INDEX]);
+    for key in (A3_KEY_INDEX + 1)..A4_KEY_INDEX {
+        let steps = (key - A3_KEY_INDEX) as f32;
+        frequency[key] = frequency[A3_KEY_INDEX] * 2.0_f32.powf(steps * octave_cents / 12.0 / 1200.0);
+    }
+
+    for key in (A4_KEY_INDEX + 1)..88 {
+        tune_up(&mut frequency, key);
+    }
+    for key in (0..A3_KEY_INDEX).rev() {
+        tune_down(&mut frequency, key);
+    }
+
+    let mut cents = [0.0f32; 88];
+    for (key, offset) in cents.iter_mut().enumerate() {
+        *offset = calculate_cents_deviation(frequency[key], NOTES[key].frequency);
+    }
+    cents
+}
+
 /// Calculates inharmonicity-compensated target frequency for professional piano tuning.
 /// 
-/// **Note**: This function is planned for future implementation and currently returns
-/// the equal temperament frequency. Inharmonicity compensation will account for:
-/// - Piano string stiffness and inharmonicity
-/// - Stretch tuning for different piano sizes
-/// - Partial frequency adjustments
-/// - Professional tuning curve generation
+/// The equal temperament frequency is offset by the key's entry in the
+/// [`stretch_curve`] (2:1 octaves), cached by the profile so this is cheap
+/// to call for every frame. Measured keys always use their own 'B'
+/// value; unmeasured keys use the piano type's preset curve when one is
+/// given, so the tuner gives a usable stretch before anything is measured.
 /// 
 /// # Arguments
 /// * `key_index` - Piano key index (0-87)
-/// * `piano_type` - Type of piano (grand, upright, etc.) - future parameter
+/// * `profile` - Measured inharmonicity of the piano
+/// * `piano_type` - Piano size preset for unmeasured keys (None = measurements only)
 /// 
 /// # Returns
-/// * Target frequency with inharmonicity compensation (currently equal temperament)
-/// 
-/// # Future Implementation
-/// This function will implement the inharmonicity calculations described in:
-/// - Young's inharmonicity model
-/// - Piano-specific stretch tuning curves
-/// - Partial frequency analysis and compensation
+/// * Target frequency with inharmonicity compensation
 pub fn calculate_inharmonicity_compensated_frequency(
     key_index: u8,
-    _piano_type: &str, // Reserved for future piano type parameter
+    profile: &InharmonicityProfile,
+    piano_type: Option<PianoType>,
 ) -> f32 {
-    // TODO: Implement inharmonicity compensation
-    // For now, return equal temperament frequency
     let (_, freq) = find_nearest_note_by_index(key_index);
-    freq
+    let curve = profile.stretch_cents(piano_type);
+    freq * 2.0_f32.powf(curve[key_index.min(87) as usize] / 1200.0)
+}
+
+/// Tuning mode for the piano tuner.
+/// 
+/// Determines whether the tuner follows whatever note is played or tunes
+/// one selected piano key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TuningMode {
+    /// Automatic pitch detection mode - detects any note being played
+    Auto,
+    /// Manual mode - user has selected a specific piano key to tune
+    Manual {
+        key_index: u8,        // Piano key index (0-87)
+        note_name: String,    // Note name (e.g., "A4", "C#3")
+        target_freq: f32,     // Target frequency in Hz
+    },
+}
+
+/// How far, in cents, auto mode lets the pitch stray from the note it is
+/// locked to before switching to the neighbour. Past the 50-cent midpoint,
+/// so a pitch sitting between two notes doesn't make the name flicker.
+pub const NOTE_LOCK_CENTS: f32 = 60.0;
+
+/// How the target of a manually selected key is derived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetOptions {
+    /// Partial of the key that is tuned (1 = fundamental)
+    pub partial: u32,
+    /// Offset the fundamental by the stretch curve
+    pub stretch: bool,
+    /// Piano size preset for unmeasured keys, used only with `stretch`
+    pub piano_type: Option<PianoType>,
+    /// Keys auto mode matches against
+    pub keyboard: KeyboardSpec,
+    /// Position on `keyboard` of the note auto mode is locked to; it is kept
+    /// while the pitch stays within `NOTE_LOCK_CENTS` of it
+    pub locked_note: Option<usize>,
+}
+
+impl Default for TargetOptions {
+    fn default() -> Self {
+        Self { partial: 1, stretch: false, piano_type: None, keyboard: KeyboardSpec::STANDARD, locked_note: None }
+    }
+}
+
+/// A frequency compared against the note it is being tuned to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PitchReading {
+    /// Scientific name of the note the frequency is compared against
+    pub note_name: String,
+    /// Frequency the measured partial should have, in Hz
+    pub target_freq: f32,
+    /// Deviation from the target (positive = sharp, negative = flat)
+    pub cents: f32,
+}
+
+/// Returns the frequency partial `n` of a key should have.
+///
+/// Uses the key's measured 'B' value when the profile has one, so the
+/// target follows the real (stretched) partial rather than `n * f0`. With
+/// stretch enabled the fundamental itself is first offset by the stretch
+/// curve, and unmeasured keys take their 'B' from the piano-size preset.
+///
+/// # Arguments
+/// * `key_index` - Piano key index (0-87)
+/// * `profile` - Measured inharmonicity of the piano
+/// * `options` - Target partial and stretch settings
+///
+/// # Returns
+/// * Target frequency of the partial in Hz, at A4 = 440 Hz
+pub fn partial_target_frequency(key_index: u8, profile: &InharmonicityProfile, options: &TargetOptions) -> f32 {
+    let preset = options.piano_type.filter(|_| options.stretch);
+    let b = match preset {
+        Some(_) => profile.b_for_key(key_index, preset),
+        None => profile.measurements.get(&key_index).and_then(|m| m.calculated_b),
+    }
+    .unwrap_or(0.0);
+    let fundamental = if options.stretch {
+        calculate_inharmonicity_compensated_frequency(key_index, profile, preset)
+    } else {
+        find_nearest_note_by_index(key_index).1
+    };
+    inharmonicity::partial_frequency(fundamental, options.partial.max(1), b)
+}
+
+/// Predicts where the partials of a key lie for a given 'B' value.
+///
+/// Works without a measurement of the key, e.g. with 'B' from a piano-size
+/// preset or [`InharmonicityProfile::interpolated_b`], so target markers can
+/// be drawn before the key has been measured. The fundamental is the key's
+/// equal-tempered frequency and partial `n` is `n * f1 * sqrt(1 + B * n²)`,
+/// as in `KeyMeasurement::predict_partial`.
+///
+/// # Arguments
+/// * `key_index` - Piano key index (0-87)
+/// * `b` - Inharmonicity constant to assume
+/// * `count` - Number of partials, starting with the fundamental
+/// * `a4_hz` - Concert pitch in Hz
+///
+/// # Returns
+/// * Frequencies of partials 1 to `count` in Hz; empty for a key outside 0-87
+pub fn expected_partials(key_index: u8, b: f32, count: u32, a4_hz: f32) -> Vec<f32> {
+    let Some(note) = NOTES.get(key_index as usize) else {
+        return Vec::new();
+    };
+    let fundamental = note.frequency * a4_hz / REFERENCE_A4_HZ;
+    (1..=count)
+        .map(|n| inharmonicity::partial_frequency(fundamental, n, b))
+        .collect()
+}
+
+/// Compares a measured frequency with the note it should be tuned to.
+///
+/// In auto mode `freq` is the detected fundamental and is compared with the
+/// nearest equal-tempered key of `options.keyboard`, or with
+/// `options.locked_note` while it is within `NOTE_LOCK_CENTS`. In manual mode `freq` is
+/// the measured frequency of `options.partial` of the selected key and is
+/// compared with that partial's target from [`partial_target_frequency`].
+/// Every target is scaled from A4 = 440 Hz to `a4_hz`.
+///
+/// # Arguments
+/// * `freq` - Measured frequency in Hz
+/// * `mode` - Auto or manual tuning mode
+/// * `profile` - Measured inharmonicity of the piano
+/// * `options` - Keyboard range (auto mode), target partial and stretch settings (manual mode)
+/// * `a4_hz` - Concert pitch in Hz
+///
+/// # Returns
+/// * `PitchReading` - Note name, target frequency and deviation in cents
+pub fn analyze_pitch(
+    freq: f32,
+    mode: &TuningMode,
+    profile: &InharmonicityProfile,
+    options: &TargetOptions,
+    a4_hz: f32,
+) -> PitchReading {
+    let scale = a4_hz / REFERENCE_A4_HZ;
+    let (note_name, target_freq) = match mode {
+        TuningMode::Auto => {
+            let (name, freq_at_440) = options
+                .locked_note
+                .and_then(|position| options.keyboard.note(position))
+                .filter(|note| calculate_cents_deviation(freq / scale, note.frequency).abs() <= NOTE_LOCK_CENTS)
+                .map(|note| (note.name, note.frequency))
+                .unwrap_or_else(|| options.keyboard.nearest_note(freq / scale));
+            (name, freq_at_440 * scale)
+        }
+        TuningMode::Manual { key_index, note_name, .. } => {
+            (note_name.clone(), partial_target_frequency(*key_index, profile, options) * scale)
+        }
+    };
+    PitchReading {
+        note_name,
+        target_freq,
+        cents: calculate_cents_deviation(freq, target_freq),
+    }
+}
+
+/// Loads a Scala (`.scl`) tuning file and returns the cents value of each scale step.
+///
+/// The Scala format consists of `!` comment lines, a description line, the number
+/// of notes, and then one pitch per line. A pitch containing a `.` is interpreted
+/// as cents; otherwise it is a ratio (`3/2`) or a whole number (`2`, meaning `2/1`).
+/// The unison (0 cents) is implicit and not included in the result, so the last
+/// entry is the period of the scale (usually 1200.0 cents for an octave).
+///
+/// # Arguments
+/// * `path` - Path to the `.scl` file
+///
+/// # Returns
+/// * `Ok(steps)` - Cents offset of each scale degree from the unison
+/// * `Err(e)` - File could not be read or is not a valid Scala file
+pub fn load_scala(path: &str) -> Result<Vec<f32>> {
+    let contents = std::fs::read_to_string(path)?;
+    parse_scala(&contents)
+}
+
+/// Parses the contents of a Scala (`.scl`) file into cents-per-step.
+///
+/// See [`load_scala`] for the format details.
+pub fn parse_scala(contents: &str) -> Result<Vec<f32>> {
+    let mut lines = contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.starts_with('!'));
+
+    // The description may legitimately be empty, so it is skipped unconditionally.
+    lines
+        .next()
+        .ok_or_else(|| anyhow!("Scala file is missing its description line"))?;
+
+    let count_line = lines
+        .next()
+        .ok_or_else(|| anyhow!("Scala file is missing the note count"))?;
+    let count: usize = count_line
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .parse()
+        .map_err(|_| anyhow!("Invalid note count in Scala file: '{}'", count_line))?;
+
+    let steps = lines
+        .filter(|line| !line.is_empty())
+        .take(count)
+        .map(parse_scala_pitch)
+        .collect::<Result<Vec<f32>>>()?;
+
+    if steps.len() != count {
+        return Err(anyhow!(
+            "Scala file declares {} notes but contains {}",
+            count,
+            steps.len()
+        ));
+    }
+
+    Ok(steps)
+}
+
+/// Parses a single Scala pitch line (cents or ratio) into cents.
+fn parse_scala_pitch(line: &str) -> Result<f32> {
+    // Anything after the first whitespace is a comment.
+    let value = line.split_whitespace().next().unwrap_or("");
+
+    if value.contains('.') {
+        return value
+            .parse::<f32>()
+            .map_err(|_| anyhow!("Invalid cents value in Scala file: '{}'", value));
+    }
+
+    let (numerator, denominator) = match value.split_once('/') {
+        Some((n, d)) => (n, d),
+        None => (value, "1"),
+    };
+    let numerator: f64 = numerator
+        .parse()
+        .map_err(|_| anyhow!("Invalid ratio in Scala file: '{}'", value))?;
+    let denominator: f64 = denominator
+        .parse()
+        .map_err(|_| anyhow!("Invalid ratio in Scala file: '{}'", value))?;
+
+    if numerator <= 0.0 || denominator <= 0.0 {
+        return Err(anyhow!("Ratio must be positive in Scala file: '{}'", value));
+    }
+
+    Ok((1200.0 * (numerator / denominator).log2()) as f32)
+}
+
+/// Saves a set of scale steps as a Scala (`.scl`) file.
+///
+/// All steps are written in the cents format, so a file loaded with
+/// [`load_scala`] and saved again keeps its pitches but not its original ratios.
+///
+/// # Arguments
+/// * `path` - Path of the `.scl` file to write
+/// * `description` - One-line description stored in the file
+/// * `steps` - Cents offset of each scale degree, ending with the period
+pub fn save_scala(path: &str, description: &str, steps: &[f32]) -> Result<()> {
+    let mut contents = String::new();
+    contents.push_str("! Exported by Inharmonicity\n!\n");
+    contents.push_str(description.lines().next().unwrap_or(""));
+    contents.push('\n');
+    contents.push_str(&format!(" {}\n!\n", steps.len()));
+    for step in steps {
+        contents.push_str(&format!(" {:.5}\n", step));
+    }
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Lowest frequency `expand_scale` expands a scale down to, in Hz. Nearer
+/// 0 Hz the range would span an unbounded number of periods.
+const MIN_SCALE_FREQ: f32 = 1.0;
+
+/// Expands a scale's steps across octaves into a sorted list of target frequencies.
+///
+/// The unison is placed on `reference_freq` and the scale is repeated up and
+/// down by its period (the last step) until the frequency range is covered.
+///
+/// # Arguments
+/// * `steps` - Cents offset of each scale degree, as returned by [`load_scala`]
+/// * `reference_freq` - Frequency of the scale's unison in Hz
+/// * `min_freq` - Lowest frequency to include in Hz (at least `MIN_SCALE_FREQ`)
+/// * `max_freq` - Highest frequency to include in Hz
+///
+/// # Returns
+/// * `Vec<f32>` - Target frequencies in ascending order (empty for an invalid scale)
+pub fn expand_scale(steps: &[f32], reference_freq: f32, min_freq: f32, max_freq: f32) -> Vec<f32> {
+    let period = match steps.last() {
+        Some(&p) if p > 0.0 => p,
+        _ => return vec![],
+    };
+    let min_freq = min_freq.max(MIN_SCALE_FREQ);
+    if reference_freq <= 0.0 || min_freq > max_freq {
+        return vec![];
+    }
+
+    // Number of periods needed below and above the reference to cover the range.
+    let cents_to = |freq: f32| 1200.0 * (freq / reference_freq).log2();
+    let lowest_period = (cents_to(min_freq) / period).floor() as i32 - 1;
+    let highest_period = (cents_to(max_freq) / period).ceil() as i32;
+
+    let mut freqs = Vec::new();
+    for k in lowest_period..=highest_period {
+        let base_cents = k as f32 * period;
+        // Degree 0 (the unison) plus every step except the period itself,
+        // which is the unison of the next repetition.
+        let degrees = std::iter::once(0.0).chain(steps[..steps.len() - 1].iter().copied());
+        for step in degrees {
+            let freq = reference_freq * 2.0_f32.powf((base_cents + step) / 1200.0);
+            if freq >= min_freq && freq <= max_freq {
+                freqs.push(freq);
+            }
+        }
+    }
+
+    freqs.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    freqs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inharmonicity::KeyMeasurement;
+
+    const STRATEGIES: [StretchStrategy; 3] =
+        [StretchStrategy::Octave2To1, StretchStrategy::Octave4To2, StretchStrategy::Octave6To3];
+
+    #[test]
+    fn stretch_curve_is_flat_without_inharmonicity() {
+        let curve = stretch_curve(&InharmonicityProfile::default(), StretchStrategy::Octave2To1, None);
+        assert!(curve.iter().all(|cents| cents.abs() < 0.01), "{curve:?}");
+    }
+
+    #[test]
+    fn preset_stretch_curve_rises_through_a4() {
+        for preset in PianoType::ALL {
+            for strategy in STRATEGIES {
+                let curve = stretch_curve(&InharmonicityProfile::default(), strategy, Some(preset));
+                let label = format!("{} {strategy:?}", preset.label());
+
+                assert!(curve[A4_KEY_INDEX].abs() < 1e-3, "{label}: A4 at {}", curve[A4_KEY_INDEX]);
+                for (key, pair) in curve.windows(2).enumerate() {
+                    assert!(pair[1] >= pair[0] - 0.01, "{label}: key {} below key {key}", key + 1);
+                }
+                assert!(curve[..A4_KEY_INDEX].iter().all(|&c| c <= 0.01), "{label}: bass not flat");
+                assert!(curve[A4_KEY_INDEX + 1..].iter().all(|&c| c >= -0.01), "{label}: treble not sharp");
+                // The treble strings are stiffer, so the top stretches further than the bottom
+                assert!(curve[87] > -curve[0], "{label}: A0 {} C8 {}", curve[0], curve[87]);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_b_stretch_curve_is_symmetric_about_a4() {
+        let mut profile = InharmonicityProfile::default();
+        profile.insert_measurement(KeyMeasurement {
+            key_index: A4_KEY_INDEX as u8,
+            partials: vec![],
+            calculated_b: Some(4.0e-4),
+            fit_r_squared: None,
+            envelope: None,
+            low_confidence: false,
+            confidence: None,
+            reliability: None,
+        });
+
+        for strategy in STRATEGIES {
+            let curve = stretch_curve(&profile, strategy, None);
+            assert!(curve[A4_KEY_INDEX + 12] > 0.5, "{strategy:?}: A5 at {}", curve[A4_KEY_INDEX + 12]);
+            // Every key above A4 mirrors the one as far below (C8 is 39 keys up)
+            for offset in 1..=(87 - A4_KEY_INDEX) {
+                let (below, above) = (curve[A4_KEY_INDEX - offset], curve[A4_KEY_INDEX + offset]);
+                assert!((below + above).abs() < 0.05, "{strategy:?}: -{offset} at {below}, +{offset} at {above}");
+            }
+        }
+    }
+
+    fn assert_cents(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "{actual} cents, expected {expected}");
+    }
+
+    #[test]
+    fn scala_pitch_reads_ratios_and_cents() {
+        assert_cents(parse_scala_pitch("3/2").unwrap(), 701.955);
+        assert_cents(parse_scala_pitch("2").unwrap(), 1200.0);
+        assert_cents(parse_scala_pitch("100.0").unwrap(), 100.0);
+        // Anything after the value is a comment
+        assert_cents(parse_scala_pitch("5/4 major third").unwrap(), 386.314);
+        assert_cents(parse_scala_pitch("700.0 ! fifth").unwrap(), 700.0);
+        assert!(parse_scala_pitch("0/1").is_err());
+        assert!(parse_scala_pitch("fifth").is_err());
+    }
+
+    #[test]
+    fn scala_file_skips_comment_lines_and_checks_count() {
+        let contents = "! steps.scl\n!\nSome steps of a scale\n 4\n!\n 100.0\n 3/2 fifth\n! the last step is the period\n 900.0\n 2\n";
+        let steps = parse_scala(contents).unwrap();
+        assert_eq!(steps.len(), 4);
+        for (step, expected) in steps.into_iter().zip([100.0, 701.955, 900.0, 1200.0]) {
+            assert_cents(step, expected);
+        }
+
+        let short = "! short.scl\nDeclares more notes than it has\n 3\n 3/2\n 2\n";
+        assert!(parse_scala(short).is_err());
+    }
+
+    #[test]
+    fn expand_scale_stops_at_lowest_frequency() {
+        // A 1-cent scale down to 0 Hz would otherwise walk ~150 000 periods
+        let freqs = expand_scale(&[1.0], 440.0, 0.0, 2.0 * MIN_SCALE_FREQ);
+        assert!(freqs.iter().all(|&f| (MIN_SCALE_FREQ..=2.0 * MIN_SCALE_FREQ).contains(&f)));
+        // One step per cent of the octave above MIN_SCALE_FREQ
+        assert_eq!(freqs.len(), 1200);
+    }
 }
@@ -0,0 +1,257 @@
+//! # WAV File Input Module
+//!
+//! Runs recorded notes through the same analysis as the live input, so a
+//! profile can be built from a folder of recordings without a microphone.
+//! Each file holds one struck note and is named after it (e.g. `A4.wav`,
+//! `C#3 soft.wav`).
+//!
+//! ## Features
+//! - Reading 16/24/32-bit integer and 32-bit float WAV files, averaged to mono
+//! - Fixed-size, optionally overlapping frames of a file, as the live input delivers them
+//! - Frame-by-frame analysis with the same cross-frame tracking as the audio thread
+//! - One `KeyMeasurement` per file, using the capture processing strategies
+//! - Building an `InharmonicityProfile` from a whole directory
+
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    analysis::{self, AnalysisConfig},
+    capture_processing::{self, CaptureConfig, ProcessingOperation},
+    inharmonicity::{InharmonicityProfile, KeyMeasurement},
+    tracking::FrameTracker,
+    tuning::{self, OctaveConvention},
+    AnalysisResult,
+};
+
+/// Reads a WAV file and averages its channels to mono.
+///
+/// # Arguments
+/// * `path` - Path of the `.wav` file
+///
+/// # Returns
+/// * `Ok((samples, sample_rate))` - Samples in the range -1.0..=1.0 and their rate in Hz
+/// * `Err(e)` - The file could not be read or has an unsupported format
+pub fn read_wav(path: &Path) -> Result<(Vec<f32>, u32)> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let full_scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 / full_scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let samples = interleaved
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    Ok((samples, spec.sample_rate))
+}
+
+/// Reads a WAV file as the analysis frames the audio thread would receive.
+///
+/// Channels are averaged to mono as in [`read_wav`]; a shorter remainder at
+/// the end is left out.
+///
+/// # Arguments
+/// * `path` - Path of the `.wav` file
+/// * `frame_size` - Samples per frame (e.g. `audio::BUFFER_SIZE`)
+/// * `hop_size` - Samples between the starts of consecutive frames;
+///   `frame_size` gives back-to-back frames. Clamped to `1..=frame_size`
+///
+/// # Returns
+/// * `Ok((frames, sample_rate))` - The frames in order, and the file's rate in Hz
+/// * `Err(e)` - The file could not be read, or `frame_size` is zero
+pub fn read_frames(path: &Path, frame_size: usize, hop_size: usize) -> Result<(impl Iterator<Item = Vec<f32>>, u32)> {
+    if frame_size == 0 {
+        return Err(anyhow!("Frame size must be at least one sample"));
+    }
+    let (samples, sample_rate) = read_wav(path)?;
+    let hop_size = hop_size.clamp(1, frame_size);
+    let count = match samples.len().checked_sub(frame_size) {
+        Some(spare) => spare / hop_size + 1,
+        None => 0,
+    };
+    let frames = (0..count).map(move |i| samples[i * hop_size..i * hop_size + frame_size].to_vec());
+    Ok((frames, sample_rate))
+}
+
+/// Analyzes a recording frame by frame, as the audio thread would.
+///
+/// The file is cut into frames by [`read_frames`], so the frame and hop
+/// sizes can follow `AudioConfig::frame_and_hop` of the live input.
+///
+/// # Arguments
+/// * `path` - Path of the `.wav` file
+/// * `frame_size` - Samples per frame
+/// * `hop_size` - Samples between the starts of consecutive frames, clamped to `1..=frame_size`
+/// * `config` - Analysis settings
+///
+/// # Returns
+/// * `Ok((results, sample_rate))` - One `AnalysisResult` per frame in order, and the file's rate in Hz
+/// * `Err(e)` - The file could not be read, or `frame_size` is zero
+pub fn analyze_file(
+    path: &Path,
+    frame_size: usize,
+    hop_size: usize,
+    config: &AnalysisConfig,
+) -> Result<(Vec<AnalysisResult>, u32)> {
+    let (frames, sample_rate) = read_frames(path, frame_size, hop_size)?;
+    let hop_seconds = hop_size.clamp(1, frame_size) as f32 / sample_rate as f32;
+    let mut tracker = FrameTracker::new();
+    let results = frames
+        .map(|frame| {
+            let mut result = analysis::analyze_frame(&frame, sample_rate, config);
+            tracker.track(&mut result, hop_seconds);
+            result
+        })
+        .collect();
+    Ok((results, sample_rate))
+}
+
+/// Finds the key a recording is named after.
+///
+/// The first word of the file name is read as a scientific note name, so
+/// `A4.wav`, `A4_take2.wav` and `A4 soft.wav` are all A4.
+///
+/// # Returns
+/// * `Some(key_index)` - Key index (0-87)
+/// * `None` - The name does not start with a note of the 88-key range
+pub fn key_index_from_file_name(path: &Path) -> Option<u8> {
+    let stem = path.file_stem()?.to_str()?;
+    let note = stem.split([' ', '_', '-']).next()?;
+    tuning::key_index_from_name_in(note, OctaveConvention::Scientific)
+}
+
+/// Measures the key a recording is named after.
+///
+/// Frames starting within `capture_config.settle_time` of the start are
+/// skipped, like the attack of a live capture, and only frames detected as
+/// the named key are passed to [`capture_processing::process`].
+///
+/// # Arguments
+/// * `path` - Path of the `.wav` file
+/// * `frame_size` - Samples per analysis frame; the lowest keys need 4096 or more
+/// * `hop_size` - Samples between the starts of consecutive frames
+/// * `analysis_config` - Analysis settings
+/// * `capture_config` - Quality thresholds the measurement must meet
+/// * `operation` - Processing strategy applied to the frames
+///
+/// # Returns
+/// * `Ok(measurement)` - Measurement of the named key
+/// * `Err(e)` - The name is not a note, the file can't be read, or the capture was rejected
+pub fn measure_file(
+    path: &Path,
+    frame_size: usize,
+    hop_size: usize,
+    analysis_config: &AnalysisConfig,
+    capture_config: &CaptureConfig,
+    operation: ProcessingOperation,
+) -> Result<KeyMeasurement> {
+    let key_index = key_index_from_file_name(path)
+        .ok_or_else(|| anyhow!("File name does not start with a note name"))?;
+    let (results, sample_rate) = analyze_file(path, frame_size, hop_size, analysis_config)?;
+    let hop_size = hop_size.clamp(1, frame_size);
+    let settle_frames = (capture_config.settle_time.as_secs_f32() * sample_rate as f32 / hop_size as f32).ceil() as usize;
+
+    let frames: Vec<AnalysisResult> = results
+        .into_iter()
+        .skip(settle_frames)
+        .filter(|frame| {
+            frame.note_name.as_deref().and_then(|name| {
+                tuning::key_index_from_name_in(name, OctaveConvention::Scientific)
+            }) == Some(key_index)
+        })
+        .collect();
+
+    Ok(capture_processing::process(frames, operation, capture_config)?)
+}
+
+/// Builds a profile from every `.wav` file in a directory.
+///
+/// Files that can't be measured are skipped and reported; when several files
+/// name the same key, the last one in file-name order wins.
+///
+/// # Arguments
+/// * `dir` - Directory of recordings named by note
+/// * `frame_size` - Samples per analysis frame
+/// * `hop_size` - Samples between the starts of consecutive frames
+/// * `analysis_config` - Analysis settings
+/// * `capture_config` - Quality thresholds each measurement must meet
+/// * `operation` - Processing strategy applied to each file
+///
+/// # Returns
+/// * `Ok((profile, failures))` - The profile, and each skipped file with the reason
+/// * `Err(e)` - The directory could not be read
+pub fn profile_from_dir(
+    dir: &Path,
+    frame_size: usize,
+    hop_size: usize,
+    analysis_config: &AnalysisConfig,
+    capture_config: &CaptureConfig,
+    operation: ProcessingOperation,
+) -> Result<(InharmonicityProfile, Vec<(PathBuf, String)>)> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav")))
+        .collect();
+    paths.sort();
+
+    let mut profile = InharmonicityProfile::default();
+    let mut failures = Vec::new();
+    for path in paths {
+        match measure_file(&path, frame_size, hop_size, analysis_config, capture_config, operation.clone()) {
+            Ok(measurement) => {
+                profile.insert_measurement(measurement);
+            }
+            Err(e) => failures.push((path, e.to_string())),
+        }
+    }
+    Ok((profile, failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{self, BUFFER_SIZE};
+
+    /// Writes `samples` as a 16-bit mono WAV file in the temp directory.
+    fn write_wav(name: &str, samples: &[f32], sample_rate: u32) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.wav", name, std::process::id()));
+        let spec = hound::WavSpec { channels: 1, sample_rate, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample((sample * i16::MAX as f32) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    #[test]
+    fn recorded_note_replays_as_frames_of_its_pitch() {
+        let note = crate::demo::synthesize_note(24, 48000);
+        let path = write_wav("inharmonicity-a2", &note, 48000);
+
+        let (frames, sample_rate) = audio::wav_frame_iterator(path.to_str().unwrap(), BUFFER_SIZE).unwrap();
+        let frames: Vec<Vec<f32>> = frames.collect();
+        assert_eq!(sample_rate, 48000);
+        assert_eq!(frames.len(), note.len() / BUFFER_SIZE);
+        assert!(frames.iter().all(|frame| frame.len() == BUFFER_SIZE));
+        let result = analysis::analyze_frame(&frames[1], sample_rate, &AnalysisConfig::default());
+        assert_eq!(result.note_name.as_deref(), Some("A2"));
+        // The demo piano's A2 is 10 cents flat; one default-sized frame
+        // resolves it to about a dozen cents
+        let cents = result.cents_deviation.unwrap();
+        assert!((cents + 10.0).abs() <= 15.0, "{cents:+.1} cents");
+
+        let (overlapping, _) = read_frames(&path, BUFFER_SIZE, BUFFER_SIZE / 2).unwrap();
+        assert_eq!(overlapping.count(), (note.len() - BUFFER_SIZE) / (BUFFER_SIZE / 2) + 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
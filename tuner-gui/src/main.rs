@@ -8,41 +8,88 @@
 //! - **Main Thread**: Iced GUI application with dark theme
 //! - **Audio Thread**: Dedicated thread for real-time audio processing
 //! - **Communication**: Crossbeam channels for thread-safe data exchange
-//! - **Updates**: 60 FPS continuous updates via subscription system
+//! - **Updates**: Continuous updates via subscription system (60 FPS by default, throttled when idle)
 
+mod settings;
 mod ui;
 
 use crossbeam_channel::{Receiver, Sender};
 use cpal::traits::StreamTrait;
 use iced::{
-    self, Element, Theme, Subscription
+    self, Element, Theme, Subscription, Task
 };
 use std::collections::VecDeque;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use tuner_core::{
-    audio, fft, pitch, tuning, AnalysisResult,
-    inharmonicity::InharmonicityProfile,
-    capture_processing::{self, ProcessingOperation}
+    analysis::{self, AnalysisConfig}, audio::{self, InputInfo}, demo, pitch::{PartialThreshold, PitchAlgorithm}, tuning::{self, KeyboardSpec, OctaveConvention, PitchReading, TargetOptions, TuningMode}, AnalysisResult,
+    inharmonicity::{self, InharmonicityProfile, KeyMeasurement, PianoType, ProfileLibrary},
+    capture_processing::{CaptureConfig, ProcessingOperation}, capture_session::CaptureSession, tracking::FrameTracker,
 };
 use ui::main_display::create_main_view;
+use ui::cent_meter::{NeedleDynamics, ToleranceCurve};
+use ui::partials_display::PartialDisplayMode;
+use ui::spectrogram::SpectrumRange;
+use ui::palette::Palette;
+use settings::UiSettings;
 
 // Audio processing constants
-const SMOOTHING_FACTOR: usize = 5;  // Number of samples for cent smoothing
-const AMPLITUDE_THRESHOLD: f32 = 0.01;  // Minimum amplitude for pitch detection
-const STABILITY_TARGET: usize = 20; // Number of stable frames required for capture
-const STABILITY_CONFIDENCE_THRESHOLD: f32 = 0.9; // Confidence threshold for stability
+const FRAME_RATE_CHOICES: [u32; 3] = [60, 30, 15]; // How often per second the GUI polls for analysis results and redraws
+const IDLE_FRAME_RATE: u32 = 4; // Redraw rate once nothing has been detected for IDLE_TIMEOUT
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5); // Time without a detected pitch before idle throttling starts
+const SMOOTHING_CHOICES: [usize; 5] = [1, 3, 5, 10, 20]; // Cent smoothing sample counts offered in the sidebar
+const KEYBOARD_CHOICES: [KeyboardSpec; 2] = [KeyboardSpec::STANDARD, KeyboardSpec::IMPERIAL]; // Keyboard ranges offered in the sidebar
+const DEFAULT_SMOOTHING_SAMPLES: usize = 5; // Readings averaged for the cent display by default
+const MEASUREMENT_PARTIAL_CHOICES: [u32; 4] = [7, 10, 12, 15]; // Overtones searched for per frame, offered in the sidebar
+const SNR_GATE_CHOICES: [f32; 4] = [0.0, 6.0, analysis::MIN_SNR_DB, 20.0]; // Lowest pitch-to-noise-floor ratios offered in the sidebar, in dB (0 = off)
+const DEFAULT_MEASUREMENT_PARTIALS: u32 = 10; // More points give a better bass 'B' fit
+const DISPLAYED_PARTIAL_CHOICES: [usize; 3] = [8, 12, 15]; // Most overtones listed in the partials panel, offered in the sidebar
+const DEFAULT_DISPLAYED_PARTIALS: usize = 15; // Enough to list every overtone the measurement searches for
+const DONE_DISPLAY_CHOICES: [Duration; 4] = [Duration::ZERO, Duration::from_secs(1), Duration::from_secs(2), Duration::from_secs(4)]; // How long the capture button may show "Done", offered in the sidebar
+const DEFAULT_DONE_DISPLAY_TIME: Duration = Duration::from_secs(1); // "Done" confirmation time by default
+const DEFAULT_NOISE_FLOOR_DB: f32 = -96.0; // Spectrogram gate threshold in dBFS
+const SPECTRUM_SMOOTHING: f32 = 0.7; // Weight of the previous frame in the displayed spectrum EMA
+const PEAK_HOLD_DECAY: f32 = 0.95; // Per-frame decay of the spectrum peak hold (about -10 dB per second at 44.1 kHz)
+pub const PITCH_HISTORY_LENGTH: usize = 200; // Readings kept for the pitch history graph (~9 s at 44.1 kHz)
+pub const NOTE_HISTORY_LENGTH: usize = 12; // Detected notes kept for the note history log
+const QUALITY_WINDOW: usize = 200; // Frames with signal the detection quality score covers (~9 s at 44.1 kHz)
+pub const LOW_DETECTION_RATE: f32 = 0.6; // Below this fraction of detected frames the quality score is flagged
+pub const LOW_MEAN_CONFIDENCE: f32 = 0.7; // Below this mean confidence the quality score is flagged
+const WATERFALL_DURATION: Duration = Duration::from_millis(4600); // Time spanned by the waterfall view
+const OVERLAP_CHOICES: [usize; 3] = [1, 2, 4]; // Frames overlapping each sample (1 = none), offered in the sidebar
+const AUDIO_EVENT_QUEUE: usize = 4; // Stream events (started, lost, failed) waiting for the GUI
+const RAW_FRAME_QUEUE: usize = 16; // Frames waiting for analysis before the capture drops new ones (a slow analysis can't pile up memory)
+const FRAME_SIZE_CHOICES: [usize; 3] = [audio::BUFFER_SIZE, 4096, 8192]; // Analysis frame sizes offered in the sidebar; longer frames resolve the low bass
+const CLIP_HOLD: Duration = Duration::from_millis(1000); // How long the CLIP indicator stays lit
+const STREAM_BUFFER_CHOICES: [Option<u32>; 5] = [None, Some(256), Some(512), Some(1024), Some(2048)]; // Device buffer sizes offered in the sidebar
+pub const NEEDLE_DAMPING_CHOICES: [(&str, Option<NeedleDynamics>); 3] = [ // Cent meter needle settings offered in the sidebar
+    ("off", None),
+    ("light", Some(NeedleDynamics { stiffness: 400.0, damping: 0.7 })),
+    ("heavy", Some(NeedleDynamics { stiffness: 60.0, damping: 1.0 })),
+];
+const PROFILE_PATH: &str = "tuning_profile.json"; // File the single active profile is saved to and loaded from
+const PROFILE_LIBRARY_PATH: &str = "profile_library.json"; // File holding every named profile
+const UI_SETTINGS_PATH: &str = "ui_settings.json"; // Panel visibility and display preferences, apart from the profiles
+const DEMO_ENV_VAR: &str = "INHARMONICITY_DEMO"; // When set, a synthetic piano replaces the input (for demos without a mic)
+const DEFAULT_PROFILE_NAME: &str = "Piano 1"; // Name of the profile the app starts with
+const REFERENCE_TONE_MS: u64 = 2000; // Length of the reference tone played for the manual key
+pub const SWEEP_INTERVAL_MS: u64 = 1500; // Time each key is held by the sidebar's reference sweep
+const SWEEP_TONE_GAP_MS: u64 = 150; // Silence between consecutive reference tones of a sweep
+const NOISE_FLOOR_PARTIAL_FACTOR: f32 = 8.0; // Partials must be this many times the local noise floor (~18 dB)
 
 
 /// Main entry point for the Inharmonicity application.
 /// 
 /// Initializes the Iced GUI application with dark theme, real-time audio processing,
-/// and continuous updates for smooth visualization.
+/// and continuous updates for smooth visualization. Closing the window is routed
+/// through `Message::Exit` so the audio thread is joined before `run()` returns.
 pub fn main() -> iced::Result {
     eprintln!("[MAIN] Starting Inharmonicity application...");
     eprintln!("[MAIN] Initializing GUI framework...");
     let result = iced::application("Inharmonicity", TunerApp::update, TunerApp::view)
         .subscription(TunerApp::subscription)
         .theme(TunerApp::theme)
+        .exit_on_close_request(false)
         .run();
     eprintln!("[MAIN] Application finished with result: {:?}", result);
     result
@@ -56,20 +103,44 @@ pub fn main() -> iced::Result {
 pub enum Message {
     // Piano keyboard interactions
     KeySelected(u8),           // User selected a piano key (0-87)
+    KeyOutsideProfile(String), // User clicked an extended-keyboard key the profiles can't hold (its note name)
     SwitchToAutoMode,          // Switch from manual to automatic pitch detection
+    TuneReferenceA,            // Pin the target to A4 (or back to auto if it already is)
+    SelectPartial(u32),        // Tune the given partial of the manual key (1 = fundamental)
+    PlayReference,             // Play the target pitch of the manual key
+    StartSweep { from_key: u8, to_key: u8, interval_ms: u64 }, // Step the manual key through a range, playing each target
+    SweepStep,                 // Timer tick of a running sweep: advance to the next key
+    StopSweep,                 // Stop a running sweep, keeping the current key selected
     
     // --- Messages for Inharmonicity Measurement & Profile ---
     ToggleMeasurementMode,     // Toggle the partial measurement mode
     CaptureButtonClicked,      // Capture button was clicked (behavior depends on current state)
+    ToggleAutoMeasure,         // Automatically capture each new stable note
+    UndoLastMeasurement,       // Remove the most recently stored measurement
+    ToggleBeatTrainer,         // Compare the interval from the manual key to later keys with equal temperament
     SaveProfile,               // Save the current inharmonicity profile
     LoadProfile,               // Load an inharmonicity profile from file
+    ProfileNameChanged(String), // Name typed for the next new profile
+    CreateProfile,             // Start a new, empty named profile
+    SelectNextProfile,         // Switch to the next profile in the library
+    DeleteProfile,             // Remove the active profile from the library
+    SaveLibrary,               // Save every named profile to the library file
+    LoadLibrary,               // Load the named profiles from the library file
+    ExportTuningTable,         // Write the 88-key tuning from the profile as CSV and JSON
+    RecalculateProfile,        // Recompute B and fit quality for every stored measurement
+    CompareWithSavedProfile,   // Write how the current profile differs from the saved one as CSV
     // ----------------------------------------------
     
     // Settings menu items (placeholder for future implementation)
     Temperament,              // Temperament selection
     TuningStandard,           // Tuning standard (A440, etc.)
-    InharmonicCurve,          // Inharmonicity curve adjustment
-    SampleBuffer,             // Sample buffer size adjustment
+    InharmonicCurve,          // Cycle stretch tuning: off, measured keys only, then each piano-size preset
+    ToggleStereoCoherence,    // Combine two microphones, keeping only their coherent component (restarts audio)
+    CycleInputDevice,         // Switch to the next input device (restarts audio)
+    RetryAudio,               // Try to open the input device again after it failed
+    SampleBuffer,             // Cycle the device stream buffer size (restarts audio)
+    CycleFrameOverlap,        // Cycle how much consecutive analysis frames overlap (restarts audio)
+    CycleFrameSize,           // Cycle the number of samples per analysis frame (restarts audio)
     TuningProfile,            // Tuning profile management
     
     // Application control
@@ -80,25 +151,121 @@ pub enum Message {
     ToggleCentMeter,         // Show/hide cent meter panel
     ToggleKeySelect,         // Show/hide piano keyboard
     TogglePartials,          // Show/hide partials panel
+    TogglePitchHistory,      // Show/hide pitch history graph
+    ToggleNoteHistory,       // Show/hide detected note history log
+    CycleNeedleDamping,      // Switch to the next cent meter needle damping setting
+    CycleSmoothing,          // Switch to the next cent smoothing sample count
+    CycleDoneDisplayTime,    // Switch to the next duration of the capture "Done" confirmation
+    CycleMeasurementPartials, // Switch to the next count of overtones searched for the 'B' regression
+    CycleDisplayedPartials,   // Switch to the next count of overtones listed in the partials panel
+    CycleFrameRate,          // Switch to the next GUI update rate
+    ToggleIdleThrottling,    // Lower the update rate while no pitch is detected
+    CyclePalette,            // Switch to the next color palette preset
+    CycleConfidenceDisplay,  // Show confidence as a percentage, a bar, or not at all
+    TogglePartialDisplayMode, // Show partials in Hz or as cents from the ideal harmonic
+    CycleSpectrumRange,       // Zoom the spectrogram to the next frequency range
+    ToggleBandpass,          // Enable/disable the manual-mode band-pass pre-filter
+    ToggleBandLimit,         // Enable/disable manual-mode band-limited spectrum analysis
+    ToggleStretchedSearch,   // Center the manual key's partial search on its stretched positions
+    ToggleKeyFilter,         // Ignore notes other than the manual key
+    ToggleSecondPartialReference, // Tune the lowest bass keys from their 2nd partial
+    ToggleEnvelopeCapture,   // Record partial amplitude envelopes when capturing
+    TogglePartialPeakCapture, // Measure each partial in the frame where it is strongest
+    ToggleWeightedAverageCapture, // Average each partial over the capture, weighted by frame confidence
+    TogglePartialThreshold,  // Switch partial detection between fundamental-relative and noise-floor thresholds
+    CyclePitchAlgorithm,     // Switch to the next pitch detection algorithm
+    CycleSnrGate,            // Switch to the next signal-to-noise gate level
+    
+    // Display settings
+    NoiseFloorChanged(f32),   // Spectrogram noise floor gate in dBFS
+    InputGainChanged(f32),    // Software input gain in dB, applied before analysis
+    ToggleOctaveConvention,   // Switch note names between scientific (C4) and Yamaha (C3) middle C
+    ToggleNoteLock,           // Keep the auto-mode note until the pitch is well past the semitone midpoint
+    ToggleCentsReadout,       // Show the smoothed cents value as text next to the needle
+    TogglePolyphonic,         // Report up to three simultaneous notes instead of one
+    CycleKeyboard,            // Switch between the standard 88-key and extended keyboard ranges
+    ToggleWaterfall,          // Switch the spectrogram between bars and a scrolling waterfall
+    ToggleSpectrumSmoothing,  // Smooth the displayed spectrum across frames
+    TogglePeakHold,           // Draw the decaying per-bin maximum over the spectrum bars
     
     // Continuous update message
     Tick,                     // Timer tick for real-time updates
 }
 
-/// Tuning mode for the piano tuner application.
-/// 
-/// Determines whether the application is in automatic pitch detection mode
-/// or manual key selection mode.
-#[derive(Debug, Clone, PartialEq)]
-pub enum TuningMode {
-    /// Automatic pitch detection mode - detects any note being played
-    Auto,
-    /// Manual mode - user has selected a specific piano key to tune
-    Manual {
-        key_index: u8,        // Piano key index (0-87)
-        note_name: String,    // Note name (e.g., "A4", "C#3")
-        target_freq: f32,     // Target frequency in Hz
-    },
+/// How pitch confidence is shown in the cent meter panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfidenceDisplay {
+    #[default]
+    Percent,  // "97%"
+    Bar,      // Small horizontal gauge
+    Hidden,   // Not shown
+}
+
+impl ConfidenceDisplay {
+    /// Returns the next display mode, for cycling through them in the sidebar.
+    fn next(self) -> Self {
+        match self {
+            ConfidenceDisplay::Percent => ConfidenceDisplay::Bar,
+            ConfidenceDisplay::Bar => ConfidenceDisplay::Hidden,
+            ConfidenceDisplay::Hidden => ConfidenceDisplay::Percent,
+        }
+    }
+
+    /// Returns the name shown in the sidebar.
+    pub fn label(self) -> &'static str {
+        match self {
+            ConfidenceDisplay::Percent => "percent",
+            ConfidenceDisplay::Bar => "bar",
+            ConfidenceDisplay::Hidden => "hidden",
+        }
+    }
+}
+
+/// One entry of the detected note history log.
+#[derive(Debug, Clone)]
+pub struct DetectedNote {
+    pub note_name: String,     // Scientific note name, as detected
+    pub cents: Option<f32>,    // Latest deviation while the note was held
+    pub detected_at: Instant,  // When the note was first detected
+}
+
+/// Rolling summary of how reliably pitch has been detected.
+///
+/// Only frames with signal above the analysis amplitude threshold are
+/// counted, so pauses between notes don't lower the score. A low score
+/// usually means the microphone is too far away or the room is noisy.
+#[derive(Debug, Clone, Default)]
+pub struct DetectionQuality {
+    confidences: VecDeque<Option<f32>>,  // Confidence of recent frames with signal (None = no pitch)
+}
+
+impl DetectionQuality {
+    /// Adds a frame, ignoring it if it carried no signal.
+    fn push(&mut self, result: &AnalysisResult) {
+        if result.input_level < analysis::AMPLITUDE_THRESHOLD {
+            return;
+        }
+        if self.confidences.len() == QUALITY_WINDOW {
+            self.confidences.pop_front();
+        }
+        let confidence = result.detected_frequency.map(|_| result.confidence.unwrap_or(0.0));
+        self.confidences.push_back(confidence);
+    }
+
+    /// Fraction of frames with signal in which a pitch was detected (None = no frames yet).
+    pub fn detection_rate(&self) -> Option<f32> {
+        if self.confidences.is_empty() {
+            return None;
+        }
+        let detected = self.confidences.iter().filter(|c| c.is_some()).count();
+        Some(detected as f32 / self.confidences.len() as f32)
+    }
+
+    /// Mean confidence of the frames in which a pitch was detected.
+    pub fn mean_confidence(&self) -> Option<f32> {
+        let detected: Vec<f32> = self.confidences.iter().flatten().copied().collect();
+        (!detected.is_empty()).then(|| detected.iter().sum::<f32>() / detected.len() as f32)
+    }
 }
 
 /// State for the stability-gated capture system.
@@ -110,6 +277,16 @@ pub enum CaptureState {
     Done,       // Capture is complete, data is being processed
 }
 
+/// A running reference sweep.
+///
+/// The key currently sounding is the manual key of the tuning mode; the sweep
+/// only remembers where it is heading and how fast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceSweep {
+    pub to_key: u8,        // Last key of the sweep (0-87), above or below the first
+    pub interval_ms: u64,  // Time each key is held
+}
+
 
 /// UI-specific data needed for rendering the interface.
 /// 
@@ -118,20 +295,96 @@ pub enum CaptureState {
 pub struct AppDisplayData {
     // Audio state
     pub audio_worker_active: bool,
+    pub frame_rate: u32,  // GUI updates per second while a pitch is being detected
+    pub idle_throttling: bool,  // Drop to IDLE_FRAME_RATE after IDLE_TIMEOUT without a pitch
+    pub audio_config: audio::AudioConfig,  // Settings the input stream is opened with
+    pub demo_signal: bool,  // Analyze the synthetic demo signal instead of an input (DEMO_ENV_VAR)
+    pub audio_error: Option<String>,  // Why the input stream could not be opened or was lost, if it failed
+    pub input_info: Option<InputInfo>,  // Device, rate and channel mode of the running input stream
+    pub active_profile: String,  // Name of the profile being measured and tuned against
+    pub new_profile_name: String,  // Name typed for the next new profile
+    pub input_gain_db: f32,  // Software gain applied to each frame before analysis (0 = unity)
     pub last_analysis: Option<AnalysisResult>,
-    pub smoothing_buffer: Vec<f32>,
+    pub last_reading: Option<PitchReading>,  // Latest analysis compared against the current target
+    pub note_lock_enabled: bool,
+    pub cents_readout_visible: bool,
+    pub locked_note: Option<usize>,  // Keyboard position of the note auto mode last showed, for hysteresis
+    pub clip_indicator_until: Option<Instant>,  // The CLIP indicator is lit until this time
+    pub smoothing_buffer: VecDeque<(Instant, f32)>,  // Recent cent readings with their frame times, oldest first
+    pub smoothing_samples: usize,  // Readings averaged for the cent display (1 = no smoothing)
+    pub measurement_partials: u32,  // Overtones searched for in each frame
+    pub displayed_partials: usize,  // Most overtones listed in the partials panel
+    pub capture_done_at: Option<Instant>,  // When the last capture was stored, for the "Done" indicator
+    pub done_display_time: Duration,  // How long the capture button shows "Done" (zero = not at all)
+    pub pitch_history: VecDeque<Option<f32>>,  // Recent cent deviations for the history graph, oldest first
+    pub note_history: VecDeque<DetectedNote>,  // Recently detected notes, oldest first
+    pub detection_quality: DetectionQuality,  // Rolling detection rate and confidence, for mic placement
     
     // UI visibility states
     pub spectrogram_visible: bool,
     pub cent_meter_visible: bool,
     pub key_select_visible: bool,
     pub partials_visible: bool,
+    pub partial_display_mode: PartialDisplayMode,  // Partials panel shows Hz or cents from n * f0
+    pub pitch_history_visible: bool,
+    pub note_history_visible: bool,
+    
+    // Spectrogram settings
+    pub noise_floor_db: f32,
+    pub waterfall_enabled: bool,
+    pub spectrum_range: SpectrumRange,  // Frequencies the spectrogram and waterfall are zoomed to
+    pub spectrum_smoothing_enabled: bool,
+    pub smoothed_spectrum: Vec<f32>,  // Per-bin EMA of the spectrum for display only; analysis keeps the raw data
+    pub peak_hold_enabled: bool,
+    pub peak_hold: Vec<f32>,  // Per-bin maximum magnitude, decaying by PEAK_HOLD_DECAY each frame
+    pub spectrogram_history: VecDeque<Vec<f32>>,  // Recent spectra for the waterfall, oldest first
+    pub waterfall_rows: usize,  // Frames spanning WATERFALL_DURATION at the current hop size
+    
+    // Cent meter settings
+    pub tolerance_curve: ToleranceCurve,
+    pub confidence_display: ConfidenceDisplay,
+    pub palette: Palette,  // Colors of the spectrogram, keyboard and cent meter
+    pub needle_dynamics: Option<NeedleDynamics>,  // Spring model of the cent meter needle (None = jumps to each reading)
+    pub octave_convention: OctaveConvention,  // Octave numbering used for displayed note names
+    pub keyboard: KeyboardSpec,  // Range of keys detected and drawn (default 88 keys)
+    pub keyboard_notice: Option<String>,  // Why the last clicked key could not be selected, shown above the keyboard
+    pub polyphonic_enabled: bool,  // Detect several simultaneous notes (for unisons and intervals)
     
     // Tuning mode
     pub tuning_mode: TuningMode,
+    pub sweep: Option<ReferenceSweep>,  // Stepping the manual key through a range, if running
+    pub beat_reference_key: Option<u8>,  // First key of the beat trainer's interval, if the trainer is on
+    pub target_partial: u32,  // Partial compared against its target in manual mode (1 = fundamental)
+    pub stretch_enabled: bool,  // Offset manual targets by the profile's stretch curve
+    pub piano_type: Option<PianoType>,  // Preset 'B' curve for keys without a measurement
+    pub bandpass_enabled: bool,
+    pub band_limit_enabled: bool,  // Limit spectrum analysis to the manual key's partial range
+    pub stretched_search_enabled: bool,  // Search the manual key's partials where its 'B' puts them
+    pub key_filter_enabled: bool,  // In manual mode, treat frames of any other note as silence
+    pub second_partial_enabled: bool,  // Derive the fundamental of a manual A0-C1 key from its 2nd partial
+    pub noise_floor_partials: bool,  // Gate partials against the local noise floor instead of the fundamental
+    pub min_snr_db: f32,  // Pitches must stand this far out of the spectral noise floor (0 = no gate)
+    pub pitch_algorithm: PitchAlgorithm,
     
     // Capture state
     pub capture_state: CaptureState,
+    pub processing_operation: ProcessingOperation,
+    pub auto_measure: bool,  // Re-arm after every capture and wait for the next different note
+    pub capture_status: Option<String>,  // Outcome of the most recent capture, shown under the capture button
+    pub profile_status: Option<String>,  // Outcome of the last profile save or load, shown under the profile controls
+}
+
+impl AppDisplayData {
+    /// Returns the band from an octave below the manual key to just above the
+    /// highest partial searched, in Hz; `None` in auto mode.
+    pub fn note_band(&self) -> Option<(f32, f32)> {
+        match &self.tuning_mode {
+            TuningMode::Manual { target_freq, .. } => {
+                Some((target_freq * 0.5, target_freq * (self.measurement_partials as f32 + 1.5)))
+            }
+            TuningMode::Auto => None,
+        }
+    }
 }
 
 /// Main application state for the Inharmonicity piano tuner.
@@ -146,14 +399,27 @@ struct TunerApp {
     analysis_sender: Option<Sender<AnalysisResult>>,      // Channel to send analysis results
     
     // --- New Inharmonicity State ---
-    stability_buffer: VecDeque<AnalysisResult>, // Buffer for checking note stability
+    capture_session: CaptureSession,            // Stability-gated capture: settle time, stability buffer, auto-measure memory
     inharmonicity_profile: InharmonicityProfile,
+    profile_library: ProfileLibrary,            // Every other named profile; the active one lives in inharmonicity_profile
+    measurement_history: Vec<(u8, Option<KeyMeasurement>)>, // Stored keys in order, with the measurement each replaced
+    reference_tone: Option<JoinHandle<()>>,     // Playback thread of the current reference tone
+    last_pitch_at: Instant,                     // When a pitch was last detected, for idle throttling
+    ui_settings: UiSettings,                    // Preferences as last saved, to notice when they change
     // ---------------------------------
     
     // Single source of truth for all display data
     display_data: AppDisplayData,
 }
 
+/// Outcome of opening (or reopening) the input stream, reported by the audio thread.
+#[derive(Debug, Clone)]
+enum AudioEvent {
+    Started(InputInfo),            // The stream is running with this device, rate and channels
+    Failed(String),                // No stream could be opened; the thread has exited
+    Reconnecting(String),          // The running input was lost (reason); it is being reopened
+}
+
 /// Audio worker thread management structure.
 /// 
 /// Handles the dedicated audio processing thread and provides
@@ -161,6 +427,8 @@ struct TunerApp {
 #[derive(Debug)]
 struct AudioWorker {
     shutdown_tx: Sender<()>,              // Channel to send shutdown signal
+    config_tx: Sender<AnalysisConfig>,    // Channel to send analysis settings
+    event_rx: Receiver<AudioEvent>,       // Channel reporting whether the stream started, was lost or failed
     thread_handle: Option<JoinHandle<()>>, // Handle to the audio thread
 }
 
@@ -170,7 +438,7 @@ impl Default for TunerApp {
     /// 
     /// Initializes the application with:
     /// - Crossbeam channels for audio data communication
-    /// - All tool panels visible by default
+    /// - Panel visibility and display preferences restored from `UI_SETTINGS_PATH`
     /// - Automatic tuning mode
     /// - Audio processing thread started
     fn default() -> Self {
@@ -181,22 +449,97 @@ impl Default for TunerApp {
             analysis_receiver: Some(analysis_rx),
             analysis_sender: Some(analysis_tx),
             // --- Initialize new state ---
-            stability_buffer: VecDeque::with_capacity(STABILITY_TARGET),
+            capture_session: CaptureSession::new(CaptureConfig::default()),
             inharmonicity_profile: InharmonicityProfile::default(),
+            profile_library: ProfileLibrary::default(),
+            measurement_history: Vec::new(),
+            reference_tone: None,
+            last_pitch_at: Instant::now(),
+            ui_settings: UiSettings::default(),
             // ----------------------------
             // Initialize display data
             display_data: AppDisplayData {
                 audio_worker_active: false, // Will be set to true after audio starts
+                frame_rate: FRAME_RATE_CHOICES[0],
+                idle_throttling: true,
+                audio_config: audio::AudioConfig::default(),
+                demo_signal: std::env::var_os(DEMO_ENV_VAR).is_some(),
+                audio_error: None,
+                input_info: None,
+                active_profile: DEFAULT_PROFILE_NAME.to_string(),
+                new_profile_name: String::new(),
+                input_gain_db: 0.0,
                 last_analysis: None,
-                smoothing_buffer: Vec::new(),
+                last_reading: None,
+                note_lock_enabled: true,
+                cents_readout_visible: true,
+                locked_note: None,
+                clip_indicator_until: None,
+                smoothing_buffer: VecDeque::new(),
+                smoothing_samples: DEFAULT_SMOOTHING_SAMPLES,
+                measurement_partials: DEFAULT_MEASUREMENT_PARTIALS,
+                displayed_partials: DEFAULT_DISPLAYED_PARTIALS,
+                capture_done_at: None,
+                done_display_time: DEFAULT_DONE_DISPLAY_TIME,
                 spectrogram_visible: true,
                 cent_meter_visible: true,
                 key_select_visible: true,
                 partials_visible: true,
+                partial_display_mode: PartialDisplayMode::default(),
+                pitch_history_visible: false,
+                pitch_history: VecDeque::with_capacity(PITCH_HISTORY_LENGTH),
+                note_history_visible: false,
+                note_history: VecDeque::with_capacity(NOTE_HISTORY_LENGTH),
+                detection_quality: DetectionQuality::default(),
+                noise_floor_db: DEFAULT_NOISE_FLOOR_DB,
+                waterfall_enabled: false,
+                spectrum_range: SpectrumRange::default(),
+                spectrum_smoothing_enabled: false,
+                smoothed_spectrum: Vec::new(),
+                peak_hold_enabled: false,
+                peak_hold: Vec::new(),
+                spectrogram_history: VecDeque::new(),
+                waterfall_rows: 0,
+                tolerance_curve: ToleranceCurve::default(),
+                confidence_display: ConfidenceDisplay::default(),
+                palette: Palette::default(),
+                needle_dynamics: NEEDLE_DAMPING_CHOICES[1].1,
+                octave_convention: OctaveConvention::default(),
+                keyboard: KeyboardSpec::default(),
+                keyboard_notice: None,
+                polyphonic_enabled: false,
                 tuning_mode: TuningMode::Auto,
+                sweep: None,
+                beat_reference_key: None,
+                target_partial: 1,
+                stretch_enabled: false,
+                piano_type: None,
+                bandpass_enabled: false,
+                band_limit_enabled: false,
+                stretched_search_enabled: false,
+                key_filter_enabled: false,
+                second_partial_enabled: false,
+                noise_floor_partials: false,
+                min_snr_db: analysis::MIN_SNR_DB,
+                pitch_algorithm: PitchAlgorithm::default(),
                 capture_state: CaptureState::Off,
+                processing_operation: ProcessingOperation::BestConfidence,
+                auto_measure: false,
+                capture_status: None,
+                profile_status: None,
             },
         };
+
+        // A missing file just means the defaults above
+        match UiSettings::load(UI_SETTINGS_PATH) {
+            Ok(settings) => {
+                eprintln!("[MAIN] UI settings loaded from {}", UI_SETTINGS_PATH);
+                settings.apply(&mut app.display_data);
+                app.ui_settings = settings;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("[MAIN] Error loading UI settings: {}", e),
+        }
         
         eprintln!("[MAIN] Starting audio processing...");
         app.start_audio_processing();
@@ -205,6 +548,13 @@ impl Default for TunerApp {
     }
 }
 
+impl Drop for TunerApp {
+    /// Makes sure the audio thread is joined on every exit path.
+    fn drop(&mut self) {
+        self.stop_audio_processing();
+    }
+}
+
 impl TunerApp {
     /// Starts the dedicated audio processing thread.
     /// 
@@ -217,19 +567,36 @@ impl TunerApp {
     /// back to the GUI thread via the analysis channel.
     fn start_audio_processing(&mut self) {
         if let Some(analysis_tx) = self.analysis_sender.take() {
+            let audio_config = self.display_data.audio_config.clone();
+            let demo_signal = self.display_data.demo_signal;
             let (shutdown_tx, shutdown_rx) = crossbeam_channel::bounded(1);
+            let (config_tx, config_rx) = crossbeam_channel::unbounded::<AnalysisConfig>();
+            let (event_tx, event_rx) = crossbeam_channel::bounded::<AudioEvent>(AUDIO_EVENT_QUEUE);
             let thread_handle = thread::spawn(move || {
             eprintln!("[AUDIO-THREAD] Starting audio thread...");
-                let (raw_audio_tx, raw_audio_rx) = crossbeam_channel::unbounded::<Vec<f32>>();
+                let (raw_audio_tx, raw_audio_rx) = crossbeam_channel::bounded::<Vec<f32>>(RAW_FRAME_QUEUE);
+                let (stream_error_tx, stream_error_rx) = crossbeam_channel::bounded::<cpal::StreamError>(1);
+                // Kept to reopen a lost input; the demo generator has nothing to lose
+                let reopen_tx = (!demo_signal).then(|| raw_audio_tx.clone());
             
             eprintln!("[AUDIO-THREAD] Attempting to start audio capture...");
-                let (stream, sample_rate) = match audio::start_audio_capture(raw_audio_tx) {
-                Ok(tuple) => {
-                    eprintln!("[AUDIO-THREAD] Audio capture started successfully");
-                    tuple
+                // The demo generator stops by itself once raw_audio_rx is dropped, so only a stream is kept
+                let started = if demo_signal {
+                    demo::start_demo_signal(raw_audio_tx, &audio_config).map(|(_, info)| (None, info))
+                } else {
+                    audio::start_audio_capture(raw_audio_tx, stream_error_tx.clone(), &audio_config).map(|(stream, info)| (Some(stream), info))
+                };
+                let (mut stream, mut sample_rate, mut hop_size) = match started {
+                Ok((stream, info)) => {
+                    eprintln!("[AUDIO-THREAD] Audio capture started successfully: {}", info);
+                    let (sample_rate, hop_size) = (info.sample_rate, info.hop_size);
+                    // Never block on a full event queue, or the thread could miss its shutdown signal
+                    let _ = event_tx.try_send(AudioEvent::Started(info));
+                    (stream, sample_rate, hop_size)
                 },
                     Err(e) => {
                         eprintln!("[AUDIO-THREAD] Fatal Error starting audio: {}", e);
+                        let _ = event_tx.try_send(AudioEvent::Failed(e.to_string()));
                         return;
                     }
                 };
@@ -238,27 +605,34 @@ impl TunerApp {
             // Add a small delay to let GUI initialize
             std::thread::sleep(std::time::Duration::from_millis(100));
             
+                let mut config = AnalysisConfig::default();
+                let mut tracker = FrameTracker::new();
+                // Attempt number and timer of a pending reconnect to a lost input
+                let mut reconnect: Option<(u32, Receiver<Instant>)> = None;
                 loop {
+                    let reconnect_timer = reconnect.as_ref().map_or_else(crossbeam_channel::never, |(_, timer)| timer.clone());
+                    // A dead stream may never report an error, so silence from a live one counts as a loss too
+                    let stall_timeout = match (&stream, &reconnect) {
+                        (Some(_), None) => audio::INPUT_STALL_TIMEOUT,
+                        _ => Duration::MAX,
+                    };
+                    let mut lost_input: Option<String> = None;
                     crossbeam_channel::select! {
                         recv(raw_audio_rx) -> msg => match msg {
                             Ok(audio_frame) => {
                             // Add error handling for analysis
-                            let result = match std::panic::catch_unwind(|| {
-                                perform_analysis(&audio_frame, sample_rate)
+                            let mut result = match std::panic::catch_unwind(|| {
+                                analysis::analyze_frame(&audio_frame, sample_rate, &config)
                             }) {
                                 Ok(result) => result,
                                 Err(_) => {
                                     eprintln!("[AUDIO-THREAD] Analysis panicked, using default result");
-                                    AnalysisResult {
-                                        detected_frequency: None,
-                                        confidence: None,
-                                        cents_deviation: None,
-                                        note_name: None,
-                                        spectrogram_data: vec![],
-                                        partials: vec![],
-                                    }
+                                    AnalysisResult::default()
                                 }
                             };
+                            // Overlapping frames start one hop apart, not one frame length
+                            result.hop_size = hop_size;
+                            tracker.track(&mut result, hop_size as f32 / sample_rate as f32);
                             
                             if analysis_tx.send(result).is_err() { 
                                 eprintln!("[AUDIO-THREAD] Failed to send analysis result");
@@ -270,32 +644,178 @@ impl TunerApp {
                             break;
                         },
                     },
+                    recv(config_rx) -> msg => {
+                        if let Ok(new_config) = msg {
+                            config = new_config;
+                        }
+                    },
+                    recv(stream_error_rx) -> msg => {
+                        if let Ok(cpal::StreamError::DeviceNotAvailable) = msg {
+                            lost_input = Some("The input device was disconnected".to_string());
+                        }
+                    },
+                    recv(reconnect_timer) -> _ => {
+                        let Some((attempt, _)) = reconnect.take() else { continue };
+                        eprintln!("[AUDIO-THREAD] Reconnecting, attempt {} of {}...", attempt, audio::RECONNECT_ATTEMPTS);
+                        let Some(frame_tx) = reopen_tx.clone() else { break };
+                        match audio::reopen_audio_capture(frame_tx, stream_error_tx.clone(), &audio_config) {
+                            Ok((new_stream, info)) => {
+                                eprintln!("[AUDIO-THREAD] Audio capture reopened: {}", info);
+                                (sample_rate, hop_size) = (info.sample_rate, info.hop_size);
+                                stream = Some(new_stream);
+                                tracker = FrameTracker::new();
+                                let _ = event_tx.try_send(AudioEvent::Started(info));
+                            }
+                            Err(e) => match audio::reconnect_delay(attempt + 1) {
+                                Some(delay) => reconnect = Some((attempt + 1, crossbeam_channel::after(delay))),
+                                None => {
+                                    eprintln!("[AUDIO-THREAD] Giving up reconnecting: {}", e);
+                                    let _ = event_tx.try_send(AudioEvent::Failed(e.to_string()));
+                                    break;
+                                }
+                            },
+                        }
+                    },
                     recv(shutdown_rx) -> _ => {
                         eprintln!("[AUDIO-THREAD] Received shutdown signal");
                         break;
                     },
+                    default(stall_timeout) => {
+                        lost_input = Some("No audio received from the input device".to_string());
+                    },
+                }
+
+                if let Some(reason) = lost_input.filter(|_| stream.is_some()) {
+                    eprintln!("[AUDIO-THREAD] Input lost: {}", reason);
+                    if let Some(dead) = stream.take() {
+                        let _ = dead.pause();
+                    }
+                    // Drop whatever the old stream still had queued
+                    while stream_error_rx.try_recv().is_ok() {}
+                    let delay = audio::reconnect_delay(1).unwrap_or_default();
+                    reconnect = Some((1, crossbeam_channel::after(delay)));
+                    let _ = event_tx.try_send(AudioEvent::Reconnecting(reason));
                 }
             }
             
             eprintln!("[AUDIO-THREAD] Stopping stream and exiting...");
-            // Properly stop the stream before dropping it
-            if let Err(e) = stream.pause() {
-                eprintln!("[AUDIO-THREAD] Error pausing stream: {}", e);
+            if let Some(stream) = stream {
+                // Properly stop the stream before dropping it
+                if let Err(e) = stream.pause() {
+                    eprintln!("[AUDIO-THREAD] Error pausing stream: {}", e);
+                }
+                // Give the stream a moment to fully stop
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                drop(stream);
             }
-            // Give the stream a moment to fully stop
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            drop(stream);
             eprintln!("[AUDIO-THREAD] Audio thread finished");
         });
         self.audio_worker = Some(AudioWorker {
                 shutdown_tx,
+                config_tx,
+                event_rx,
                 thread_handle: Some(thread_handle),
             });
+        self.push_analysis_config();
         // Update the display data to reflect that audio is active
         self.display_data.audio_worker_active = true;
         }
     }
 
+    /// Stops the audio thread and starts a new one with the current audio settings.
+    ///
+    /// Used when a setting that is fixed when the stream is opened changes.
+    fn restart_audio_processing(&mut self) {
+        eprintln!("[MAIN] Restarting audio worker...");
+        self.stop_audio_processing();
+        // The old thread owned the sender, so start over with a fresh channel
+        let (analysis_tx, analysis_rx) = crossbeam_channel::unbounded();
+        self.analysis_receiver = Some(analysis_rx);
+        self.analysis_sender = Some(analysis_tx);
+        self.start_audio_processing();
+    }
+
+    /// Signals the audio thread to stop and waits for it to finish.
+    ///
+    /// The thread pauses and drops its stream before exiting, so once this
+    /// returns the input device has been released.
+    fn stop_audio_processing(&mut self) {
+        if let Some(mut worker) = self.audio_worker.take() {
+            eprintln!("[MAIN] Shutting down audio worker...");
+            // The thread may already have exited (e.g. audio failed to start)
+            let _ = worker.shutdown_tx.send(());
+            // Nobody reads its events any more, so they must not hold the thread up
+            drop(worker.event_rx);
+            if let Some(handle) = worker.thread_handle.take() {
+                eprintln!("[MAIN] Waiting for audio thread to finish...");
+                if handle.join().is_err() {
+                    eprintln!("[MAIN] Audio thread panicked while stopping");
+                }
+            }
+        }
+        self.display_data.audio_worker_active = false;
+    }
+
+    /// Builds the analysis settings implied by the current GUI state.
+    fn analysis_config(&self) -> AnalysisConfig {
+        let bandpass_center = match &self.display_data.tuning_mode {
+            TuningMode::Manual { target_freq, .. } if self.display_data.bandpass_enabled => Some(*target_freq),
+            _ => None, // Always bypassed in Auto mode
+        };
+        let partial_threshold = if self.display_data.noise_floor_partials {
+            PartialThreshold::AboveNoiseFloor(NOISE_FLOOR_PARTIAL_FACTOR)
+        } else {
+            PartialThreshold::default()
+        };
+        // Always the full band in Auto mode
+        let analysis_band = self.display_data.note_band().filter(|_| self.display_data.band_limit_enabled);
+        // The note isn't known ahead of time in Auto mode, so neither is its 'B'
+        let partial_search_b = match &self.display_data.tuning_mode {
+            TuningMode::Manual { key_index, .. } if self.display_data.stretched_search_enabled => {
+                self.inharmonicity_profile.b_for_key(*key_index, self.display_data.piano_type)
+            }
+            _ => None,
+        };
+        // The 2nd partial is looked for where the manual key puts it, so this needs a known key
+        let second_partial = match &self.display_data.tuning_mode {
+            TuningMode::Manual { key_index, target_freq, .. }
+                if self.display_data.second_partial_enabled && *key_index <= analysis::SECOND_PARTIAL_MAX_KEY =>
+            {
+                Some((*target_freq, self.inharmonicity_profile.b_for_key(*key_index, self.display_data.piano_type).unwrap_or(0.0)))
+            }
+            _ => None,
+        };
+        AnalysisConfig {
+            bandpass_center,
+            partial_threshold,
+            pitch_algorithm: self.display_data.pitch_algorithm,
+            analysis_band,
+            keyboard: self.display_data.keyboard,
+            polyphonic: self.display_data.polyphonic_enabled,
+            input_gain_db: self.display_data.input_gain_db,
+            min_snr_db: self.display_data.min_snr_db,
+            max_partials: self.display_data.measurement_partials,
+            partial_search_b,
+            second_partial,
+            ..AnalysisConfig::default()
+        }
+    }
+
+    /// Sends the current analysis settings to the audio thread.
+    fn push_analysis_config(&self) {
+        if let Some(worker) = &self.audio_worker {
+            let _ = worker.config_tx.send(self.analysis_config());
+        }
+    }
+
+    /// Follows up any change to the active profile's measurements.
+    ///
+    /// The profile's 'B' values steer the stretched partial search and the
+    /// 2nd-partial fit, so the audio thread gets the settings they imply.
+    fn profile_changed(&self) {
+        self.push_analysis_config();
+    }
+
     /// Handles application state updates based on incoming messages.
     /// 
     /// This function processes all user interactions and system events,
@@ -307,55 +827,99 @@ impl TunerApp {
     fn update(
         &mut self,
         message: Message
-    ) {
+    ) -> Task<Message> {
         eprintln!("[UPDATE] Received message: {:?}", message);
         
         match message {
             Message::Exit => {
                 eprintln!("[MAIN] Window close requested - starting cleanup...");
-                // Properly shutdown audio worker
-                if let Some(mut worker) = self.audio_worker.take() {
-                    eprintln!("[MAIN] Shutting down audio worker...");
-                    let _ = worker.shutdown_tx.send(());
-                    if let Some(handle) = worker.thread_handle.take() {
-                        eprintln!("[MAIN] Waiting for audio thread to finish...");
-                        // Use detach to avoid hanging on problematic thread join
-                        handle.thread().unpark();
-                        std::thread::sleep(std::time::Duration::from_millis(100));
-                        eprintln!("[MAIN] Audio thread detached - continuing cleanup");
-                    }
-                }
-                // Clear channels to prevent segfault
+                self.stop_audio_processing();
                 eprintln!("[MAIN] Clearing analysis channels...");
                 self.analysis_receiver = None;
                 self.analysis_sender = None;
-                eprintln!("[MAIN] Cleanup completed - forcing clean exit");
-                // Force clean exit to avoid segfault
-                std::process::exit(0);
+                eprintln!("[MAIN] Cleanup completed - exiting");
+                return iced::exit();
             }
             Message::KeySelected(key_index) => {
+                // Picking a key by hand takes over from a running sweep
+                self.display_data.sweep = None;
+                self.display_data.keyboard_notice = None;
+
                 // Check if the same key is already selected - if so, switch to auto mode
                 if let TuningMode::Manual { key_index: current_key, .. } = &self.display_data.tuning_mode {
                     if *current_key == key_index {
                         // Same key clicked again - switch to auto mode
                         self.display_data.tuning_mode = TuningMode::Auto;
                         self.display_data.smoothing_buffer.clear();
-                        return;
+                        self.push_analysis_config();
+                        return Task::none();
                     }
                 }
                 
                 // Different key or not in manual mode - switch to manual mode with new key
-                let (note_name, target_freq) = tuning::find_nearest_note_by_index(key_index);
-                self.display_data.tuning_mode = TuningMode::Manual {
-                    key_index,
-                    note_name,
-                    target_freq,
-                };
-                self.display_data.smoothing_buffer.clear();
+                self.select_manual_key(key_index);
+            }
+            Message::KeyOutsideProfile(note_name) => {
+                // Profiles, stretch curves and targets cover A0-C8 only, so the
+                // key can be detected and drawn but not tuned by hand or captured
+                eprintln!("[MAIN] {} is outside the 88-key profile range", note_name);
+                self.display_data.keyboard_notice =
+                    Some(format!("{} is outside the 88-key profile range and can't be selected or captured", note_name));
             }
             Message::SwitchToAutoMode => {
+                self.display_data.sweep = None;
                 self.display_data.tuning_mode = TuningMode::Auto;
                 self.display_data.smoothing_buffer.clear();
+                self.push_analysis_config();
+            }
+            Message::TuneReferenceA => {
+                eprintln!("[MAIN] Quick-tuning A4 against {} Hz", tuning::REFERENCE_A4_HZ);
+                return self.update(Message::KeySelected(tuning::A4_KEY_INDEX as u8));
+            }
+            Message::SelectPartial(partial) => {
+                eprintln!("[MAIN] Target partial set to {}", partial);
+                self.display_data.target_partial = partial.max(1);
+                self.display_data.smoothing_buffer.clear();
+            }
+            Message::PlayReference => {
+                if self.reference_tone_playing() {
+                    eprintln!("[MAIN] Reference tone already playing");
+                } else {
+                    self.play_reference(REFERENCE_TONE_MS);
+                }
+            }
+            Message::StartSweep { from_key, to_key, interval_ms } => {
+                if self.display_data.sweep.is_some() {
+                    // The sidebar button doubles as the stop button
+                    return self.update(Message::StopSweep);
+                }
+                let (from_key, to_key) = (from_key.min(87), to_key.min(87));
+                eprintln!(
+                    "[MAIN] Sweeping {} to {} every {} ms",
+                    tuning::key_name(from_key, self.display_data.octave_convention),
+                    tuning::key_name(to_key, self.display_data.octave_convention),
+                    interval_ms
+                );
+                self.display_data.sweep = Some(ReferenceSweep { to_key, interval_ms: interval_ms.max(1) });
+                self.select_manual_key(from_key);
+                self.play_sweep_tone();
+            }
+            Message::SweepStep => {
+                let (Some(sweep), TuningMode::Manual { key_index, .. }) = (self.display_data.sweep, &self.display_data.tuning_mode) else {
+                    return Task::none();
+                };
+                let key_index = *key_index;
+                if key_index == sweep.to_key {
+                    return self.update(Message::StopSweep);
+                }
+                let next_key = if sweep.to_key > key_index { key_index + 1 } else { key_index - 1 };
+                self.select_manual_key(next_key);
+                self.play_sweep_tone();
+            }
+            Message::StopSweep => {
+                if self.display_data.sweep.take().is_some() {
+                    eprintln!("[MAIN] Sweep stopped");
+                }
             }
             Message::ToggleMeasurementMode => {
                 // This toggles the measurement mode on/off
@@ -366,12 +930,12 @@ impl TunerApp {
                     }
                     CaptureState::Armed => {
                         eprintln!("[MAIN] Measurement mode OFF");
-                        self.stability_buffer.clear();
+                        self.capture_session.stop();
                         CaptureState::Off
                     }
                     CaptureState::Capturing => {
                         eprintln!("[MAIN] Measurement mode OFF (from Capturing)");
-                        self.stability_buffer.clear();
+                        self.capture_session.stop();
                         CaptureState::Off
                     }
                     CaptureState::Done => {
@@ -380,17 +944,37 @@ impl TunerApp {
                         CaptureState::Off
                     }
                 };
+                if self.display_data.capture_state == CaptureState::Off {
+                    self.display_data.auto_measure = false;
+                    self.capture_session.set_auto_measure(false);
+                }
+            }
+            Message::ToggleAutoMeasure => {
+                self.display_data.auto_measure = !self.display_data.auto_measure;
+                self.capture_session.set_auto_measure(self.display_data.auto_measure);
+                if self.display_data.auto_measure {
+                    // Enter measurement mode if needed and start listening straight away
+                    eprintln!("[MAIN] Auto-measure ON - capturing each new stable note");
+                    self.start_capturing();
+                } else {
+                    eprintln!("[MAIN] Auto-measure OFF");
+                    if self.display_data.capture_state == CaptureState::Capturing {
+                        self.display_data.capture_state = CaptureState::Armed;
+                        self.capture_session.stop();
+                    }
+                }
             }
             Message::CaptureButtonClicked => {
                 // This handles the capture button click behavior
                 match self.display_data.capture_state {
                     CaptureState::Armed => {
                         eprintln!("[MAIN] Capture button clicked - starting capture");
-                        self.display_data.capture_state = CaptureState::Capturing;
+                        self.start_capturing();
                     }
                     CaptureState::Capturing => {
                         eprintln!("[MAIN] Capture button clicked - stopping capture");
                         self.display_data.capture_state = CaptureState::Armed;
+                        self.capture_session.stop();
                     }
                     CaptureState::Done => {
                         eprintln!("[MAIN] Capture button clicked - resetting to Off");
@@ -402,21 +986,174 @@ impl TunerApp {
                     }
                 }
             }
-            Message::SaveProfile => {
-                match save_profile(&self.inharmonicity_profile, "tuning_profile.json") {
-                    Ok(_) => eprintln!("[MAIN] Tuning profile saved successfully."),
-                    Err(e) => eprintln!("[MAIN] Error saving profile: {}", e),
+            Message::ToggleBeatTrainer => {
+                self.display_data.beat_reference_key = match (self.display_data.beat_reference_key, &self.display_data.tuning_mode) {
+                    (Some(_), _) => None,
+                    (None, TuningMode::Manual { key_index, .. }) => Some(*key_index),
+                    (None, TuningMode::Auto) => {
+                        eprintln!("[MAIN] Select the first key of the interval before starting the beat trainer");
+                        None
+                    }
+                };
+                eprintln!("[MAIN] Beat trainer reference key: {:?}", self.display_data.beat_reference_key);
+            }
+            Message::UndoLastMeasurement => {
+                match self.measurement_history.pop() {
+                    Some((key_index, previous)) => {
+                        let note_name = tuning::key_name(key_index, self.display_data.octave_convention);
+                        match previous {
+                            Some(measurement) => {
+                                self.inharmonicity_profile.insert_measurement(measurement);
+                            }
+                            None => {
+                                self.inharmonicity_profile.remove_measurement(key_index);
+                            }
+                        }
+                        if self.capture_session.last_capture_key() == Some(key_index) {
+                            self.capture_session.forget_last_capture(); // Allow the key to be re-measured straight away
+                        }
+                        eprintln!("[MAIN] Undid measurement of {}", note_name);
+                        self.display_data.capture_status = Some(format!("Undid {}", note_name));
+                        self.profile_changed();
+                    }
+                    None => eprintln!("[MAIN] No measurement to undo"),
                 }
             }
+            Message::SaveProfile => {
+                let status = match self.inharmonicity_profile.save(PROFILE_PATH) {
+                    Ok(_) => format!("Profile saved to {}", PROFILE_PATH),
+                    Err(e) => format!("Could not save {}: {}", PROFILE_PATH, e),
+                };
+                eprintln!("[MAIN] {}", status);
+                self.display_data.profile_status = Some(status);
+            }
             Message::LoadProfile => {
-                match load_profile("tuning_profile.json") {
+                let status = match InharmonicityProfile::load(PROFILE_PATH) {
                     Ok(profile) => {
                         self.inharmonicity_profile = profile;
-                        eprintln!("[MAIN] Tuning profile loaded successfully.");
+                        self.measurement_history.clear();
+                        self.profile_changed();
+                        format!("Profile loaded from {}", PROFILE_PATH)
+                    }
+                    Err(e) => format!("Could not load {}: {}", PROFILE_PATH, e),
+                };
+                eprintln!("[MAIN] {}", status);
+                self.display_data.profile_status = Some(status);
+            }
+            Message::ProfileNameChanged(name) => {
+                self.display_data.new_profile_name = name;
+            }
+            Message::CreateProfile => {
+                let typed = self.display_data.new_profile_name.trim().to_string();
+                let name = if typed.is_empty() {
+                    // "Piano N" with the first N not already taken
+                    (1..)
+                        .map(|n| format!("Piano {}", n))
+                        .find(|name| !self.profile_library.profiles.contains_key(name) && name != &self.display_data.active_profile)
+                        .unwrap_or_default()
+                } else {
+                    typed
+                };
+                self.stash_active_profile();
+                // An existing name opens that profile rather than overwriting it
+                let profile = match self.profile_library.profiles.get(&name) {
+                    Some(existing) => {
+                        eprintln!("[MAIN] Profile \"{}\" already exists; selected it", name);
+                        existing.clone()
+                    }
+                    None => {
+                        eprintln!("[MAIN] Created profile \"{}\"", name);
+                        InharmonicityProfile::default()
                     }
-                    Err(e) => eprintln!("[MAIN] Error loading profile: {}", e),
+                };
+                self.switch_profile(name, profile);
+                self.display_data.new_profile_name.clear();
+            }
+            Message::SelectNextProfile => {
+                self.stash_active_profile();
+                let names: Vec<&String> = self.profile_library.profiles.keys().collect();
+                let index = names.iter().position(|&name| name == &self.display_data.active_profile).unwrap_or(0);
+                let next = names[(index + 1) % names.len()].clone();
+                let profile = self.profile_library.profiles[&next].clone();
+                eprintln!("[MAIN] Profile: {} -> {}", self.display_data.active_profile, next);
+                self.switch_profile(next, profile);
+            }
+            Message::DeleteProfile => {
+                let deleted = self.display_data.active_profile.clone();
+                self.profile_library.profiles.remove(&deleted);
+                eprintln!("[MAIN] Deleted profile \"{}\"", deleted);
+                // Continue with the next remaining profile, or a fresh one if none is left
+                let next = self.profile_library.profiles
+                    .range(deleted.clone()..)
+                    .next()
+                    .or_else(|| self.profile_library.profiles.iter().next())
+                    .map(|(name, profile)| (name.clone(), profile.clone()));
+                match next {
+                    Some((name, profile)) => self.switch_profile(name, profile),
+                    None => self.switch_profile(DEFAULT_PROFILE_NAME.to_string(), InharmonicityProfile::default()),
                 }
             }
+            Message::SaveLibrary => {
+                self.stash_active_profile();
+                let status = match self.profile_library.save(PROFILE_LIBRARY_PATH) {
+                    Ok(_) => format!("{} profiles saved to {}", self.profile_library.profiles.len(), PROFILE_LIBRARY_PATH),
+                    Err(e) => format!("Could not save {}: {}", PROFILE_LIBRARY_PATH, e),
+                };
+                eprintln!("[MAIN] {}", status);
+                self.display_data.profile_status = Some(status);
+            }
+            Message::LoadLibrary => {
+                match ProfileLibrary::load(PROFILE_LIBRARY_PATH) {
+                    Ok(library) => {
+                        self.profile_library = library;
+                        // Keep working on the active profile if the library has it, otherwise open the first
+                        let active = self.display_data.active_profile.clone();
+                        let selected = self.profile_library.profiles
+                            .get_key_value(&active)
+                            .or_else(|| self.profile_library.profiles.iter().next())
+                            .map(|(name, profile)| (name.clone(), profile.clone()));
+                        if let Some((name, profile)) = selected {
+                            self.switch_profile(name, profile);
+                        }
+                        let status = format!("{} profiles loaded from {}", self.profile_library.profiles.len(), PROFILE_LIBRARY_PATH);
+                        eprintln!("[MAIN] {}", status);
+                        self.display_data.profile_status = Some(status);
+                    }
+                    Err(e) => {
+                        let status = format!("Could not load {}: {}", PROFILE_LIBRARY_PATH, e);
+                        eprintln!("[MAIN] {}", status);
+                        self.display_data.profile_status = Some(status);
+                    }
+                }
+            }
+            Message::ExportTuningTable => {
+                let table = self.inharmonicity_profile.tuning_table(self.display_data.piano_type);
+                let saved = inharmonicity::save_tuning_table_csv("tuning_table.csv", &table)
+                    .and_then(|_| inharmonicity::save_tuning_table_json("tuning_table.json", &table));
+                match saved {
+                    Ok(_) => eprintln!("[MAIN] Tuning table exported to tuning_table.csv and tuning_table.json"),
+                    Err(e) => eprintln!("[MAIN] Error exporting tuning table: {}", e),
+                }
+            }
+            Message::CompareWithSavedProfile => {
+                match InharmonicityProfile::load(PROFILE_PATH) {
+                    Ok(saved) => {
+                        let diffs = inharmonicity::profile_diff(&saved, &self.inharmonicity_profile);
+                        match inharmonicity::save_profile_diff_csv("profile_diff.csv", &diffs) {
+                            Ok(_) => eprintln!("[MAIN] Compared {} keys with the saved profile; written to profile_diff.csv", diffs.len()),
+                            Err(e) => eprintln!("[MAIN] Error writing profile comparison: {}", e),
+                        }
+                    }
+                    Err(e) => eprintln!("[MAIN] Error loading profile to compare with: {}", e),
+                }
+            }
+            Message::RecalculateProfile => {
+                let total = self.inharmonicity_profile.measurements.len();
+                let with_b = self.inharmonicity_profile.recalculate();
+                eprintln!("[MAIN] Recalculated B for {} of {} measured keys", with_b, total);
+                self.display_data.capture_status = Some(format!("Recalculated B for {} of {} keys", with_b, total));
+                self.profile_changed();
+            }
             // ------------------------------------------
 
             Message::Temperament => {
@@ -426,10 +1163,79 @@ impl TunerApp {
                 // Placeholder for tuning standard settings
             }
             Message::InharmonicCurve => {
-                // Placeholder for inharmonic curve adjustment
+                // Off -> measured keys only -> each preset in turn -> off
+                let (enabled, piano_type) = match (self.display_data.stretch_enabled, self.display_data.piano_type) {
+                    (false, _) => (true, None),
+                    (true, None) => (true, Some(PianoType::ALL[0])),
+                    (true, Some(current)) => {
+                        let index = PianoType::ALL.iter().position(|&p| p == current).unwrap_or(0);
+                        match PianoType::ALL.get(index + 1) {
+                            Some(&next) => (true, Some(next)),
+                            None => (false, None),
+                        }
+                    }
+                };
+                eprintln!("[MAIN] Stretch tuning: enabled={}, preset={:?}", enabled, piano_type);
+                self.display_data.stretch_enabled = enabled;
+                self.display_data.piano_type = piano_type;
+                self.display_data.smoothing_buffer.clear();
+                self.push_analysis_config();
+            }
+            Message::ToggleStereoCoherence => {
+                let enabled = !self.display_data.audio_config.stereo_coherence;
+                eprintln!("[MAIN] Stereo coherence: {} -> {}", !enabled, enabled);
+                self.display_data.audio_config.stereo_coherence = enabled;
+                self.restart_audio_processing();
+            }
+            Message::CycleInputDevice => {
+                // None (the system default) followed by every device the host lists
+                let choices: Vec<Option<String>> = std::iter::once(None)
+                    .chain(audio::input_device_names().into_iter().map(Some))
+                    .collect();
+                let current = &self.display_data.audio_config.input_device;
+                let index = choices.iter().position(|c| c == current).unwrap_or(0);
+                let next = choices[(index + 1) % choices.len()].clone();
+                eprintln!("[MAIN] Input device: {:?} -> {:?}", current, next);
+                self.display_data.audio_config.input_device = next;
+                self.restart_audio_processing();
+            }
+            Message::RetryAudio => {
+                eprintln!("[MAIN] Retrying audio input...");
+                self.restart_audio_processing();
             }
             Message::SampleBuffer => {
-                // Placeholder for sample buffer adjustment
+                let current = self.display_data.audio_config.stream_buffer;
+                let index = STREAM_BUFFER_CHOICES.iter().position(|&c| c == current).unwrap_or(0);
+                let next = STREAM_BUFFER_CHOICES[(index + 1) % STREAM_BUFFER_CHOICES.len()];
+                eprintln!("[MAIN] Stream buffer: {:?} -> {:?}", current, next);
+                self.display_data.audio_config.stream_buffer = next;
+                self.restart_audio_processing();
+            }
+            Message::CycleFrameOverlap => {
+                let (frame_size, hop_size) = self.display_data.audio_config.frame_and_hop();
+                let current = frame_size / hop_size;
+                let index = OVERLAP_CHOICES.iter().position(|&c| c == current).unwrap_or(0);
+                let next = OVERLAP_CHOICES[(index + 1) % OVERLAP_CHOICES.len()];
+                eprintln!("[MAIN] Frames per hop: {} -> {}", current, next);
+                // No overlap leaves the hop unset, so it follows later frame size changes
+                self.display_data.audio_config.hop_size = (next > 1).then(|| frame_size / next);
+                self.display_data.spectrogram_history.clear();
+                self.restart_audio_processing();
+            }
+            Message::CycleFrameSize => {
+                let config = &mut self.display_data.audio_config;
+                let (current, hop_size) = config.frame_and_hop();
+                let index = FRAME_SIZE_CHOICES.iter().position(|&c| c == current).unwrap_or(0);
+                let next = FRAME_SIZE_CHOICES[(index + 1) % FRAME_SIZE_CHOICES.len()];
+                eprintln!("[MAIN] Frame size: {} -> {}", current, next);
+                // Keep the overlap, not the hop, so a longer frame doesn't multiply the analysis load
+                if config.hop_size.is_some() {
+                    config.hop_size = Some(next / (current / hop_size).max(1));
+                }
+                config.frame_size = next;
+                // Every spectrum changes length, so the waterfall starts over
+                self.display_data.spectrogram_history.clear();
+                self.restart_audio_processing();
             }
             Message::TuningProfile => {
                 // Placeholder for tuning profile settings
@@ -446,11 +1252,227 @@ impl TunerApp {
                 eprintln!("[MAIN] Toggling key select visibility: {} -> {}", self.display_data.key_select_visible, !self.display_data.key_select_visible);
                 self.display_data.key_select_visible = !self.display_data.key_select_visible;
             }
+            Message::TogglePitchHistory => {
+                eprintln!("[MAIN] Toggling pitch history visibility: {} -> {}", self.display_data.pitch_history_visible, !self.display_data.pitch_history_visible);
+                self.display_data.pitch_history_visible = !self.display_data.pitch_history_visible;
+            }
+            Message::ToggleNoteHistory => {
+                eprintln!("[MAIN] Toggling note history visibility: {} -> {}", self.display_data.note_history_visible, !self.display_data.note_history_visible);
+                self.display_data.note_history_visible = !self.display_data.note_history_visible;
+            }
             Message::TogglePartials => {
                 eprintln!("[MAIN] Toggling partials visibility: {} -> {}", self.display_data.partials_visible, !self.display_data.partials_visible);
                 self.display_data.partials_visible = !self.display_data.partials_visible;
             }
+            Message::ToggleBandpass => {
+                eprintln!("[MAIN] Toggling band-pass pre-filter: {} -> {}", self.display_data.bandpass_enabled, !self.display_data.bandpass_enabled);
+                self.display_data.bandpass_enabled = !self.display_data.bandpass_enabled;
+                self.push_analysis_config();
+            }
+            Message::TogglePartialDisplayMode => {
+                self.display_data.partial_display_mode = match self.display_data.partial_display_mode {
+                    PartialDisplayMode::Hz => PartialDisplayMode::CentsFromHarmonic,
+                    PartialDisplayMode::CentsFromHarmonic => PartialDisplayMode::Hz,
+                };
+            }
+            Message::CycleSpectrumRange => {
+                let next = self.display_data.spectrum_range.next();
+                eprintln!("[MAIN] Spectrum range: {} -> {}", self.display_data.spectrum_range.label(), next.label());
+                self.display_data.spectrum_range = next;
+            }
+            Message::CycleConfidenceDisplay => {
+                self.display_data.confidence_display = self.display_data.confidence_display.next();
+            }
+            Message::CycleNeedleDamping => {
+                let current = self.display_data.needle_dynamics;
+                let index = NEEDLE_DAMPING_CHOICES.iter().position(|&(_, d)| d == current).unwrap_or(0);
+                let (label, next) = NEEDLE_DAMPING_CHOICES[(index + 1) % NEEDLE_DAMPING_CHOICES.len()];
+                eprintln!("[MAIN] Needle damping: {}", label);
+                self.display_data.needle_dynamics = next;
+            }
+            Message::CyclePalette => {
+                let index = Palette::PRESETS.iter().position(|&(_, p)| p == self.display_data.palette).unwrap_or(0);
+                let (label, next) = Palette::PRESETS[(index + 1) % Palette::PRESETS.len()];
+                eprintln!("[MAIN] Color palette: {}", label);
+                self.display_data.palette = next;
+            }
+            Message::CycleDoneDisplayTime => {
+                let current = self.display_data.done_display_time;
+                let index = DONE_DISPLAY_CHOICES.iter().position(|&d| d == current).unwrap_or(0);
+                let next = DONE_DISPLAY_CHOICES[(index + 1) % DONE_DISPLAY_CHOICES.len()];
+                eprintln!("[MAIN] Done confirmation: {:?} -> {:?}", current, next);
+                self.display_data.done_display_time = next;
+            }
+            Message::CycleSmoothing => {
+                let current = self.display_data.smoothing_samples;
+                let index = SMOOTHING_CHOICES.iter().position(|&n| n == current).unwrap_or(0);
+                let next = SMOOTHING_CHOICES[(index + 1) % SMOOTHING_CHOICES.len()];
+                eprintln!("[MAIN] Cent smoothing: {} -> {} samples", current, next);
+                self.display_data.smoothing_samples = next;
+            }
+            Message::CycleMeasurementPartials => {
+                let current = self.display_data.measurement_partials;
+                let index = MEASUREMENT_PARTIAL_CHOICES.iter().position(|&n| n == current).unwrap_or(0);
+                let next = MEASUREMENT_PARTIAL_CHOICES[(index + 1) % MEASUREMENT_PARTIAL_CHOICES.len()];
+                eprintln!("[MAIN] Measurement partials: {} -> {}", current, next);
+                self.display_data.measurement_partials = next;
+                self.push_analysis_config();
+            }
+            Message::CycleDisplayedPartials => {
+                let current = self.display_data.displayed_partials;
+                let index = DISPLAYED_PARTIAL_CHOICES.iter().position(|&n| n == current).unwrap_or(0);
+                let next = DISPLAYED_PARTIAL_CHOICES[(index + 1) % DISPLAYED_PARTIAL_CHOICES.len()];
+                eprintln!("[MAIN] Displayed partials: {} -> {}", current, next);
+                self.display_data.displayed_partials = next;
+            }
+            Message::CycleFrameRate => {
+                let current = self.display_data.frame_rate;
+                let index = FRAME_RATE_CHOICES.iter().position(|&fps| fps == current).unwrap_or(0);
+                let next = FRAME_RATE_CHOICES[(index + 1) % FRAME_RATE_CHOICES.len()];
+                eprintln!("[MAIN] Frame rate: {} -> {} FPS", current, next);
+                self.display_data.frame_rate = next;
+            }
+            Message::ToggleIdleThrottling => {
+                eprintln!("[MAIN] Toggling idle throttling: {} -> {}", self.display_data.idle_throttling, !self.display_data.idle_throttling);
+                self.display_data.idle_throttling = !self.display_data.idle_throttling;
+            }
+            Message::ToggleBandLimit => {
+                eprintln!("[MAIN] Toggling band-limited analysis: {} -> {}", self.display_data.band_limit_enabled, !self.display_data.band_limit_enabled);
+                self.display_data.band_limit_enabled = !self.display_data.band_limit_enabled;
+                self.push_analysis_config();
+            }
+            Message::ToggleSecondPartialReference => {
+                eprintln!("[MAIN] Toggling 2nd partial reference: {} -> {}", self.display_data.second_partial_enabled, !self.display_data.second_partial_enabled);
+                self.display_data.second_partial_enabled = !self.display_data.second_partial_enabled;
+                self.push_analysis_config();
+            }
+            Message::ToggleKeyFilter => {
+                eprintln!("[MAIN] Toggling key filter: {} -> {}", self.display_data.key_filter_enabled, !self.display_data.key_filter_enabled);
+                self.display_data.key_filter_enabled = !self.display_data.key_filter_enabled;
+            }
+            Message::ToggleStretchedSearch => {
+                eprintln!("[MAIN] Toggling stretched partial search: {} -> {}", self.display_data.stretched_search_enabled, !self.display_data.stretched_search_enabled);
+                self.display_data.stretched_search_enabled = !self.display_data.stretched_search_enabled;
+                self.push_analysis_config();
+            }
+            Message::ToggleEnvelopeCapture => {
+                self.display_data.processing_operation = match self.display_data.processing_operation {
+                    ProcessingOperation::Envelope => ProcessingOperation::BestConfidence,
+                    _ => ProcessingOperation::Envelope,
+                };
+                eprintln!("[MAIN] Capture processing operation: {:?}", self.display_data.processing_operation);
+            }
+            Message::TogglePartialPeakCapture => {
+                self.display_data.processing_operation = match self.display_data.processing_operation {
+                    ProcessingOperation::PartialPeak => ProcessingOperation::BestConfidence,
+                    _ => ProcessingOperation::PartialPeak,
+                };
+                eprintln!("[MAIN] Capture processing operation: {:?}", self.display_data.processing_operation);
+            }
+            Message::ToggleWeightedAverageCapture => {
+                self.display_data.processing_operation = match self.display_data.processing_operation {
+                    ProcessingOperation::WeightedAverage => ProcessingOperation::BestConfidence,
+                    _ => ProcessingOperation::WeightedAverage,
+                };
+                eprintln!("[MAIN] Capture processing operation: {:?}", self.display_data.processing_operation);
+            }
+            Message::CycleSnrGate => {
+                let current = self.display_data.min_snr_db;
+                let index = SNR_GATE_CHOICES.iter().position(|&db| db == current).unwrap_or(0);
+                let next = SNR_GATE_CHOICES[(index + 1) % SNR_GATE_CHOICES.len()];
+                eprintln!("[MAIN] SNR gate: {} -> {} dB", current, next);
+                self.display_data.min_snr_db = next;
+                self.push_analysis_config();
+            }
+            Message::CyclePitchAlgorithm => {
+                self.display_data.pitch_algorithm = self.display_data.pitch_algorithm.next();
+                eprintln!("[MAIN] Pitch algorithm: {:?}", self.display_data.pitch_algorithm);
+                self.push_analysis_config();
+            }
+            Message::TogglePartialThreshold => {
+                eprintln!("[MAIN] Toggling noise-floor partial threshold: {} -> {}", self.display_data.noise_floor_partials, !self.display_data.noise_floor_partials);
+                self.display_data.noise_floor_partials = !self.display_data.noise_floor_partials;
+                self.push_analysis_config();
+            }
+            Message::NoiseFloorChanged(noise_floor_db) => {
+                self.display_data.noise_floor_db = noise_floor_db;
+            }
+            Message::InputGainChanged(gain_db) => {
+                self.display_data.input_gain_db = gain_db.clamp(0.0, analysis::MAX_INPUT_GAIN_DB);
+                self.push_analysis_config();
+            }
+            Message::TogglePolyphonic => {
+                eprintln!("[MAIN] Toggling polyphonic detection: {} -> {}", self.display_data.polyphonic_enabled, !self.display_data.polyphonic_enabled);
+                self.display_data.polyphonic_enabled = !self.display_data.polyphonic_enabled;
+                self.push_analysis_config();
+            }
+            Message::CycleKeyboard => {
+                let index = KEYBOARD_CHOICES.iter().position(|&k| k == self.display_data.keyboard).unwrap_or(0);
+                let next = KEYBOARD_CHOICES[(index + 1) % KEYBOARD_CHOICES.len()];
+                eprintln!("[MAIN] Keyboard: {} -> {}", self.display_data.keyboard.label(), next.label());
+                self.display_data.keyboard = next;
+                self.display_data.keyboard_notice = None;
+                self.display_data.locked_note = None; // Positions differ between keyboards
+                self.push_analysis_config();
+            }
+            Message::ToggleCentsReadout => {
+                eprintln!("[MAIN] Toggling cents readout: {} -> {}", self.display_data.cents_readout_visible, !self.display_data.cents_readout_visible);
+                self.display_data.cents_readout_visible = !self.display_data.cents_readout_visible;
+            }
+            Message::ToggleNoteLock => {
+                eprintln!("[MAIN] Toggling note lock: {} -> {}", self.display_data.note_lock_enabled, !self.display_data.note_lock_enabled);
+                self.display_data.note_lock_enabled = !self.display_data.note_lock_enabled;
+                self.display_data.locked_note = None;
+            }
+            Message::ToggleOctaveConvention => {
+                self.display_data.octave_convention = match self.display_data.octave_convention {
+                    OctaveConvention::Scientific => OctaveConvention::Yamaha,
+                    OctaveConvention::Yamaha => OctaveConvention::Scientific,
+                };
+                eprintln!("[MAIN] Octave convention: {:?}", self.display_data.octave_convention);
+            }
+            Message::ToggleSpectrumSmoothing => {
+                eprintln!("[MAIN] Toggling spectrum smoothing: {} -> {}", self.display_data.spectrum_smoothing_enabled, !self.display_data.spectrum_smoothing_enabled);
+                self.display_data.spectrum_smoothing_enabled = !self.display_data.spectrum_smoothing_enabled;
+                self.display_data.smoothed_spectrum.clear();
+            }
+            Message::TogglePeakHold => {
+                eprintln!("[MAIN] Toggling peak hold: {} -> {}", self.display_data.peak_hold_enabled, !self.display_data.peak_hold_enabled);
+                self.display_data.peak_hold_enabled = !self.display_data.peak_hold_enabled;
+                self.display_data.peak_hold.clear();
+            }
+            Message::ToggleWaterfall => {
+                eprintln!("[MAIN] Toggling waterfall view: {} -> {}", self.display_data.waterfall_enabled, !self.display_data.waterfall_enabled);
+                self.display_data.waterfall_enabled = !self.display_data.waterfall_enabled;
+                self.display_data.spectrogram_history.clear();
+            }
             Message::Tick => {
+                // Find out whether the audio thread managed to open its stream; every
+                // pending event is applied in order, so the latest one wins
+                let events: Vec<AudioEvent> = match &self.audio_worker {
+                    Some(worker) => worker.event_rx.try_iter().collect(),
+                    None => Vec::new(),
+                };
+                for event in events {
+                    match event {
+                        AudioEvent::Started(info) => {
+                            eprintln!("[MAIN] Audio input running: {}", info);
+                            self.display_data.audio_error = None;
+                            self.display_data.input_info = Some(info);
+                        }
+                        AudioEvent::Failed(e) => {
+                            eprintln!("[MAIN] Audio input unavailable: {}", e);
+                            self.display_data.audio_error = Some(e);
+                            self.display_data.input_info = None;
+                        }
+                        AudioEvent::Reconnecting(reason) => {
+                            eprintln!("[MAIN] Audio input lost, reconnecting: {}", reason);
+                            self.display_data.audio_error = Some(format!("{}; reconnecting...", reason));
+                            self.display_data.input_info = None;
+                        }
+                    }
+                }
+
                 // Continuous update - poll for audio data
                 if let Some(receiver) = &self.analysis_receiver {
                     // --- REFACTORED: Delegate result processing ---
@@ -468,12 +1490,32 @@ impl TunerApp {
 
                 // --- State reset after capture processing ---
                 if self.display_data.capture_state == CaptureState::Done {
-                    // Reset state after capture is processed
-                    eprintln!("[MAIN] Capture complete. Resetting state to Armed.");
-                    self.display_data.capture_state = CaptureState::Armed;
+                    // Reset state after capture is processed; auto-measure re-arms immediately
+                    if self.display_data.auto_measure {
+                        eprintln!("[MAIN] Capture complete. Waiting for the next note.");
+                        self.start_capturing();
+                    } else {
+                        eprintln!("[MAIN] Capture complete. Resetting state to Armed.");
+                        self.display_data.capture_state = CaptureState::Armed;
+                    }
                 }
             }
         }
+        self.save_ui_settings();
+        Task::none()
+    }
+
+    /// Writes the UI preferences to `UI_SETTINGS_PATH` if they changed since the last save.
+    fn save_ui_settings(&mut self) {
+        let settings = UiSettings::from_display(&self.display_data);
+        if settings == self.ui_settings {
+            return;
+        }
+        match settings.save(UI_SETTINGS_PATH) {
+            Ok(_) => eprintln!("[MAIN] UI settings saved to {}", UI_SETTINGS_PATH),
+            Err(e) => eprintln!("[MAIN] Error saving UI settings: {}", e),
+        }
+        self.ui_settings = settings;
     }
 
     // --- ADDED: New helper function to process analysis results ---
@@ -485,56 +1527,266 @@ impl TunerApp {
     /// - Triggering the capture process when stable
     /// - Updating the cent smoothing buffer
     /// - Storing the latest analysis result
-    fn process_analysis_result(&mut self, result: AnalysisResult) {
+    fn process_analysis_result(&mut self, mut result: AnalysisResult) {
+        // --- Key Filter ---
+        // A neighbouring string ringing along must not move the reading or be
+        // captured, so in manual mode any other note counts as no pitch at all.
+        if let (true, TuningMode::Manual { key_index, .. }) = (self.display_data.key_filter_enabled, &self.display_data.tuning_mode) {
+            let detected_key = result.note_name
+                .as_deref()
+                .and_then(|name| tuning::key_index_from_name_in(name, OctaveConvention::Scientific));
+            if detected_key.is_some_and(|key| key != *key_index) {
+                result.clear_pitch();
+            }
+        }
+
+        if result.detected_frequency.is_some() {
+            self.last_pitch_at = Instant::now();
+        }
+
         // --- Stability-Gated Capture Logic ---
-        if self.display_data.capture_state == CaptureState::Capturing {
-            self.stability_buffer.push_back(result.clone()); // Clone for stability check
-
-            if self.stability_buffer.len() > STABILITY_TARGET {
-                self.stability_buffer.pop_front();
-            }
-
-            if self.stability_buffer.len() == STABILITY_TARGET {
-                if check_stability(&self.stability_buffer) {
-                    eprintln!("[MAIN] STABILITY DETECTED! Capturing...");
-                    self.display_data.capture_state = CaptureState::Done;
-                    // Convert stability buffer to Vec and process it
-                    let stability_data: Vec<AnalysisResult> = self.stability_buffer.drain(..).collect();
-                    // Call the processing function with the stability buffer using default operation
-                    if let Some(measurement) = capture_processing::process(stability_data, ProcessingOperation::BestConfidence) {
-                        // Store the measurement in the profile
-                        self.inharmonicity_profile
-                            .measurements
-                            .insert(measurement.key_index, measurement);
+        // The microphone hears the reference tone, so never capture it as a measurement.
+        if self.reference_tone_playing() {
+            self.capture_session.interrupt();
+        } else if self.display_data.capture_state == CaptureState::Capturing {
+            let operation = self.display_data.processing_operation.clone();
+            if let Some(outcome) = self.capture_session.push(&result, operation) {
+                eprintln!("[MAIN] STABILITY DETECTED! Capturing...");
+                self.display_data.capture_state = CaptureState::Done;
+                match outcome {
+                    Ok(measurement) => {
+                        if self.display_data.auto_measure {
+                            eprintln!("[MAIN] Auto-measure captured key {}", measurement.key_index);
+                        }
+                        let note_name = tuning::key_name(measurement.key_index, self.display_data.octave_convention);
+                        self.display_data.capture_status = Some(match measurement.calculated_b {
+                            // Stored all the same, but worth striking again
+                            Some(b) if !measurement.is_reliable() => format!("Stored {} (B = {:.2e}, unreliable)", note_name, b),
+                            Some(b) => format!("Stored {} (B = {:.2e})", note_name, b),
+                            None => format!("Stored {}", note_name),
+                        });
+                        // Store the measurement in the profile, remembering what it replaced for undo
+                        let key_index = measurement.key_index;
+                        let previous = self.inharmonicity_profile.insert_measurement(measurement);
+                        self.measurement_history.push((key_index, previous));
+                        self.profile_changed();
+                        // Start the "Done" indicator for visual feedback
+                        self.display_data.capture_done_at = Some(Instant::now());
+                    }
+                    Err(e) => {
+                        // Nothing is stored; the status tells the user to strike again
+                        eprintln!("[MAIN] Capture rejected: {}", e);
+                        self.display_data.capture_status = Some(format!("Not stored: {}", e));
                     }
-                    // Initialize the "Done" timer for visual feedback
-                    ui::main_display::initialize_done_timer();
                 }
             }
         }
         // --- End Capture Logic ---
 
         // --- Smoothing Buffer Logic ---
-        let cents_for_smoothing = match self.display_data.tuning_mode {
-            TuningMode::Auto => result.cents_deviation,
-            TuningMode::Manual { target_freq, .. } => result
-                .detected_frequency
-                .map(|freq| tuning::calculate_cents_deviation(freq, target_freq)),
+        // The smoothed and displayed deviations both come from this one reading
+        let options = self.target_options();
+        let measured = match self.display_data.tuning_mode {
+            // Off the keyboard, the nearest end key would give a meaningless deviation
+            TuningMode::Auto if result.out_of_range => None,
+            TuningMode::Auto => result.detected_frequency,
+            TuningMode::Manual { .. } => result.partial_frequency(options.partial),
         };
+        let reading = measured.map(|freq| {
+            tuning::analyze_pitch(
+                freq,
+                &self.display_data.tuning_mode,
+                &self.inharmonicity_profile,
+                &options,
+                tuning::REFERENCE_A4_HZ,
+            )
+        });
+        if let (TuningMode::Auto, Some(reading), true) = (&self.display_data.tuning_mode, &reading, self.display_data.note_lock_enabled) {
+            self.display_data.locked_note = self.display_data.keyboard.position_of_name(&reading.note_name);
+        }
+        let cents_for_smoothing = reading.as_ref().map(|r| r.cents);
+        self.display_data.last_reading = reading;
+        let history = &mut self.display_data.pitch_history;
+        if history.len() == PITCH_HISTORY_LENGTH {
+            history.pop_front();
+        }
+        history.push_back(cents_for_smoothing);
+
+        // --- Note History ---
+        // A new entry starts whenever a different note is detected; while the
+        // same note is held its entry keeps the latest deviation.
+        if let Some(note_name) = &result.note_name {
+            let notes = &mut self.display_data.note_history;
+            match notes.back_mut() {
+                Some(last) if &last.note_name == note_name => last.cents = result.cents_deviation,
+                _ => {
+                    if notes.len() == NOTE_HISTORY_LENGTH {
+                        notes.pop_front();
+                    }
+                    notes.push_back(DetectedNote {
+                        note_name: note_name.clone(),
+                        cents: result.cents_deviation,
+                        detected_at: Instant::now(),
+                    });
+                }
+            }
+        }
+
         if let Some(cents) = cents_for_smoothing {
-            self.display_data.smoothing_buffer.push(cents);
-            if self.display_data.smoothing_buffer.len() > SMOOTHING_FACTOR {
-                self.display_data.smoothing_buffer.remove(0);
+            // The sample count is turned into a time span so it means the same at any frame rate
+            let window = frame_duration(&result) * self.display_data.smoothing_samples as u32;
+            let now = frame_time(&result);
+            let buffer = &mut self.display_data.smoothing_buffer;
+            buffer.push_back((now, cents));
+            while buffer.front().is_some_and(|&(time, _)| now - time >= window) {
+                buffer.pop_front();
             }
         } else {
             self.display_data.smoothing_buffer.clear();
         }
         
+        // --- Detection Quality ---
+        self.display_data.detection_quality.push(&result);
+
+        // --- Clipping Indicator ---
+        // A clipped frame only lasts ~46 ms, so hold the indicator long enough to be seen
+        if result.clipped {
+            self.display_data.clip_indicator_until = Some(Instant::now() + CLIP_HOLD);
+        }
+        
+        // --- Display Spectrum Smoothing ---
+        if self.display_data.spectrum_smoothing_enabled {
+            let smoothed = &mut self.display_data.smoothed_spectrum;
+            if smoothed.len() == result.spectrogram_data.len() {
+                for (average, &magnitude) in smoothed.iter_mut().zip(&result.spectrogram_data) {
+                    *average = SPECTRUM_SMOOTHING * *average + (1.0 - SPECTRUM_SMOOTHING) * magnitude;
+                }
+            } else {
+                // First frame (or the FFT size changed): start from the raw spectrum
+                smoothed.clone_from(&result.spectrogram_data);
+            }
+        }
+        
+        // --- Spectrum Peak Hold ---
+        if self.display_data.peak_hold_enabled {
+            let peaks = &mut self.display_data.peak_hold;
+            if peaks.len() == result.spectrogram_data.len() {
+                for (peak, &magnitude) in peaks.iter_mut().zip(&result.spectrogram_data) {
+                    *peak = (*peak * PEAK_HOLD_DECAY).max(magnitude);
+                }
+            } else {
+                // First frame (or the FFT size changed): start from the raw spectrum
+                peaks.clone_from(&result.spectrogram_data);
+            }
+        }
+        
+        // --- Waterfall History ---
+        if self.display_data.waterfall_enabled {
+            // Each row covers one hop, so the view spans the same time at any overlap
+            let rows = (WATERFALL_DURATION.as_secs_f64() / frame_duration(&result).as_secs_f64()).ceil() as usize;
+            self.display_data.waterfall_rows = rows;
+            let history = &mut self.display_data.spectrogram_history;
+            while history.len() >= rows {
+                history.pop_front();
+            }
+            history.push_back(result.spectrogram_data.clone());
+        }
+        
         // --- Store Last Analysis ---
         self.display_data.last_analysis = Some(result); // Move the original result
     }
     // ----------------------------------------------------------------
 
+    /// Switches to `Capturing` and starts the settle time before frames are buffered.
+    fn start_capturing(&mut self) {
+        self.display_data.capture_state = CaptureState::Capturing;
+        self.capture_session.start();
+    }
+
+    /// Returns true while a reference tone started by `PlayReference` is still playing.
+    fn reference_tone_playing(&self) -> bool {
+        self.reference_tone.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Copies the active profile into the library under its name.
+    fn stash_active_profile(&mut self) {
+        self.profile_library
+            .profiles
+            .insert(self.display_data.active_profile.clone(), self.inharmonicity_profile.clone());
+    }
+
+    /// Makes another profile the active one.
+    ///
+    /// Undo history and the auto-measure memory belong to the previous
+    /// profile, so both start over.
+    fn switch_profile(&mut self, name: String, profile: InharmonicityProfile) {
+        self.inharmonicity_profile = profile;
+        self.display_data.active_profile = name;
+        self.measurement_history.clear();
+        self.capture_session.forget_last_capture();
+        self.display_data.smoothing_buffer.clear();
+        self.profile_changed();
+    }
+
+    /// Switches to manual mode on the given key and retunes the analysis for it.
+    fn select_manual_key(&mut self, key_index: u8) {
+        let (note_name, target_freq) = tuning::find_nearest_note_by_index(key_index);
+        self.display_data.tuning_mode = TuningMode::Manual {
+            key_index,
+            note_name,
+            target_freq,
+        };
+        self.display_data.smoothing_buffer.clear();
+        self.push_analysis_config();
+    }
+
+    /// Plays the target pitch of the manual key, if one is selected.
+    fn play_reference(&mut self, duration_ms: u64) {
+        if let TuningMode::Manual { target_freq, .. } = self.display_data.tuning_mode {
+            match audio::play_reference_tone(target_freq, duration_ms, tuning::REFERENCE_A4_HZ) {
+                Ok(handle) => self.reference_tone = Some(handle),
+                Err(e) => eprintln!("[MAIN] Could not play reference tone: {}", e),
+            }
+        }
+    }
+
+    /// Plays the current key of a sweep, short enough to end before the next step.
+    ///
+    /// Without an output device the sweep still steps through the targets,
+    /// so it can be followed on the display alone.
+    fn play_sweep_tone(&mut self) {
+        if let Some(sweep) = self.display_data.sweep {
+            let duration_ms = sweep.interval_ms.min(REFERENCE_TONE_MS).saturating_sub(SWEEP_TONE_GAP_MS);
+            if duration_ms > 0 {
+                self.play_reference(duration_ms);
+            }
+        }
+    }
+
+    /// Returns the time between GUI updates.
+    ///
+    /// Drops to `IDLE_FRAME_RATE` once no pitch has been detected for
+    /// `IDLE_TIMEOUT`, unless a capture is waiting for a note. The audio
+    /// thread keeps analyzing at its own rate; results just queue up between ticks.
+    fn tick_interval(&self) -> Duration {
+        let idle = self.display_data.idle_throttling
+            && self.display_data.capture_state != CaptureState::Capturing
+            && self.last_pitch_at.elapsed() >= IDLE_TIMEOUT;
+        let frame_rate = if idle { IDLE_FRAME_RATE } else { self.display_data.frame_rate };
+        Duration::from_secs(1) / frame_rate
+    }
+
+    /// Returns the target partial and stretch settings for manual mode.
+    fn target_options(&self) -> TargetOptions {
+        TargetOptions {
+            partial: self.display_data.target_partial,
+            stretch: self.display_data.stretch_enabled,
+            piano_type: self.display_data.piano_type,
+            keyboard: self.display_data.keyboard,
+            locked_note: self.display_data.locked_note.filter(|_| self.display_data.note_lock_enabled),
+        }
+    }
+
     /// Renders the main application interface.
     /// 
     /// Delegates all UI rendering to the main_display module,
@@ -542,16 +1794,27 @@ impl TunerApp {
     fn view(&self) -> Element<'_, Message> {
         create_main_view(
             &self.display_data, 
+            &self.inharmonicity_profile,
             Message::CaptureButtonClicked
         )
     }
     
     /// Creates a subscription for continuous application updates.
     /// 
-    /// Returns a timer subscription that fires every 16ms (60 FPS) to ensure
-    /// smooth real-time audio visualization and responsive UI updates.
+    /// Returns a timer subscription that fires at the selected frame rate
+    /// (60 FPS by default, less while idle) to ensure smooth real-time audio
+    /// visualization and responsive UI updates, and
+    /// turns window close requests into `Message::Exit`. While a reference
+    /// sweep runs, a second timer advances it.
     fn subscription(&self) -> Subscription<Message> {
-        iced::time::every(std::time::Duration::from_millis(16)).map(|_| Message::Tick)
+        let sweep = self.display_data.sweep.map_or_else(Subscription::none, |sweep| {
+            iced::time::every(Duration::from_millis(sweep.interval_ms)).map(|_| Message::SweepStep)
+        });
+        Subscription::batch([
+            iced::time::every(self.tick_interval()).map(|_| Message::Tick),
+            iced::window::close_requests().map(|_| Message::Exit),
+            sweep,
+        ])
     }
 
     /// Returns the application theme.
@@ -564,140 +1827,22 @@ impl TunerApp {
 }
 
 
-
-/// Performs a full analysis on a single frame of audio data.
-/// 
-/// This function processes raw audio data through the complete analysis pipeline:
-/// 1. Performs FFT to get frequency spectrum
-/// 2. Detects fundamental frequency using PYIN algorithm
-/// 3. Refines frequency detection using spectrum analysis
-/// 4. Finds nearest musical note and calculates cents deviation
-/// 5. Identifies harmonic partials for inharmonicity analysis
-/// 
-/// # Arguments
-/// * `audio_frame` - Raw audio samples (typically 2048 samples)
-/// * `sample_rate` - Sample rate in Hz (typically 44100 or 48000)
-/// 
-/// # Returns
-/// * `AnalysisResult` - Complete analysis including frequency, confidence, 
-///   cents deviation, note name, spectrogram data, and detected partials
-fn perform_analysis(
-    audio_frame: &[f32],
-    sample_rate: u32
-) -> AnalysisResult {
-    let complex_spectrum = fft::perform_fft(audio_frame);
-    let spectrogram_data = fft::spectrum_to_magnitudes(&complex_spectrum);
-    
-    // --- Unpack the frequency and confidence ---
-    let (detected_frequency, confidence) = 
-        if let Some((freq, conf)) = pitch::detect_pitch_pyin(audio_frame, sample_rate, AMPLITUDE_THRESHOLD) {
-            let refined_freq = pitch::refine_from_spectrum(&spectrogram_data, freq, sample_rate);
-            (refined_freq, Some(conf))
-        } else {
-            (None, None)
-        };
-
-    let (cents_deviation, note_name) = if let Some(freq) = detected_frequency {
-        let (name, target_freq) = tuning::find_nearest_note(freq);
-        let deviation = tuning::calculate_cents_deviation(freq, target_freq);
-        (Some(deviation), Some(name))
-    } else {
-        (None, None)
-    };
-    
-    let partials = if let Some(fundamental) = detected_frequency {
-        // Search for up to 7 partials
-        pitch::find_partials(&spectrogram_data, fundamental, sample_rate, 7)
-    } else {
-        vec![] // No fundamental, no partials
-    };
-
-    AnalysisResult {
-        detected_frequency,
-        confidence,
-        cents_deviation,
-        note_name,
-        spectrogram_data,
-        partials,
-    }
+/// Returns when a frame was analyzed, falling back to now for results without a timestamp.
+fn frame_time(result: &AnalysisResult) -> Instant {
+    result.timestamp.unwrap_or_else(Instant::now)
 }
 
-/// Checks if all AnalysisResult frames in the buffer are "stable."
-///
-/// Stability is defined as:
-/// 1. The buffer is not empty.
-/// 2. All frames have a `note_name` that is `Some` and is the *same* note.
-/// 3. All frames have a `confidence` that is `Some` and is above the `STABILITY_CONFIDENCE_THRESHOLD`.
-fn check_stability(buffer: &VecDeque<AnalysisResult>) -> bool {
-    if buffer.is_empty() {
-        return false;
-    }
-
-    // Get the note name from the first frame. If it's None, it's not stable.
-    let first_note = match &buffer[0].note_name {
-        Some(n) => n,
-        None => return false,
+/// Returns the time between the start of a result's frame and the next one,
+/// at the rate the device actually delivered (the default rate and
+/// back-to-back frames if they weren't recorded).
+fn frame_duration(result: &AnalysisResult) -> Duration {
+    let sample_rate = match result.sample_rate {
+        0 => audio::DEFAULT_SAMPLE_RATE,
+        rate => rate,
     };
-
-    // Use `iter().all()` to efficiently check every frame against the criteria.
-    buffer.iter().all(|frame| {
-        // 1. Check confidence
-        let high_confidence = frame
-            .confidence
-            .map_or(false, |c| c > STABILITY_CONFIDENCE_THRESHOLD);
-
-        // 2. Check for matching note name
-        let matching_note = frame.note_name.as_ref().map_or(false, |n| n == first_note);
-
-        high_confidence && matching_note
-    })
-}
-
-// --- New Profile Save/Load Functions ---
-
-use std::fs::File;
-use std::io::{Read, Write};
-use serde_json;
-
-/// Saves the inharmonicity profile to a JSON file.
-/// 
-/// Serializes the complete inharmonicity profile (including all measured
-/// partials and calculated B values) to a JSON file for persistent storage.
-/// This allows users to save their piano's unique inharmonicity characteristics
-/// and reload them in future tuning sessions.
-/// 
-/// # Arguments
-/// * `profile` - The inharmonicity profile to save
-/// * `path` - File path where the profile should be saved (e.g., "tuning_profile.json")
-/// 
-/// # Returns
-/// * `Ok(())` - Profile saved successfully
-/// * `Err(io::Error)` - File I/O error or JSON serialization error
-fn save_profile(profile: &InharmonicityProfile, path: &str) -> std::io::Result<()> {
-    let json_string = serde_json::to_string_pretty(profile)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    let mut file = File::create(path)?;
-    file.write_all(json_string.as_bytes())?;
-    Ok(())
+    let hop_size = match result.hop_size {
+        0 => audio::BUFFER_SIZE,
+        hop => hop,
+    };
+    Duration::from_secs_f64(hop_size as f64 / sample_rate as f64)
 }
-
-/// Loads an inharmonicity profile from a JSON file.
-/// 
-/// Deserializes a previously saved inharmonicity profile from a JSON file.
-/// This allows users to restore their piano's unique inharmonicity characteristics
-/// from a previous tuning session, maintaining consistency across tuning sessions.
-/// 
-/// # Arguments
-/// * `path` - File path to load the profile from (e.g., "tuning_profile.json")
-/// 
-/// # Returns
-/// * `Ok(InharmonicityProfile)` - Successfully loaded profile
-/// * `Err(io::Error)` - File I/O error or JSON deserialization error
-fn load_profile(path: &str) -> std::io::Result<InharmonicityProfile> {
-    let mut file = File::open(path)?;
-    let mut data = String::new();
-    file.read_to_string(&mut data)?;
-    let profile: InharmonicityProfile = serde_json::from_str(&data)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    Ok(profile)
-}
\ No newline at end of file
@@ -0,0 +1,138 @@
+//! # UI Settings
+//!
+//! Panel visibility and display preferences that should survive a restart.
+//! They are kept in their own small file, apart from the tuning profiles, so
+//! sharing or resetting a profile never changes how the window looks.
+//!
+//! ## Features
+//! - Visibility of every tool panel
+//! - Spectrogram, partials and cents readout display options
+//! - How long a capture's "Done" confirmation stays up
+//! - Color palette, stored by preset name
+//! - Missing or unknown fields fall back to the defaults, so older files still load
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+use crate::AppDisplayData;
+use crate::ui::palette::Palette;
+use crate::ui::partials_display::PartialDisplayMode;
+use crate::ui::spectrogram::SpectrumRange;
+
+/// Display preferences saved whenever they change and restored on startup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings {
+    pub spectrogram_visible: bool,
+    pub cent_meter_visible: bool,
+    pub key_select_visible: bool,
+    pub partials_visible: bool,
+    pub pitch_history_visible: bool,
+    pub note_history_visible: bool,
+    pub cents_readout_visible: bool,
+    pub partial_display_mode: PartialDisplayMode,
+    pub displayed_partials: usize,
+    pub waterfall_enabled: bool,
+    pub spectrum_smoothing_enabled: bool,
+    pub peak_hold_enabled: bool,
+    pub spectrum_range: SpectrumRange,
+    pub done_display_ms: u64,
+    pub palette: String,  // Name of one of Palette::PRESETS
+}
+
+impl Default for UiSettings {
+    /// The layout the app has always started with.
+    fn default() -> Self {
+        Self {
+            spectrogram_visible: true,
+            cent_meter_visible: true,
+            key_select_visible: true,
+            partials_visible: true,
+            pitch_history_visible: false,
+            note_history_visible: false,
+            cents_readout_visible: true,
+            partial_display_mode: PartialDisplayMode::default(),
+            displayed_partials: crate::DEFAULT_DISPLAYED_PARTIALS,
+            waterfall_enabled: false,
+            spectrum_smoothing_enabled: false,
+            peak_hold_enabled: false,
+            spectrum_range: SpectrumRange::default(),
+            done_display_ms: crate::DEFAULT_DONE_DISPLAY_TIME.as_millis() as u64,
+            palette: Palette::PRESETS[0].0.to_string(),
+        }
+    }
+}
+
+impl UiSettings {
+    /// Reads the preferences currently shown by the app.
+    pub fn from_display(data: &AppDisplayData) -> Self {
+        let palette = Palette::PRESETS
+            .iter()
+            .find(|&&(_, p)| p == data.palette)
+            .map_or(Palette::PRESETS[0].0, |&(name, _)| name);
+        Self {
+            spectrogram_visible: data.spectrogram_visible,
+            cent_meter_visible: data.cent_meter_visible,
+            key_select_visible: data.key_select_visible,
+            partials_visible: data.partials_visible,
+            pitch_history_visible: data.pitch_history_visible,
+            note_history_visible: data.note_history_visible,
+            cents_readout_visible: data.cents_readout_visible,
+            partial_display_mode: data.partial_display_mode,
+            displayed_partials: data.displayed_partials,
+            waterfall_enabled: data.waterfall_enabled,
+            spectrum_smoothing_enabled: data.spectrum_smoothing_enabled,
+            peak_hold_enabled: data.peak_hold_enabled,
+            spectrum_range: data.spectrum_range,
+            done_display_ms: data.done_display_time.as_millis() as u64,
+            palette: palette.to_string(),
+        }
+    }
+
+    /// Shows these preferences in the app. An unknown palette name keeps the current palette.
+    pub fn apply(&self, data: &mut AppDisplayData) {
+        data.spectrogram_visible = self.spectrogram_visible;
+        data.cent_meter_visible = self.cent_meter_visible;
+        data.key_select_visible = self.key_select_visible;
+        data.partials_visible = self.partials_visible;
+        data.pitch_history_visible = self.pitch_history_visible;
+        data.note_history_visible = self.note_history_visible;
+        data.cents_readout_visible = self.cents_readout_visible;
+        data.partial_display_mode = self.partial_display_mode;
+        data.displayed_partials = self.displayed_partials;
+        data.waterfall_enabled = self.waterfall_enabled;
+        data.spectrum_smoothing_enabled = self.spectrum_smoothing_enabled;
+        data.peak_hold_enabled = self.peak_hold_enabled;
+        data.spectrum_range = self.spectrum_range;
+        data.done_display_time = Duration::from_millis(self.done_display_ms);
+        if let Some(&(_, palette)) = Palette::PRESETS.iter().find(|(name, _)| *name == self.palette) {
+            data.palette = palette;
+        }
+    }
+
+    /// Saves the preferences to a JSON file.
+    ///
+    /// # Arguments
+    /// * `path` - File path to save to (e.g., "ui_settings.json")
+    ///
+    /// # Returns
+    /// * `Ok(())` - Successfully saved
+    /// * `Err(io::Error)` - File I/O error or JSON serialization error
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let json_string = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(path, json_string)
+    }
+
+    /// Loads preferences from a JSON file.
+    ///
+    /// # Arguments
+    /// * `path` - File path to load from (e.g., "ui_settings.json")
+    ///
+    /// # Returns
+    /// * `Ok(UiSettings)` - Successfully loaded preferences
+    /// * `Err(io::Error)` - File I/O error or JSON deserialization error
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str(&data).map_err(std::io::Error::other)
+    }
+}
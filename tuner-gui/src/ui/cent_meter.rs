@@ -7,17 +7,167 @@
 //! ## Features
 //! - Real-time cent deviation display
 //! - Color-coded accuracy zones (green/yellow/red)
-//! - Smooth needle animation
+//! - Per-key tolerances (tighter in the midrange, looser at the extremes)
+//! - Zone colors shared with the numeric cents readout
+//! - Spring-damped needle animation with adjustable stiffness and damping
 //! - Professional tuning meter appearance
 
 use iced::widget::canvas::{self, Geometry, Path, Stroke};
 use iced::widget::container;
 use iced::{mouse, Color, Element, Point, Rectangle, Renderer, Size, Theme};
+use std::cell::Cell;
+use std::time::Instant;
+use super::palette::Palette;
 
 /// Maximum cent deviation range for the meter display.
 /// The meter shows deviations from -50 to +50 cents.
 const METER_RANGE: f32 = 50.0;
 
+/// Longest time step the needle is advanced by in one go, in seconds.
+/// Longer gaps between frames are split so the spring stays stable.
+const MAX_NEEDLE_STEP: f32 = 0.005;
+
+/// Longest gap between frames that is simulated; after a longer pause the
+/// needle simply continues from where it was.
+const MAX_NEEDLE_GAP: f32 = 0.1;
+
+/// Zone limits used when no key is known.
+const DEFAULT_TOLERANCE: Tolerance = Tolerance { green: 5.0, yellow: 20.0 };
+
+/// Color zone limits in cents for a single key.
+///
+/// Deviations below `green` are shown green, below `yellow` yellow,
+/// and anything larger red.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    pub green: f32,
+    pub yellow: f32,
+}
+
+impl Tolerance {
+    /// Returns the zone color for a deviation in cents.
+    pub fn color(&self, cents: f32, palette: &Palette) -> Color {
+        if cents.abs() < self.green {
+            palette.in_tune
+        } else if cents.abs() < self.yellow {
+            palette.near
+        } else {
+            palette.out_of_tune
+        }
+    }
+}
+
+/// Describes how the acceptable tuning error varies across the keyboard.
+///
+/// Keys inside the midrange region use the `midrange` tolerance; outside it
+/// the tolerance widens linearly to reach `extremes` at A0 and C8, where
+/// small errors are far less audible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToleranceCurve {
+    pub midrange: Tolerance,
+    pub extremes: Tolerance,
+    pub midrange_low: u8,   // First key of the midrange region (0-87)
+    pub midrange_high: u8,  // Last key of the midrange region (0-87)
+}
+
+impl Default for ToleranceCurve {
+    fn default() -> Self {
+        Self {
+            midrange: Tolerance { green: 2.0, yellow: 8.0 },
+            extremes: Tolerance { green: 6.0, yellow: 20.0 },
+            midrange_low: 27,  // C3
+            midrange_high: 63, // C6
+        }
+    }
+}
+
+impl ToleranceCurve {
+    /// Returns the zone limits for a key, or the default ones if no key is known.
+    pub fn for_optional_key(&self, key_index: Option<u8>) -> Tolerance {
+        key_index.map(|key| self.for_key(key)).unwrap_or(DEFAULT_TOLERANCE)
+    }
+
+    /// Returns the zone limits for a key index (0-87).
+    pub fn for_key(&self, key_index: u8) -> Tolerance {
+        let key = key_index as f32;
+        let low = self.midrange_low as f32;
+        let high = self.midrange_high as f32;
+
+        // 0.0 inside the midrange, rising to 1.0 at the outermost keys
+        let t = if key < low && low > 0.0 {
+            (low - key) / low
+        } else if key > high && high < 87.0 {
+            (key - high) / (87.0 - high)
+        } else {
+            0.0
+        };
+
+        Tolerance {
+            green: self.midrange.green + (self.extremes.green - self.midrange.green) * t,
+            yellow: self.midrange.yellow + (self.extremes.yellow - self.midrange.yellow) * t,
+        }
+    }
+}
+
+/// Spring model that pulls the needle towards the current reading.
+///
+/// The needle behaves like a mass on a spring: `stiffness` sets how quickly
+/// it responds and `damping` how much it overshoots (1.0 = critically
+/// damped, no overshoot; lower values swing past the reading first).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NeedleDynamics {
+    pub stiffness: f32, // Spring constant in 1/s^2
+    pub damping: f32,   // Damping ratio
+}
+
+/// Needle position and velocity, kept by the canvas between frames.
+#[derive(Default)]
+pub struct NeedleState {
+    position: Cell<Option<f32>>, // Displayed cents (None = needle hidden)
+    velocity: Cell<f32>,         // Cents per second
+    last_frame: Cell<Option<Instant>>,
+}
+
+impl NeedleState {
+    /// Moves the needle towards `target` by the time elapsed since the last frame.
+    ///
+    /// # Returns
+    /// * The cents value to draw the needle at
+    fn advance(&self, target: f32, dynamics: Option<NeedleDynamics>) -> f32 {
+        let now = Instant::now();
+        let elapsed = self.last_frame.replace(Some(now)).map(|last| now.duration_since(last).as_secs_f32());
+
+        let (Some(dynamics), Some(mut position), Some(elapsed)) = (dynamics, self.position.get(), elapsed) else {
+            // No animation, or the needle has just appeared: jump to the reading
+            self.position.set(Some(target));
+            self.velocity.set(0.0);
+            return target;
+        };
+
+        let mut velocity = self.velocity.get();
+        let mut remaining = elapsed.min(MAX_NEEDLE_GAP);
+        let damping = 2.0 * dynamics.damping * dynamics.stiffness.sqrt();
+        while remaining > 0.0 {
+            let dt = remaining.min(MAX_NEEDLE_STEP);
+            let acceleration = dynamics.stiffness * (target - position) - damping * velocity;
+            velocity += acceleration * dt;
+            position += velocity * dt;
+            remaining -= dt;
+        }
+
+        self.position.set(Some(position));
+        self.velocity.set(velocity);
+        position
+    }
+
+    /// Hides the needle until the next reading.
+    fn reset(&self) {
+        self.position.set(None);
+        self.velocity.set(0.0);
+        self.last_frame.set(None);
+    }
+}
+
 /// Cent meter widget for displaying tuning accuracy.
 /// 
 /// This widget provides a visual representation of how far the current
@@ -26,6 +176,12 @@ const METER_RANGE: f32 = 50.0;
 pub struct CentMeter {
     /// Current cent deviation (None if no pitch detected)
     cents: Option<f32>,
+    /// Color zone limits for the key being tuned
+    tolerance: Tolerance,
+    /// Needle spring model (None = needle jumps to each reading)
+    dynamics: Option<NeedleDynamics>,
+    /// Needle colors for each zone
+    palette: Palette,
 }
 
 impl CentMeter {
@@ -33,8 +189,19 @@ impl CentMeter {
     /// 
     /// # Arguments
     /// * `cents` - Current cent deviation (None if no pitch detected)
-    pub fn new(cents: Option<f32>) -> Self {
-        Self { cents }
+    /// * `key_index` - Key being tuned (0-87), used to pick the tolerance zones
+    /// * `curve` - Tolerance curve mapping keys to zone limits
+    /// * `dynamics` - Needle spring model (None = no animation)
+    /// * `palette` - Needle colors for each zone
+    pub fn new(
+        cents: Option<f32>,
+        key_index: Option<u8>,
+        curve: &ToleranceCurve,
+        dynamics: Option<NeedleDynamics>,
+        palette: Palette,
+    ) -> Self {
+        let tolerance = curve.for_optional_key(key_index);
+        Self { cents, tolerance, dynamics, palette }
     }
 
     /// Creates the view element for the cent meter.
@@ -52,11 +219,11 @@ impl CentMeter {
 }
 
 impl<Message> canvas::Program<Message> for CentMeter {
-    type State = ();
+    type State = NeedleState;
 
     fn draw(
         &self,
-        _state: &Self::State,
+        state: &Self::State,
         renderer: &Renderer,
         _theme: &Theme,
         bounds: Rectangle,
@@ -83,20 +250,17 @@ impl<Message> canvas::Program<Message> for CentMeter {
 
         // Draw needle
         if let Some(c) = self.cents {
-            let clamped_cents = c.clamp(-METER_RANGE, METER_RANGE);
+            let needle_cents = state.advance(c.clamp(-METER_RANGE, METER_RANGE), self.dynamics);
+            let clamped_cents = needle_cents.clamp(-METER_RANGE, METER_RANGE);
             let needle_pos = (clamped_cents + METER_RANGE) / (2.0 * METER_RANGE) * bounds.width;
 
-            let color = if c.abs() < 5.0 {
-                Color::from_rgb8(0x34, 0xDB, 0x98) // Green
-            } else if c.abs() < 20.0 {
-                Color::from_rgb8(0xFF, 0xC3, 0x00) // Yellow
-            } else {
-                Color::from_rgb8(0xFF, 0x33, 0x33) // Red
-            };
+            let color = self.tolerance.color(c, &self.palette);
 
             let needle =
                 Path::rectangle(Point::new(needle_pos - 2.0, 0.0), Size::new(4.0, bounds.height));
             frame.fill(&needle, color);
+        } else {
+            state.reset();
         }
 
         vec![frame.into_geometry()]
@@ -4,24 +4,20 @@
 //! for the Inharmonicity piano tuning application.
 
 use iced::{Element, Length, Alignment};
-use iced::widget::{column, Space, container, row, text, button, horizontal_space};
-use std::time::{Duration, Instant};
+use iced::widget::{column, Space, container, row, text, text_input, button, horizontal_space, progress_bar, scrollable, slider};
+use std::time::Instant;
 
-/// Local timer state for managing "Done" button display
-use std::sync::Mutex;
-use std::sync::OnceLock;
+use super::{spectrogram, cent_meter, piano_keyboard, partials_display, pitch_history};
+use super::partials_display::PartialDisplayMode;
+use super::spectrogram::SpectrumRange;
+use super::palette::Palette;
+use tuner_core::inharmonicity::InharmonicityProfile;
+use tuner_core::temperament;
+use tuner_core::tracking::EnergyTrend;
+use tuner_core::tuning::{self, OctaveConvention};
 
-use super::{spectrogram, cent_meter, piano_keyboard, partials_display};
-
-static CAPTURE_DONE_TIMER: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
-
-/// Initializes the "Done" timer when capture completes.
-/// This should be called when the capture state changes to Done.
-pub fn initialize_done_timer() {
-    let timer_guard = CAPTURE_DONE_TIMER.get_or_init(|| Mutex::new(None));
-    let mut timer = timer_guard.lock().unwrap();
-    *timer = Some(Instant::now());
-}
+/// Highest partial offered by the target partial selector.
+const MAX_TARGET_PARTIAL: u32 = 8;
 
 /// Configuration for a single button in the settings sidebar
 #[derive(Debug, Clone)]
@@ -38,6 +34,10 @@ enum ButtonType {
     Standard,
     /// Measurement mode button that changes color when active
     MeasurementMode,
+    /// On/off setting that is highlighted while the given state reads true
+    Toggle(fn(&crate::AppDisplayData) -> bool),
+    /// Multi-choice setting; the current value is shown after the label
+    Setting(fn(&crate::AppDisplayData) -> String),
     /// Disabled button (no interaction)
     Disabled,
 }
@@ -49,23 +49,104 @@ const SETTINGS_CONFIG: &[(&str, &[ButtonConfig])] = &[
         ButtonConfig { label: "Centmeter", message: Some(crate::Message::ToggleCentMeter), button_type: ButtonType::Standard },
         ButtonConfig { label: "Key select", message: Some(crate::Message::ToggleKeySelect), button_type: ButtonType::Standard },
         ButtonConfig { label: "Partials", message: Some(crate::Message::TogglePartials), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Pitch history", message: Some(crate::Message::TogglePitchHistory), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Note history", message: Some(crate::Message::ToggleNoteHistory), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Confidence", message: Some(crate::Message::CycleConfidenceDisplay), button_type: ButtonType::Setting(|data| data.confidence_display.label().to_string()) },
+        ButtonConfig { label: "Cents readout", message: Some(crate::Message::ToggleCentsReadout), button_type: ButtonType::Toggle(|data| data.cents_readout_visible) },
+        ButtonConfig { label: "Needle damping", message: Some(crate::Message::CycleNeedleDamping), button_type: ButtonType::Setting(|data| {
+            crate::NEEDLE_DAMPING_CHOICES
+                .iter()
+                .find(|(_, dynamics)| *dynamics == data.needle_dynamics)
+                .map_or("custom", |(label, _)| label)
+                .to_string()
+        }) },
+        ButtonConfig { label: "Colors", message: Some(crate::Message::CyclePalette), button_type: ButtonType::Setting(|data| {
+            Palette::PRESETS
+                .iter()
+                .find(|(_, palette)| *palette == data.palette)
+                .map_or("custom", |(label, _)| label)
+                .to_string()
+        }) },
+        ButtonConfig { label: "Smoothing", message: Some(crate::Message::CycleSmoothing), button_type: ButtonType::Setting(|data| match data.smoothing_samples {
+            1 => "off".to_string(),
+            n => format!("{} samples", n),
+        }) },
+        ButtonConfig { label: "Measurement partials", message: Some(crate::Message::CycleMeasurementPartials), button_type: ButtonType::Setting(|data| format!("{} overtones", data.measurement_partials)) },
+        ButtonConfig { label: "Partials shown", message: Some(crate::Message::CycleDisplayedPartials), button_type: ButtonType::Setting(|data| format!("{} overtones", data.displayed_partials)) },
+        ButtonConfig { label: "Done confirmation", message: Some(crate::Message::CycleDoneDisplayTime), button_type: ButtonType::Setting(|data| match data.done_display_time.as_secs() {
+            0 => "off".to_string(),
+            secs => format!("{} s", secs),
+        }) },
         ButtonConfig { label: "Measurement Mode", message: Some(crate::Message::ToggleMeasurementMode), button_type: ButtonType::MeasurementMode },
+        ButtonConfig { label: "Auto-measure", message: Some(crate::Message::ToggleAutoMeasure), button_type: ButtonType::Toggle(|data| data.auto_measure) },
+        ButtonConfig { label: "Undo last measurement", message: Some(crate::Message::UndoLastMeasurement), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Beat trainer (from this key)", message: Some(crate::Message::ToggleBeatTrainer), button_type: ButtonType::Toggle(|data| data.beat_reference_key.is_some()) },
+        ButtonConfig { label: "Reference sweep (A0 to C8)", message: Some(crate::Message::StartSweep { from_key: 0, to_key: 87, interval_ms: crate::SWEEP_INTERVAL_MS }), button_type: ButtonType::Toggle(|data| data.sweep.is_some()) },
+        ButtonConfig { label: "YIN detector (vs pYIN)", message: Some(crate::Message::CyclePitchAlgorithm), button_type: ButtonType::Toggle(|data| data.pitch_algorithm == tuner_core::pitch::PitchAlgorithm::Yin) },
+        ButtonConfig { label: "SNR gate", message: Some(crate::Message::CycleSnrGate), button_type: ButtonType::Setting(|data| match data.min_snr_db {
+            db if db <= 0.0 => "off".to_string(),
+            db => format!("{:.0} dB", db),
+        }) },
+        ButtonConfig { label: "Noise-floor partials", message: Some(crate::Message::TogglePartialThreshold), button_type: ButtonType::Toggle(|data| data.noise_floor_partials) },
+        ButtonConfig { label: "Record envelope", message: Some(crate::Message::ToggleEnvelopeCapture), button_type: ButtonType::Toggle(|data| data.processing_operation == crate::ProcessingOperation::Envelope) },
+        ButtonConfig { label: "Measure partials at their peak", message: Some(crate::Message::TogglePartialPeakCapture), button_type: ButtonType::Toggle(|data| data.processing_operation == crate::ProcessingOperation::PartialPeak) },
+        ButtonConfig { label: "Average partials (confidence-weighted)", message: Some(crate::Message::ToggleWeightedAverageCapture), button_type: ButtonType::Toggle(|data| data.processing_operation == crate::ProcessingOperation::WeightedAverage) },
+        ButtonConfig { label: "Band-pass (manual key)", message: Some(crate::Message::ToggleBandpass), button_type: ButtonType::Toggle(|data| data.bandpass_enabled) },
+        ButtonConfig { label: "Polyphonic (up to 3 notes)", message: Some(crate::Message::TogglePolyphonic), button_type: ButtonType::Toggle(|data| data.polyphonic_enabled) },
+        ButtonConfig { label: "Band-limited analysis (manual key)", message: Some(crate::Message::ToggleBandLimit), button_type: ButtonType::Toggle(|data| data.band_limit_enabled) },
+        ButtonConfig { label: "Bass from 2nd partial (manual A0-C1)", message: Some(crate::Message::ToggleSecondPartialReference), button_type: ButtonType::Toggle(|data| data.second_partial_enabled) },
+        ButtonConfig { label: "Ignore other notes (manual key)", message: Some(crate::Message::ToggleKeyFilter), button_type: ButtonType::Toggle(|data| data.key_filter_enabled) },
+        ButtonConfig { label: "Stretched partial search (manual key)", message: Some(crate::Message::ToggleStretchedSearch), button_type: ButtonType::Toggle(|data| data.stretched_search_enabled) },
     ]),
     ("Systemic change", &[
         ButtonConfig { label: "Temperament", message: None, button_type: ButtonType::Disabled },
+        ButtonConfig { label: "Yamaha octaves (C3 = middle C)", message: Some(crate::Message::ToggleOctaveConvention), button_type: ButtonType::Toggle(|data| data.octave_convention == OctaveConvention::Yamaha) },
+        ButtonConfig { label: "Lock to current note", message: Some(crate::Message::ToggleNoteLock), button_type: ButtonType::Toggle(|data| data.note_lock_enabled) },
+        ButtonConfig { label: "Keyboard", message: Some(crate::Message::CycleKeyboard), button_type: ButtonType::Setting(|data| data.keyboard.label()) },
         ButtonConfig { label: "Tuning Standard", message: None, button_type: ButtonType::Disabled },
-        ButtonConfig { label: "Inharmonic curve adjustment", message: None, button_type: ButtonType::Disabled },
+        ButtonConfig { label: "Stretch tuning", message: Some(crate::Message::InharmonicCurve), button_type: ButtonType::Setting(|data| match (data.stretch_enabled, data.piano_type) {
+            (false, _) => "off".to_string(),
+            (true, None) => "measured keys".to_string(),
+            (true, Some(piano_type)) => format!("{} preset", piano_type.label().to_lowercase()),
+        }) },
     ]),
     ("Program", &[
-        ButtonConfig { label: "Sample Buffer adjustment", message: None, button_type: ButtonType::Disabled },
+        ButtonConfig { label: "Input", message: Some(crate::Message::CycleInputDevice), button_type: ButtonType::Setting(|data| {
+            data.audio_config.input_device.clone().unwrap_or_else(|| "system default".to_string())
+        }) },
+        ButtonConfig { label: "Sample Buffer", message: Some(crate::Message::SampleBuffer), button_type: ButtonType::Setting(|data| match data.audio_config.stream_buffer {
+            Some(frames) => format!("{} frames", frames),
+            None => "device default".to_string(),
+        }) },
+        ButtonConfig { label: "Frame overlap", message: Some(crate::Message::CycleFrameOverlap), button_type: ButtonType::Setting(|data| {
+            let (frame_size, hop_size) = data.audio_config.frame_and_hop();
+            match 100 - 100 * hop_size / frame_size {
+                0 => "none".to_string(),
+                percent => format!("{}%", percent),
+            }
+        }) },
+        ButtonConfig { label: "Frame size", message: Some(crate::Message::CycleFrameSize), button_type: ButtonType::Setting(|data| {
+            format!("{} samples", data.audio_config.frame_size)
+        }) },
+        ButtonConfig { label: "Frame rate", message: Some(crate::Message::CycleFrameRate), button_type: ButtonType::Setting(|data| format!("{} FPS", data.frame_rate)) },
+        ButtonConfig { label: "Slow down when idle", message: Some(crate::Message::ToggleIdleThrottling), button_type: ButtonType::Toggle(|data| data.idle_throttling) },
+        ButtonConfig { label: "Stereo coherence (2 mics)", message: Some(crate::Message::ToggleStereoCoherence), button_type: ButtonType::Toggle(|data| data.audio_config.stereo_coherence) },
+        ButtonConfig { label: "Profile", message: Some(crate::Message::SelectNextProfile), button_type: ButtonType::Setting(|data| data.active_profile.clone()) },
+        ButtonConfig { label: "Delete profile", message: Some(crate::Message::DeleteProfile), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Save library", message: Some(crate::Message::SaveLibrary), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Load library", message: Some(crate::Message::LoadLibrary), button_type: ButtonType::Standard },
         ButtonConfig { label: "Save Profile", message: Some(crate::Message::SaveProfile), button_type: ButtonType::Standard },
         ButtonConfig { label: "Load Profile", message: Some(crate::Message::LoadProfile), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Export tuning table", message: Some(crate::Message::ExportTuningTable), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Compare with saved profile", message: Some(crate::Message::CompareWithSavedProfile), button_type: ButtonType::Standard },
+        ButtonConfig { label: "Recalculate B values", message: Some(crate::Message::RecalculateProfile), button_type: ButtonType::Standard },
     ]),
 ];
 
 /// Creates the complete main application view
 pub fn create_main_view(
     data: &crate::AppDisplayData,
+    profile: &InharmonicityProfile,
     capture_message: crate::Message,
 ) -> Element<'static, crate::Message>
 {
@@ -90,10 +171,13 @@ pub fn create_main_view(
     let spectrogram_panel = create_spectrogram_panel(data);
     let cent_meter_panel = create_cent_meter_panel(data);
     let keyboard_panel = create_keyboard_panel(data);
-    let partials_panel = create_partials_panel(data);
+    let partials_panel = create_partials_panel(data, profile);
+    let pitch_history_panel = create_pitch_history_panel(data);
+    let note_history_panel = create_note_history_panel(data);
+    let beat_trainer_panel = create_beat_trainer_panel(data, profile);
     
     // Create sidebar
-    let sidebar = create_sidebar(data.capture_state.clone(), capture_message);
+    let sidebar = create_sidebar(data, capture_message);
 
     // Build top row dynamically based on visibility
     let top_row = match (spectrogram_panel, cent_meter_panel) {
@@ -114,16 +198,28 @@ pub fn create_main_view(
     .align_y(Alignment::Start);
     
     // Assemble the final layout
-    let main_content = row![
-        column![
-            title,
-            Space::with_height(20),
-            top_row,
-            Space::with_height(10),
-            bottom_row,
-        ]
+    let mut panels = column![title]
         .width(Length::Fill)
-        .spacing(10),
+        .spacing(10);
+    if let Some(error) = &data.audio_error {
+        panels = panels.push(create_audio_error_banner(error));
+    } else if let Some(info) = &data.input_info {
+        // Shows which device and channels are heard, e.g. input 2 of an interface
+        panels = panels.push(text(format!("Listening to {}", info)).size(14));
+    }
+    panels = panels.push(Space::with_height(20)).push(top_row).push(Space::with_height(10));
+    if let Some(history) = pitch_history_panel {
+        panels = panels.push(history).push(Space::with_height(10));
+    }
+    if let Some(history) = note_history_panel {
+        panels = panels.push(history).push(Space::with_height(10));
+    }
+    if let Some(trainer) = beat_trainer_panel {
+        panels = panels.push(trainer).push(Space::with_height(10));
+    }
+    
+    let main_content = row![
+        panels.push(bottom_row),
         Space::with_width(10),
         sidebar,
     ]
@@ -136,6 +232,35 @@ pub fn create_main_view(
         .into()
 }
 
+/// Creates the banner shown when the input stream could not be opened.
+///
+/// Without it the panels would simply stay empty, which looks like the
+/// application has hung. The banner offers to retry (e.g. after plugging a
+/// microphone in) or to switch to another input device.
+fn create_audio_error_banner(error: &str) -> Element<'static, crate::Message> {
+    container(
+        row![
+            column![
+                text("No audio input").size(18).color(iced::Color::from_rgb(1.0, 0.6, 0.0)),
+                text(error.to_string()).size(14),
+            ]
+            .spacing(4),
+            horizontal_space(),
+            button(text("Retry").size(14))
+                .padding([6, 12])
+                .on_press(crate::Message::RetryAudio),
+            button(text("Next input device").size(14))
+                .padding([6, 12])
+                .on_press(crate::Message::CycleInputDevice),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    )
+    .padding(10)
+    .width(Length::Fill)
+    .into()
+}
+
 /// Creates the spectrogram panel widget.
 fn create_spectrogram_panel(data: &crate::AppDisplayData) -> Option<Element<'static, crate::Message>>
 {
@@ -143,19 +268,71 @@ fn create_spectrogram_panel(data: &crate::AppDisplayData) -> Option<Element<'sta
         return None;
     }
 
-    let spectrogram_data = data.last_analysis.as_ref()
-        .map(|a| a.spectrogram_data.clone())
-        .unwrap_or_default();
+    let (sample_rate, fft_size) = data.last_analysis.as_ref().map_or((0, 0), |a| (a.sample_rate, a.fft_size));
+    let range = data.spectrum_range.frequencies(data.note_band());
+    let spectrogram_view = if data.waterfall_enabled {
+        spectrogram::Waterfall::new(
+            data.spectrogram_history.clone(),
+            data.waterfall_rows,
+            data.noise_floor_db,
+            sample_rate,
+            fft_size,
+            range,
+        )
+        .view()
+    } else {
+        let spectrogram_data = if data.spectrum_smoothing_enabled {
+            data.smoothed_spectrum.clone()
+        } else {
+            data.last_analysis.as_ref()
+                .map(|a| a.spectrogram_data.clone())
+                .unwrap_or_default()
+        };
+        let peak_hold = data.peak_hold_enabled.then(|| data.peak_hold.clone());
+        spectrogram::Spectrogram::new(spectrogram_data, peak_hold, data.noise_floor_db, data.palette, sample_rate, fft_size, range).view()
+    };
     
-    let spectrogram_content = container(
-        spectrogram::Spectrogram::new(spectrogram_data).view()
-    )
+    let spectrogram_content = container(spectrogram_view)
     .width(Length::Fill)
     .height(Length::Fill);
     
+    let noise_floor_control = row![
+        text(format!("Gate {:.0} dB", data.noise_floor_db)).size(14),
+        slider(-120.0..=-20.0, data.noise_floor_db, crate::Message::NoiseFloorChanged)
+            .step(1.0)
+            .width(Length::Fixed(120.0)),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center);
+    
     let panel = container(
         column![
-            text("Spectrogram").size(18),
+            row![
+                text("Spectrogram").size(18),
+                horizontal_space(),
+                button(text(data.spectrum_range.label()).size(12))
+                    .padding([2, 8])
+                    .style(if data.spectrum_range == SpectrumRange::Full { button::secondary } else { button::primary })
+                    .on_press(crate::Message::CycleSpectrumRange),
+                Space::with_width(4),
+                button(text("Smooth").size(12))
+                    .padding([2, 8])
+                    .style(if data.spectrum_smoothing_enabled { button::primary } else { button::secondary })
+                    .on_press(crate::Message::ToggleSpectrumSmoothing),
+                Space::with_width(4),
+                button(text("Peak").size(12))
+                    .padding([2, 8])
+                    .style(if data.peak_hold_enabled { button::primary } else { button::secondary })
+                    .on_press(crate::Message::TogglePeakHold),
+                Space::with_width(4),
+                button(text("Waterfall").size(12))
+                    .padding([2, 8])
+                    .style(if data.waterfall_enabled { button::primary } else { button::secondary })
+                    .on_press(crate::Message::ToggleWaterfall),
+                Space::with_width(10),
+                noise_floor_control,
+            ]
+            .align_y(Alignment::Center),
             Space::with_height(10),
             spectrogram_content
         ]
@@ -168,6 +345,109 @@ fn create_spectrogram_panel(data: &crate::AppDisplayData) -> Option<Element<'sta
     Some(panel.into())
 }
 
+/// Creates the pitch history panel
+fn create_pitch_history_panel(data: &crate::AppDisplayData) -> Option<Element<'static, crate::Message>>
+{
+    if !data.pitch_history_visible {
+        return None;
+    }
+    
+    let panel = container(
+        column![
+            text("Pitch History").size(18),
+            Space::with_height(5),
+            pitch_history::PitchHistory::new(
+                data.pitch_history.clone(),
+                crate::PITCH_HISTORY_LENGTH,
+            )
+            .view()
+        ]
+        .spacing(5)
+        .padding(15)
+    )
+    .width(Length::Fill)
+    .height(Length::Fixed(140.0));
+
+    Some(panel.into())
+}
+
+/// Creates the beat trainer panel.
+///
+/// Compares the interval between the trainer's reference key and the manual
+/// key with the beat rate it has in equal temperament on a harmonic piano.
+fn create_beat_trainer_panel(
+    data: &crate::AppDisplayData,
+    profile: &InharmonicityProfile,
+) -> Option<Element<'static, crate::Message>>
+{
+    let reference_key = data.beat_reference_key?;
+    let name = |key: u8| tuning::key_name(key, data.octave_convention);
+
+    let summary = match &data.tuning_mode {
+        crate::TuningMode::Manual { key_index, .. } => {
+            let interval = format!("{} - {}", name(reference_key.min(*key_index)), name(reference_key.max(*key_index)));
+            match temperament::interval_beat(profile, reference_key, *key_index) {
+                Some(beat) => {
+                    let measured = beat.measured_bps
+                        .map_or("measure both keys".to_string(), |bps| format!("measured {:+.1} bps", bps));
+                    format!(
+                        "{} {} ({}:{}): expected {:+.1} bps, {}",
+                        interval, beat.name, beat.lower_partial, beat.upper_partial, beat.expected_bps, measured
+                    )
+                }
+                None => format!("{}: select a third, fourth, fifth, sixth or octave", interval),
+            }
+        }
+        crate::TuningMode::Auto => format!("Select a second key to compare with {}", name(reference_key)),
+    };
+
+    let panel = container(
+        column![
+            text("Beat Trainer").size(18),
+            text(summary).size(14),
+        ]
+        .spacing(5)
+        .padding(15)
+    )
+    .width(Length::Fill);
+
+    Some(panel.into())
+}
+
+/// Creates the detected note history panel, newest note first
+fn create_note_history_panel(data: &crate::AppDisplayData) -> Option<Element<'static, crate::Message>>
+{
+    if !data.note_history_visible {
+        return None;
+    }
+
+    let now = Instant::now();
+    let entries = data.note_history.iter().rev().map(|note| {
+        let cents = note.cents.map_or("--".to_string(), |c| format!("{:+.1} cents", c));
+        let age = now.duration_since(note.detected_at).as_secs_f32();
+        row![
+            text(tuning::display_note_name(&note.note_name, data.octave_convention)).size(14).width(Length::Fixed(50.0)),
+            text(cents).size(14).width(Length::Fixed(100.0)),
+            text(format!("{:.1} s ago", age)).size(12),
+        ]
+        .into()
+    });
+
+    let panel = container(
+        column![
+            text("Note History").size(18),
+            Space::with_height(5),
+            scrollable(column(entries).spacing(2)).height(Length::Fill),
+        ]
+        .spacing(5)
+        .padding(15)
+    )
+    .width(Length::Fill)
+    .height(Length::Fixed(140.0));
+
+    Some(panel.into())
+}
+
 /// Creates the cent meter panel
 fn create_cent_meter_panel(data: &crate::AppDisplayData) -> Option<Element<'static, crate::Message>>
 {
@@ -177,34 +457,150 @@ fn create_cent_meter_panel(data: &crate::AppDisplayData) -> Option<Element<'stat
     
     // Calculate smoothed cent deviation
     let smoothed_cents = if data.smoothing_buffer.is_empty() {
-        data.last_analysis.as_ref().and_then(|analysis| analysis.cents_deviation)
+        data.last_reading.as_ref().map(|reading| reading.cents)
     } else {
-        let sum: f32 = data.smoothing_buffer.iter().sum();
+        let sum: f32 = data.smoothing_buffer.iter().map(|&(_, cents)| cents).sum();
         let count = data.smoothing_buffer.len() as f32;
         if count > 0.0 { Some(sum / count) } else { None }
     };
     
     let (note_name, freq_text, confidence) = if let Some(analysis) = &data.last_analysis {
-        let current_freq = analysis.detected_frequency.unwrap_or(0.0);
-        let note_text = match &data.tuning_mode {
-            crate::TuningMode::Auto => analysis.note_name.clone().unwrap_or_else(|| "--".to_string()),
-            crate::TuningMode::Manual { note_name, .. } => note_name.clone(),
+        let (note_text, current_freq) = match &data.tuning_mode {
+            crate::TuningMode::Auto => (
+                match &data.last_reading {
+                    Some(reading) => tuning::display_note_name(&reading.note_name, data.octave_convention),
+                    None if analysis.out_of_range => "Out of range".to_string(),
+                    None => "--".to_string(),
+                },
+                analysis.detected_frequency.unwrap_or(0.0),
+            ),
+            crate::TuningMode::Manual { note_name, .. } if data.target_partial > 1 => (
+                format!("{} P{}", tuning::display_note_name(note_name, data.octave_convention), data.target_partial),
+                analysis.partial_frequency(data.target_partial).unwrap_or(0.0),
+            ),
+            crate::TuningMode::Manual { note_name, .. } => (
+                tuning::display_note_name(note_name, data.octave_convention),
+                analysis.detected_frequency.unwrap_or(0.0),
+            ),
         };
-        // Convert the confidence value (0.0-1.0) to a percentage string.
-        let confidence_text = analysis.confidence
-            .map(|c| format!("{:.0}%", c * 100.0))
-            .unwrap_or_else(|| "0%".to_string());
-        
-        (note_text, format!("{:.2} Hz", current_freq), confidence_text)
+        (note_text, format!("{:.2} Hz", current_freq), analysis.confidence.unwrap_or(0.0))
     } else { 
-        ("--".to_string(), "0.00 Hz".to_string(), "0%".to_string()) 
+        ("--".to_string(), "0.00 Hz".to_string(), 0.0) 
+    };
+
+    // Confidence (0.0-1.0) as a percentage, a small gauge, or not at all
+    let (confidence_header, confidence_view): (Element<'static, crate::Message>, Element<'static, crate::Message>) =
+        match data.confidence_display {
+            crate::ConfidenceDisplay::Percent => (
+                text("Confidence").size(14).into(),
+                container(text(format!("{:.0}%", confidence * 100.0)).size(16)).padding([4, 8]).into(),
+            ),
+            crate::ConfidenceDisplay::Bar => (
+                text("Confidence").size(14).into(),
+                container(
+                    progress_bar(0.0..=1.0, confidence)
+                        .width(Length::Fixed(80.0))
+                        .height(Length::Fixed(10.0)),
+                )
+                .padding([4, 8])
+                .into(),
+            ),
+            crate::ConfidenceDisplay::Hidden => (Space::with_width(0).into(), Space::with_width(0).into()),
+        };
+    
+    let level_text = data.last_analysis.as_ref()
+        .filter(|analysis| analysis.input_level > 0.0)
+        .map(|analysis| format!("{:.1} dBFS", 20.0 * analysis.input_level.log10()))
+        .unwrap_or_else(|| "-inf dBFS".to_string());
+    let clipping = data.clip_indicator_until.is_some_and(|until| Instant::now() < until);
+    let clip_indicator = text("CLIP").size(14).color(if clipping {
+        iced::Color::from_rgb(0.9, 0.1, 0.1)
+    } else {
+        iced::Color::from_rgba(0.5, 0.5, 0.5, 0.3)
+    });
+
+    // A sustained note whose amplitude warbles has a false beat (usually a bad string)
+    let false_beat: Element<'static, crate::Message> = match data.last_analysis.as_ref().and_then(|a| a.modulation_hz) {
+        Some(rate) => text(format!("False beat {:.1} Hz", rate)).size(14).color(iced::Color::from_rgb(1.0, 0.6, 0.1)).into(),
+        None => Space::with_width(0).into(),
+    };
+
+    // The exact smoothed value for record-keeping, colored like the needle
+    let cents_readout: Element<'static, crate::Message> = match smoothed_cents.filter(|_| data.cents_readout_visible) {
+        Some(cents) => {
+            let color = data.tolerance_curve.for_optional_key(current_key_index(data)).color(cents, &data.palette);
+            text(format!("{:+.1} \u{a2}", cents)).size(24).color(color).width(Length::Fixed(90.0)).into()
+        }
+        None if data.cents_readout_visible => text("-- \u{a2}").size(24).width(Length::Fixed(90.0)).into(),
+        None => Space::with_width(0).into(),
+    };
+
+    // Whether the note is still blooming, sustaining or dying away
+    let energy_trend = match data.last_analysis.as_ref().filter(|a| a.note_name.is_some()).and_then(|a| a.energy_trend) {
+        Some(EnergyTrend::Rising) => "Blooming",
+        Some(EnergyTrend::Steady) => "Sustaining",
+        Some(EnergyTrend::Falling) => "Decaying",
+        None => "",
+    };
+
+    // Rolling detection quality helps with microphone placement; flagged when low
+    let quality = &data.detection_quality;
+    let quality_text = match (quality.detection_rate(), quality.mean_confidence()) {
+        (Some(rate), Some(mean)) => {
+            let low = rate < crate::LOW_DETECTION_RATE || mean < crate::LOW_MEAN_CONFIDENCE;
+            let color = if low {
+                iced::Color::from_rgb(1.0, 0.6, 0.1)
+            } else {
+                iced::Color::from_rgba(0.7, 0.7, 0.7, 0.6)
+            };
+            let hint = if low { " - move the mic closer or reduce noise" } else { "" };
+            text(format!("Detection {:.0}% of frames, mean confidence {:.0}%{}", rate * 100.0, mean * 100.0, hint))
+                .size(11)
+                .color(color)
+        }
+        (Some(_), None) => text("No pitch detected recently - move the mic closer or reduce noise")
+            .size(11)
+            .color(iced::Color::from_rgb(1.0, 0.6, 0.1)),
+        _ => text(""),
+    };
+
+    // In polyphonic mode every simultaneous note is listed with its deviation
+    let polyphonic_notes: Element<'static, crate::Message> = match &data.last_analysis {
+        Some(analysis) if data.polyphonic_enabled && !analysis.detected_notes.is_empty() => {
+            let notes: Vec<String> = analysis.detected_notes
+                .iter()
+                .map(|(name, _, cents)| format!("{} {:+.1}", tuning::display_note_name(name, data.octave_convention), cents))
+                .collect();
+            // The two lowest notes are named as an interval, colored by how consonant they sound
+            let interval: Element<'static, crate::Message> = match analysis.detected_notes.get(..2) {
+                Some([(_, lower, _), (_, upper, _)]) => match tuning::nearest_interval(*lower, *upper) {
+                    Some(interval) => {
+                        let (verdict, color) = if interval.consonant {
+                            ("consonant", data.palette.in_tune)
+                        } else {
+                            ("dissonant", data.palette.out_of_tune)
+                        };
+                        text(format!("{} {:+.1} \u{a2} ({})", interval, interval.cents_error, verdict))
+                            .size(14)
+                            .color(color)
+                            .into()
+                    }
+                    None => Space::with_width(0).into(),
+                },
+                _ => Space::with_width(0).into(),
+            };
+            row![text(notes.join("   ")).size(14), horizontal_space(), interval].into()
+        }
+        _ => Space::with_height(0).into(),
     };
 
     let cent_meter_content = column![
         row![
             text("Note").size(14),
             horizontal_space(),
-            text("Confidence").size(14),
+            text("Level").size(14),
+            Space::with_width(30),
+            confidence_header,
         ],
         Space::with_height(5),
         row![
@@ -212,17 +608,56 @@ fn create_cent_meter_panel(data: &crate::AppDisplayData) -> Option<Element<'stat
             Space::with_width(10),
             text(freq_text).size(24),
             horizontal_space(),
-            container(text(confidence).size(16)).padding([4, 8]),
+            false_beat,
+            Space::with_width(6),
+            text(energy_trend).size(14).color(iced::Color::from_rgba(0.7, 0.7, 0.7, 0.8)),
+            Space::with_width(6),
+            clip_indicator,
+            Space::with_width(6),
+            text(level_text).size(16),
+            confidence_view,
         ]
         .align_y(Alignment::Center),
+        polyphonic_notes,
         Space::with_height(10),
-        cent_meter::CentMeter::new(smoothed_cents).view()
+        row![
+            cent_meter::CentMeter::new(smoothed_cents, current_key_index(data), &data.tolerance_curve, data.needle_dynamics, data.palette).view(),
+            cents_readout,
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+        quality_text,
     ]
     .spacing(5);
     
+    // Checking A4 against the reference is one click away, whatever is selected
+    let reference_a = matches!(data.tuning_mode, crate::TuningMode::Manual { key_index, .. } if key_index as usize == tuning::A4_KEY_INDEX);
+    let reference_a_button = button(text("A4").size(12))
+        .padding([2, 8])
+        .style(if reference_a { button::primary } else { button::secondary })
+        .on_press(crate::Message::TuneReferenceA);
+
+    // The partial selector only applies when tuning a selected key
+    let title_row = match &data.tuning_mode {
+        crate::TuningMode::Manual { .. } => row![
+            text("Cent Meter").size(18),
+            horizontal_space(),
+            reference_a_button,
+            Space::with_width(6),
+            button(text("Play").size(12))
+                .padding([2, 8])
+                .style(button::secondary)
+                .on_press(crate::Message::PlayReference),
+            Space::with_width(10),
+            make_partial_selector(data.target_partial),
+        ],
+        crate::TuningMode::Auto => row![text("Cent Meter").size(18), horizontal_space(), reference_a_button],
+    }
+    .align_y(Alignment::Center);
+    
     let panel = container(
         column![
-            text("Cent Meter").size(18),
+            title_row,
             Space::with_height(10),
             cent_meter_content
         ]
@@ -235,6 +670,41 @@ fn create_cent_meter_panel(data: &crate::AppDisplayData) -> Option<Element<'stat
     Some(panel.into())
 }
 
+/// Creates the row of buttons used to choose which partial is tuned.
+///
+/// Partial 1 is the fundamental; higher partials let the tuner set an
+/// interval against a specific coincident partial, as done aurally.
+fn make_partial_selector(target_partial: u32) -> Element<'static, crate::Message> {
+    (1..=MAX_TARGET_PARTIAL).fold(row![].spacing(2), |selector, partial| {
+        let selected = partial == target_partial;
+        selector.push(
+            button(text(partial.to_string()).size(12))
+                .padding([2, 6])
+                .style(move |theme, status| {
+                    if selected {
+                        button::primary(theme, status)
+                    } else {
+                        button::secondary(theme, status)
+                    }
+                })
+                .on_press(crate::Message::SelectPartial(partial)),
+        )
+    })
+    .into()
+}
+
+/// Returns the key currently being tuned: the selected key in manual mode,
+/// otherwise the most recently detected note.
+fn current_key_index(data: &crate::AppDisplayData) -> Option<u8> {
+    match &data.tuning_mode {
+        crate::TuningMode::Manual { key_index, .. } => Some(*key_index),
+        crate::TuningMode::Auto => data.last_analysis
+            .as_ref()
+            .and_then(|analysis| analysis.note_name.as_ref())
+            .and_then(|name| crate::tuning::key_index_from_name_in(name, OctaveConvention::Scientific)),
+    }
+}
+
 /// Creates the piano keyboard panel
 fn create_keyboard_panel(data: &crate::AppDisplayData) -> Option<Element<'static, crate::Message>>
 {
@@ -242,18 +712,34 @@ fn create_keyboard_panel(data: &crate::AppDisplayData) -> Option<Element<'static
         return None;
     }
     
-    // Determine detected and selected key indices
-    let detected_key_index = data.last_analysis
-        .as_ref()
-        .and_then(|analysis| analysis.note_name.as_ref())
-        .and_then(|name| Some(crate::tuning::get_key_index_from_name(name)));
+    // Determine detected and selected key positions on the keyboard drawn;
+    // polyphonic mode highlights every simultaneous note
+    let detected_positions: Vec<usize> = match &data.last_analysis {
+        Some(analysis) if data.polyphonic_enabled => analysis.detected_notes
+            .iter()
+            .filter_map(|(name, _, _)| data.keyboard.position_of_name(name))
+            .collect(),
+        Some(analysis) => analysis.note_name
+            .as_ref()
+            .and_then(|name| data.keyboard.position_of_name(name))
+            .into_iter()
+            .collect(),
+        None => Vec::new(),
+    };
     
-    let selected_key_index = match &data.tuning_mode {
-        crate::TuningMode::Manual { key_index, .. } => Some(*key_index),
+    let selected_position = match &data.tuning_mode {
+        crate::TuningMode::Manual { key_index, .. } => data.keyboard.position(*key_index),
         crate::TuningMode::Auto => None,
     };
     
-    let piano_keyboard = piano_keyboard::PianoKeyboard::new(detected_key_index, selected_key_index);
+    let piano_keyboard = piano_keyboard::PianoKeyboard::new(
+        detected_positions,
+        selected_position,
+        data.palette,
+        data.keyboard,
+        piano_keyboard::KEYBOARD_HEIGHT,
+        piano_keyboard::BLACK_KEY_HEIGHT,
+    );
 
     let keyboard_content = container(piano_keyboard.view())
         .width(Length::Fill)
@@ -261,7 +747,12 @@ fn create_keyboard_panel(data: &crate::AppDisplayData) -> Option<Element<'static
     
     let panel = container(
         column![
-            text("Keyboard Key Select").size(18),
+            row![
+                text("Keyboard Key Select").size(18),
+                horizontal_space(),
+                text(data.keyboard_notice.clone().unwrap_or_default()).size(12),
+            ]
+            .align_y(Alignment::Center),
             Space::with_height(10),
             keyboard_content
         ]
@@ -274,29 +765,67 @@ fn create_keyboard_panel(data: &crate::AppDisplayData) -> Option<Element<'static
     Some(panel.into())
 }
 
-/// Creates the partials display panel
-fn create_partials_panel(data: &crate::AppDisplayData) -> Option<Element<'static, crate::Message>>
+/// Creates the partials display panel.
+///
+/// When the key being tuned has a stored measurement, a line under the graph
+/// lists the 'B' implied by each of its overtones and the fit of the regression.
+fn create_partials_panel(
+    data: &crate::AppDisplayData,
+    profile: &InharmonicityProfile,
+) -> Option<Element<'static, crate::Message>>
 {
     if !data.partials_visible {
         return None;
     }
     
     let partials_data = data.last_analysis.as_ref()
-        .map(|a| a.partials.clone())
+        .map(|a| a.partials.iter().map(|p| (p.number, p.frequency)).collect())
         .unwrap_or_default();
+    let fundamental = data.last_analysis.as_ref().and_then(|a| a.detected_frequency);
 
     let partials_content = container(
-        partials_display::PartialsDisplay::new(partials_data).view()
+        partials_display::PartialsDisplay::new(partials_data, fundamental, data.partial_display_mode, data.displayed_partials).view()
     )
     .width(Length::Fill)
     .height(Length::Fill);
+
+    let stored_b = current_key_index(data)
+        .and_then(|key_index| profile.measurements.get(&key_index))
+        .map(|measurement| {
+            let per_partial = measurement
+                .partial_b_values()
+                .iter()
+                .map(|(n, b)| format!("P{} {:.1e}", n, b))
+                .collect::<Vec<_>>()
+                .join("  ");
+            let fit = match measurement.fit_r_squared {
+                Some(r2) => format!("R² {:.3}", r2),
+                None if measurement.low_confidence => "two partials only".to_string(),
+                None => "no fit".to_string(),
+            };
+            let reliability = match measurement.reliability {
+                Some(r) if measurement.is_reliable() => format!(", reliability {:.0}%", r * 100.0),
+                Some(r) => format!(", UNRELIABLE {:.0}%", r * 100.0),
+                None => String::new(),
+            };
+            text(format!("Stored B by partial: {}  ({}{})", per_partial, fit, reliability)).size(12)
+        });
     
     let panel = container(
         column![
-            text("Partials").size(18),
+            row![
+                text("Partials").size(18),
+                horizontal_space(),
+                button(text("Cents").size(12))
+                    .padding([2, 8])
+                    .style(if data.partial_display_mode == PartialDisplayMode::CentsFromHarmonic { button::primary } else { button::secondary })
+                    .on_press(crate::Message::TogglePartialDisplayMode),
+            ]
+            .align_y(Alignment::Center),
             Space::with_height(10),
             partials_content
         ]
+        .push_maybe(stored_b)
         .spacing(5)
         .padding(15)
     )
@@ -315,13 +844,13 @@ fn create_partials_panel(data: &crate::AppDisplayData) -> Option<Element<'static
 /// capture button for recording partial measurements.
 /// 
 /// # Arguments
-/// * `capture_state` - Current capture state (Off, Armed, Done)
+/// * `data` - Current display data (capture state and toggle states)
 /// * `capture_message` - Message to send when capture button is pressed
 /// 
 /// # Returns
 /// * `Element` - Complete sidebar widget with all controls and sections
 fn create_sidebar(
-    capture_state: crate::CaptureState,
+    data: &crate::AppDisplayData,
     capture_message: crate::Message,
 ) -> Element<'static, crate::Message>
 {
@@ -329,13 +858,43 @@ fn create_sidebar(
     
     // Add all settings sections
     for (title, buttons) in SETTINGS_CONFIG {
-        let in_measurement_mode = capture_state != crate::CaptureState::Off;
-        sections = sections.push(make_settings_section(title, buttons, in_measurement_mode));
+        sections = sections.push(make_settings_section(title, buttons, data));
     }
+
+    // Name for the next profile; left empty, the next free "Piano N" is used
+    sections = sections.push(
+        row![
+            text_input("New profile name", &data.new_profile_name)
+                .on_input(crate::Message::ProfileNameChanged)
+                .on_submit(crate::Message::CreateProfile)
+                .size(14),
+            button(text("New").size(14))
+                .padding([6, 10])
+                .on_press(crate::Message::CreateProfile),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
+    );
+    if let Some(status) = &data.profile_status {
+        sections = sections.push(text(status.clone()).size(12));
+    }
+
+    // Software gain for inputs too quiet for the detection gate
+    sections = sections.push(
+        column![
+            text(format!("Input gain +{:.0} dB", data.input_gain_db)).size(14),
+            slider(0.0..=tuner_core::analysis::MAX_INPUT_GAIN_DB, data.input_gain_db, crate::Message::InputGainChanged)
+                .step(1.0),
+        ]
+        .spacing(4),
+    );
     
     // Add capture button if in measurement mode
-    if capture_state != crate::CaptureState::Off {
-        sections = sections.push(make_capture_button(capture_state, capture_message));
+    if data.capture_state != crate::CaptureState::Off {
+        sections = sections.push(make_capture_button(data.capture_state.clone(), capture_message, data.capture_done_at, data.done_display_time));
+        if let Some(status) = &data.capture_status {
+            sections = sections.push(text(status.clone()).size(12));
+        }
     }
     
     container(sections.padding(15))
@@ -347,27 +906,32 @@ fn create_sidebar(
 /// Creates a button based on configuration and application state.
 /// 
 /// Generates a styled button widget based on the provided configuration.
-/// Applies different visual styles based on button type (Standard, MeasurementMode, Disabled)
+/// Applies different visual styles based on button type (Standard, MeasurementMode, Toggle, Setting, Disabled)
 /// and current application state. Measurement mode buttons change color when active,
-/// while disabled buttons are grayed out and non-interactive.
+/// toggles are highlighted while on, and disabled buttons are grayed out and non-interactive.
 /// 
 /// # Arguments
 /// * `config` - Button configuration containing label, message, and type
-/// * `in_measurement_mode` - Whether the application is in measurement mode
+/// * `data` - Current display data used to resolve the button's state
 /// 
 /// # Returns
 /// * `Element` - Styled button widget with appropriate message handler
 fn make_button(
     config: &ButtonConfig,
-    in_measurement_mode: bool,
+    data: &crate::AppDisplayData,
 ) -> Element<'static, crate::Message> 
 {
-    let mut button = button(text(config.label).size(14).width(Length::Fill))
+    let in_measurement_mode = data.capture_state != crate::CaptureState::Off;
+    let label = match config.button_type {
+        ButtonType::Setting(value) => format!("{}: {}", config.label, value(data)),
+        _ => config.label.to_string(),
+    };
+    let mut button = button(text(label).size(14).width(Length::Fill))
         .padding([6, 10]);
     
     // Apply styling based on button type and state
     match config.button_type {
-        ButtonType::Standard => {
+        ButtonType::Standard | ButtonType::Setting(_) => {
             // No special styling needed
         },
         ButtonType::MeasurementMode => {
@@ -382,6 +946,18 @@ fn make_button(
                 });
             }
         },
+        ButtonType::Toggle(is_on) => {
+            if is_on(data) {
+                button = button.style(|_theme, _status| {
+                    use iced::widget::button;
+                    button::Style {
+                        background: Some(iced::Background::Color(iced::Color::from_rgb(0.2, 0.6, 0.3))), // Green background
+                        text_color: iced::Color::WHITE,
+                        ..button::Style::default()
+                    }
+                });
+            }
+        },
         ButtonType::Disabled => {
             button = button.style(|_theme, _status| {
                 use iced::widget::button;
@@ -408,41 +984,26 @@ fn make_button(
 /// The button changes appearance based on its state:
 /// - Off: Gray button with "Off" text
 /// - Armed: Gold button with "Capture" text  
-/// - Done: Green button with "Done" text (shows for 3 seconds)
+/// - Done: Green button with "Done" text (shows for `done_display_time`)
 /// This provides clear visual feedback for the measurement process.
 /// 
 /// # Arguments
 /// * `capture_state` - Current capture state (Off, Armed, Done)
 /// * `capture_message` - Message to send when the button is pressed
+/// * `done_at` - When the last capture was stored, if any
+/// * `done_display_time` - How long "Done" is shown after a capture (zero = not at all)
 /// 
 /// # Returns
 /// * `Element` - Large, prominently styled capture button
 fn make_capture_button(
     capture_state: crate::CaptureState,
     capture_message: crate::Message,
+    done_at: Option<Instant>,
+    done_display_time: std::time::Duration,
 ) -> Element<'static, crate::Message> 
 {
-    // Handle timer logic for "Done" state display
-    let should_show_done = {
-        let timer_guard = CAPTURE_DONE_TIMER.get_or_init(|| Mutex::new(None));
-        let mut timer = timer_guard.lock().unwrap();
-        
-        // Check if we should show "Done" based on timer
-        if let Some(start_time) = *timer {
-            let elapsed = start_time.elapsed();
-            if elapsed < Duration::from_secs(1) {
-                // Still within 2 seconds - show "Done"
-                true
-            } else {
-                // Timer expired - clear timer and don't show "Done"
-                *timer = None;
-                false
-            }
-        } else {
-            // No timer set - don't show "Done"
-            false
-        }
-    };
+    // Measured in real time, so it doesn't depend on how often the view is redrawn
+    let should_show_done = done_at.is_some_and(|at| at.elapsed() < done_display_time);
     
     let (text_label, color, message) = if should_show_done {
         ("Done", iced::Color::from_rgb(0.2, 0.8, 0.2), capture_message) // Green
@@ -484,14 +1045,14 @@ fn make_capture_button(
 /// # Arguments
 /// * `title` - Section title (e.g., "Tools", "Program")
 /// * `buttons` - Array of button configurations for this section
-/// * `in_measurement_mode` - Whether the application is in measurement mode
+/// * `data` - Current display data used to resolve button states
 /// 
 /// # Returns
 /// * `Element` - Complete settings section with title and button list
 fn make_settings_section(
     title: &'static str,
     buttons: &[ButtonConfig],
-    in_measurement_mode: bool,
+    data: &crate::AppDisplayData,
 ) -> Element<'static, crate::Message> 
 {
     let title_widget = text(title).size(18);
@@ -499,7 +1060,7 @@ fn make_settings_section(
     let items_widget = buttons.iter().fold(
         column![].spacing(8),
         |col, config| {
-            col.push(make_button(config, in_measurement_mode))
+            col.push(make_button(config, data))
         }
     );
 
@@ -6,4 +6,6 @@ pub mod cent_meter;
 pub mod piano_keyboard;
 pub mod spectrogram;
 pub mod partials_display;
+pub mod pitch_history;
+pub mod palette;
 pub mod main_display;
\ No newline at end of file
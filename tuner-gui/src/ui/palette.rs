@@ -0,0 +1,78 @@
+//! # Palette Module
+//!
+//! This module defines the colors the canvas widgets draw with, so they can
+//! be changed in one place and picked by the user.
+//!
+//! ## Features
+//! - One `Palette` shared by the spectrogram, keyboard and cent meter
+//! - Built-in presets, including one that avoids red/green distinctions
+
+use iced::Color;
+
+/// `Color::from_rgb8` for use in constants.
+const fn rgb8(r: u8, g: u8, b: u8) -> Color {
+    Color::from_rgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+}
+
+/// Colors used by the spectrogram, piano keyboard and cent meter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// Spectrum bars
+    pub spectrum: Color,
+    /// Key chosen for manual tuning
+    pub key_selected: Color,
+    /// Key currently detected in the audio
+    pub key_detected: Color,
+    /// Cent meter needle inside the green zone
+    pub in_tune: Color,
+    /// Cent meter needle inside the yellow zone
+    pub near: Color,
+    /// Cent meter needle outside both zones
+    pub out_of_tune: Color,
+}
+
+impl Palette {
+    /// The original colors.
+    pub const STANDARD: Palette = Palette {
+        spectrum: rgb8(0x34, 0x98, 0xDB),
+        key_selected: rgb8(0xFF, 0x33, 0x33),
+        key_detected: rgb8(0x34, 0xDB, 0x98),
+        in_tune: rgb8(0x34, 0xDB, 0x98),
+        near: rgb8(0xFF, 0xC3, 0x00),
+        out_of_tune: rgb8(0xFF, 0x33, 0x33),
+    };
+
+    /// Okabe-Ito colors, distinguishable with the common forms of color blindness.
+    /// In tune is blue rather than green so it never has to be told apart from red.
+    pub const COLOR_BLIND: Palette = Palette {
+        spectrum: rgb8(0x56, 0xB4, 0xE9),
+        key_selected: rgb8(0xE6, 0x9F, 0x00),
+        key_detected: rgb8(0x00, 0x72, 0xB2),
+        in_tune: rgb8(0x56, 0xB4, 0xE9),
+        near: rgb8(0xF0, 0xE4, 0x42),
+        out_of_tune: rgb8(0xD5, 0x5E, 0x00),
+    };
+
+    /// Saturated colors for bright rooms and low-contrast screens.
+    pub const HIGH_CONTRAST: Palette = Palette {
+        spectrum: Color::WHITE,
+        key_selected: Color::from_rgb(1.0, 0.0, 1.0),
+        key_detected: Color::from_rgb(0.0, 1.0, 1.0),
+        in_tune: Color::from_rgb(0.0, 1.0, 0.0),
+        near: Color::from_rgb(1.0, 1.0, 0.0),
+        out_of_tune: Color::from_rgb(1.0, 0.0, 1.0),
+    };
+
+    /// Presets offered in the sidebar, in order.
+    pub const PRESETS: [(&'static str, Palette); 3] = [
+        ("standard", Palette::STANDARD),
+        ("color-blind safe", Palette::COLOR_BLIND),
+        ("high contrast", Palette::HIGH_CONTRAST),
+    ];
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::STANDARD
+    }
+}
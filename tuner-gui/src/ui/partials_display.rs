@@ -2,25 +2,69 @@
 //!
 //! A custom Iced widget to display the measured partials of a musical note.
 //! It dynamically lists the frequency of each detected partial using a Canvas,
-//! consistent with other widgets in the application.
+//! consistent with other widgets in the application. Partials can be shown in
+//! Hz or as cents from their ideal harmonic (`n * f0`), which reads directly as
+//! the string's stretch. When more partials are listed than fit at the normal
+//! text size, the text shrinks and then wraps into further columns, so none is
+//! dropped.
 
 use iced::widget::canvas::{self, Frame, Geometry, Text};
 use iced::widget::container;
 use iced::{Element, Point, Rectangle, Renderer, Theme};
 
+/// Line height at the normal text size, in pixels.
+const LINE_HEIGHT: f32 = 15.0;
+
+/// Smallest line height the text shrinks to before wrapping into columns.
+const MIN_LINE_HEIGHT: f32 = 11.0;
+
+/// Text size relative to the line height.
+const TEXT_SIZE_RATIO: f32 = 14.0 / 15.0;
+
+/// How each partial's value is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum PartialDisplayMode {
+    /// Measured frequency in Hz
+    #[default]
+    Hz,
+    /// Cents from the ideal harmonic `n * f0` (positive = sharp)
+    CentsFromHarmonic,
+}
+
 /// Represents the state and view logic for the partials display panel.
 pub struct PartialsDisplay {
-    /// A vector containing the frequencies of the detected partials.
-    partials: Vec<f32>,
+    /// The partial number and frequency of each detected partial.
+    partials: Vec<(u32, f32)>,
+    /// Fundamental the ideal harmonics are computed from.
+    fundamental: Option<f32>,
+    /// Whether values are shown in Hz or cents.
+    mode: PartialDisplayMode,
+    /// Most partials listed, lowest first; more may be detected and used for measurement.
+    max_shown: usize,
 }
 
 impl PartialsDisplay {
     /// Creates a new `PartialsDisplay` widget.
     ///
     /// # Arguments
-    /// * `partials` - A vector of f32 frequencies for each detected partial.
-    pub fn new(partials: Vec<f32>) -> Self {
-        Self { partials }
+    /// * `partials` - Partial number (n) and frequency in Hz of each detected partial.
+    /// * `fundamental` - Detected fundamental in Hz, needed for `CentsFromHarmonic`.
+    /// * `mode` - Whether to show frequencies or cents from the ideal harmonic.
+    /// * `max_shown` - Most partials listed, lowest first.
+    pub fn new(partials: Vec<(u32, f32)>, fundamental: Option<f32>, mode: PartialDisplayMode, max_shown: usize) -> Self {
+        Self { partials, fundamental, mode, max_shown }
+    }
+
+    /// Formats the value shown for one partial.
+    fn value_label(&self, number: u32, freq: f32) -> String {
+        match (self.mode, self.fundamental) {
+            (PartialDisplayMode::CentsFromHarmonic, Some(f0)) if f0 > 0.0 => {
+                let cents = 1200.0 * (freq / (number as f32 * f0)).log2();
+                format!("{:+.1} cents", cents)
+            }
+            (PartialDisplayMode::CentsFromHarmonic, _) => "-- cents".to_string(),
+            (PartialDisplayMode::Hz, _) => format!("{:.2} Hz", freq),
+        }
     }
 
     /// Creates the view element for the partials display.
@@ -67,31 +111,39 @@ impl<Message> canvas::Program<Message> for PartialsDisplay {
         } else {
             // Define layout constants
             let start_y: f32 = 0.0;
-            let line_height: f32 = 15.0;
             let padding: f32 = 15.0;
 
+            // Shrink the lines to fit the height, then wrap into as many columns as needed
+            let shown = self.partials.len().min(self.max_shown.max(1));
+            let line_height = (bounds.height / shown as f32).clamp(MIN_LINE_HEIGHT, LINE_HEIGHT);
+            let rows = ((bounds.height / line_height) as usize).clamp(1, shown);
+            let columns = shown.div_ceil(rows);
+            let column_width = bounds.width / columns as f32;
+            let text_size = line_height * TEXT_SIZE_RATIO;
+
             // Draw each partial's information
-            for (i, &freq) in self.partials.iter().enumerate().take(8) { // Limit to 8 to fit
-                let y = start_y + (i as f32 * line_height);
+            for (i, &(number, freq)) in self.partials.iter().enumerate().take(shown) {
+                let y = start_y + ((i % rows) as f32 * line_height);
+                let left = (i / rows) as f32 * column_width;
 
                 // Draw "Partial X" on the left
                 let partial_label = Text {
-                    content: format!("Partial {}", i + 1),
-                    position: Point::new(padding, y),
+                    content: format!("Partial {}", number),
+                    position: Point::new(left + padding, y),
                     color: text_color,
-                    size: 14.0.into(),
+                    size: text_size.into(),
                     horizontal_alignment: iced::alignment::Horizontal::Left,
                     vertical_alignment: iced::alignment::Vertical::Top,
                     ..Text::default()
                 };
                 frame.fill_text(partial_label);
 
-                // Draw "XXX.XX Hz" on the right
+                // Draw "XXX.XX Hz" or "+X.X cents" on the right
                 let freq_label = Text {
-                    content: format!("{:.2} Hz", freq),
-                    position: Point::new(bounds.width - padding, y),
+                    content: self.value_label(number, freq),
+                    position: Point::new(left + column_width - padding, y),
                     color: text_color,
-                    size: 14.0.into(),
+                    size: text_size.into(),
                     horizontal_alignment: iced::alignment::Horizontal::Right,
                     vertical_alignment: iced::alignment::Vertical::Top,
                     ..Text::default()
@@ -1,55 +1,73 @@
 //! # Piano Keyboard Widget
 //! 
-//! This module provides an interactive 88-key piano keyboard widget
+//! This module provides an interactive piano keyboard widget
 //! for piano tuning applications. It displays a visual representation
 //! of the piano keyboard with clickable keys and visual feedback
 //! for detected and selected notes.
 //! 
 //! ## Features
-//! - 88-key piano keyboard visualization
-//! - Interactive key selection
+//! - 88-key piano keyboard visualization, or any `KeyboardSpec` range
+//! - Interactive key selection; keys of an extended keyboard outside the
+//!   standard 88 report `KeyOutsideProfile` instead, as profiles can't hold them
 //! - Visual feedback for detected notes
 //! - Professional piano appearance
 //! - Click-to-select functionality
+//! - Adjustable height and black-key length, hit-tested against the drawn size
 
 use iced::widget::canvas::{self, event, Event, Fill, Geometry, Path, Stroke};
 use iced::widget::container;
 use iced::{mouse, Color, Element, Point, Rectangle, Renderer, Size, Theme};
-
-/// Number of white keys on an 88-key piano.
-const WHITE_KEY_COUNT: usize = 52;
-/// Total number of keys on an 88-key piano.
-const TOTAL_KEY_COUNT: usize = 88;
-
-/// Pattern indicating which keys in an octave are black keys.
-/// This array represents the pattern: C, C#, D, D#, E, F, F#, G, G#, A, A#, B
-const IS_BLACK: [bool; 12] = [
-    false, true, false, false, true, false, true, false, false, true, false, true,
-];
+use tuner_core::tuning::KeyboardSpec;
+use super::palette::Palette;
 
 /// Interactive piano keyboard widget for note selection and visualization.
 /// 
-/// This widget displays a full 88-key piano keyboard with visual feedback
+/// This widget displays a full piano keyboard with visual feedback
 /// for detected notes and user-selected keys. It supports click-to-select
 /// functionality for manual tuning mode.
 #[derive(Debug, Clone)]
 pub struct PianoKeyboard {
-    /// Currently detected key index (from audio analysis)
-    detected_key_index: Option<u8>,
-    /// User-selected key index (from mouse clicks)
-    selected_key_index: Option<u8>,
+    /// Positions of the currently detected keys (from audio analysis)
+    detected_positions: Vec<usize>,
+    /// Position of the user-selected key (from mouse clicks)
+    selected_position: Option<usize>,
+    /// Colors for the selected and detected keys
+    palette: Palette,
+    /// Range of keys drawn
+    keyboard: KeyboardSpec,
+    /// Height of the keyboard in pixels
+    height: f32,
+    /// Height of a black key relative to the keyboard (0.0-1.0)
+    black_key_height: f32,
 }
 
 impl PianoKeyboard {
     /// Creates a new piano keyboard widget.
     /// 
     /// # Arguments
-    /// * `detected_key_index` - Currently detected key from audio analysis (0-87)
-    /// * `selected_key_index` - User-selected key from mouse clicks (0-87)
-    pub fn new(detected_key_index: Option<u8>, selected_key_index: Option<u8>) -> Self {
+    /// * `detected_positions` - Currently detected keys (several in polyphonic mode),
+    ///   counted from the lowest key drawn
+    /// * `selected_position` - User-selected key, counted from the lowest key drawn
+    /// * `palette` - Colors for the selected and detected keys
+    /// * `keyboard` - Range of keys drawn
+    /// * `height` - Height of the keyboard in pixels, e.g. [`KEYBOARD_HEIGHT`]
+    /// * `black_key_height` - Height of a black key relative to the keyboard,
+    ///   e.g. [`BLACK_KEY_HEIGHT`]; clamped to 0.0-1.0
+    pub fn new(
+        detected_positions: Vec<usize>,
+        selected_position: Option<usize>,
+        palette: Palette,
+        keyboard: KeyboardSpec,
+        height: f32,
+        black_key_height: f32,
+    ) -> Self {
         Self {
-            detected_key_index,
-            selected_key_index,
+            detected_positions,
+            selected_position,
+            palette,
+            keyboard,
+            height,
+            black_key_height: black_key_height.clamp(0.0, 1.0),
         }
     }
 
@@ -58,66 +76,79 @@ impl PianoKeyboard {
     /// This method consumes the PianoKeyboard instance to create an Iced Element
     /// that can be embedded in the GUI layout.
     pub fn view(self) -> Element<'static, super::super::Message> {
+        let height = self.height;
         container(
             canvas::Canvas::new(self)
                 .width(iced::Length::Fill)
-                .height(iced::Length::Fixed(120.0)),
+                .height(iced::Length::Fixed(height)),
         )
         .into()
     }
 
-    /// Determines which piano key was clicked based on mouse position.
-    /// 
-    /// Calculates the key index (0-87) from a mouse click position on the keyboard.
-    /// Handles both white and black keys, with black keys taking priority since
-    /// they are drawn on top. Returns the key index if a valid key was clicked.
-    /// 
-    /// # Arguments
-    /// * `bounds` - Size of the keyboard widget
-    /// * `pos` - Mouse click position within the keyboard bounds
-    /// 
-    /// # Returns
-    /// * `Some(u8)` - Key index (0-87) if a key was clicked
-    /// * `None` - If click was outside any key area
-    fn key_index_from_pos(&self, bounds: Size, pos: Point) -> Option<u8> {
-        let white_key_width = bounds.width / WHITE_KEY_COUNT as f32;
-        let black_key_width = white_key_width * 0.6;
-        let black_key_height = 120.0 * 0.6;
-
-        // Check black keys first (they are on top)
-        let mut white_key_idx: f32 = 0.0;
-        for i in 0..TOTAL_KEY_COUNT {
-            let note_in_octave = i % 12;
-            if IS_BLACK[note_in_octave] {
-                let key_x = (white_key_idx - 0.5) * white_key_width; // Center on the line
-                let black_key_rect = Rectangle {
-                    x: key_x,
-                    y: 0.0,
-                    width: black_key_width,
-                    height: black_key_height,
-                };
-                if black_key_rect.contains(pos) {
-                    return Some(i as u8);
-                }
-            } else {
-                white_key_idx += 1.0;
-            }
-        }
+}
+
+/// Width of a black key relative to a white key.
+const BLACK_KEY_WIDTH: f32 = 0.6;
+
+/// Default height of the keyboard in pixels.
+pub const KEYBOARD_HEIGHT: f32 = 120.0;
+
+/// Default height of a black key relative to the keyboard, about that of a real piano.
+pub const BLACK_KEY_HEIGHT: f32 = 0.6;
+
+/// Number of white keys on a keyboard.
+fn white_key_count(keyboard: &KeyboardSpec) -> usize {
+    (0..keyboard.key_count as usize).filter(|&p| !keyboard.is_black(p)).count()
+}
+
+/// Left edge of a black key that follows `white_keys_before` white keys,
+/// centered on the line between its two neighbours.
+fn black_key_x(white_keys_before: usize, white_key_width: f32) -> f32 {
+    white_keys_before as f32 * white_key_width - white_key_width * BLACK_KEY_WIDTH / 2.0
+}
+
+/// Finds the key under a point of a keyboard drawn `width` by `height`.
+///
+/// Black keys are drawn over the white ones, so they win wherever the two
+/// overlap; below the black keys only white keys are hit. Kept free of any
+/// widget types so the geometry can be checked on its own.
+///
+/// # Arguments
+/// * `keyboard` - Range of keys drawn
+/// * `width` - Width of the keyboard in pixels
+/// * `height` - Height of the keyboard in pixels
+/// * `black_key_height` - Height of a black key relative to `height`
+/// * `x`, `y` - Point relative to the top-left corner of the keyboard
+///
+/// # Returns
+/// * `Some(position)` - Position of the key hit (0 = lowest key drawn)
+/// * `None` - The point is outside the keyboard
+pub fn position_at(keyboard: &KeyboardSpec, width: f32, height: f32, black_key_height: f32, x: f32, y: f32) -> Option<usize> {
+    if !(0.0..width).contains(&x) || !(0.0..height).contains(&y) {
+        return None;
+    }
+    let white_key_width = width / white_key_count(keyboard) as f32;
 
-        // Check white keys
-        let clicked_white_key = (pos.x / white_key_width).floor() as usize;
-        let mut current_white_key_idx = 0;
-        for i in 0..TOTAL_KEY_COUNT {
-            let note_in_octave = i % 12;
-            if !IS_BLACK[note_in_octave] {
-                if current_white_key_idx == clicked_white_key {
-                    return Some(i as u8);
+    // Check black keys first (they are on top)
+    if y < height * black_key_height {
+        let mut white_keys_before = 0;
+        for position in 0..keyboard.key_count as usize {
+            if keyboard.is_black(position) {
+                let left = black_key_x(white_keys_before, white_key_width);
+                if (left..left + white_key_width * BLACK_KEY_WIDTH).contains(&x) {
+                    return Some(position);
                 }
-                current_white_key_idx += 1;
+            } else {
+                white_keys_before += 1;
             }
         }
-        None
     }
+
+    // Otherwise it is the white key the point falls in
+    let white_key = (x / white_key_width) as usize;
+    (0..keyboard.key_count as usize)
+        .filter(|&position| !keyboard.is_black(position))
+        .nth(white_key)
 }
 
 impl<Message> canvas::Program<Message> for PianoKeyboard
@@ -135,11 +166,15 @@ where
     ) -> (event::Status, Option<Message>) {
         if let Some(position) = cursor.position_in(bounds) {
             if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event {
-                if let Some(key_index) = self.key_index_from_pos(bounds.size(), position) {
-                    return (
-                        event::Status::Captured,
-                        Some(super::super::Message::KeySelected(key_index).into()),
-                    );
+                if let Some(position) = position_at(&self.keyboard, bounds.width, bounds.height, self.black_key_height, position.x, position.y) {
+                    let message = match self.keyboard.key_index(position) {
+                        Some(key_index) => super::super::Message::KeySelected(key_index),
+                        // Profiles only hold the standard 88 keys
+                        None => super::super::Message::KeyOutsideProfile(
+                            self.keyboard.note(position).map(|note| note.name).unwrap_or_default(),
+                        ),
+                    };
+                    return (event::Status::Captured, Some(message.into()));
                 }
             }
         }
@@ -156,21 +191,20 @@ where
     ) -> Vec<Geometry> {
         let mut frame = canvas::Frame::new(renderer, bounds.size());
 
-        let white_key_width = bounds.width / WHITE_KEY_COUNT as f32;
-        let black_key_width = white_key_width * 0.6;
-        let black_key_height = bounds.height * 0.6;
+        let white_key_width = bounds.width / white_key_count(&self.keyboard) as f32;
+        let black_key_width = white_key_width * BLACK_KEY_WIDTH;
+        let black_key_height = bounds.height * self.black_key_height;
 
         // Draw white keys
         let mut white_key_x = 0.0;
-        for i in 0..TOTAL_KEY_COUNT {
-            let note_in_octave = i % 12;
-            if !IS_BLACK[note_in_octave] {
-                let is_detected = self.detected_key_index == Some(i as u8);
-                let is_selected = self.selected_key_index == Some(i as u8);
+        for i in 0..self.keyboard.key_count as usize {
+            if !self.keyboard.is_black(i) {
+                let is_detected = self.detected_positions.contains(&i);
+                let is_selected = self.selected_position == Some(i);
 
                 let color = match (is_selected, is_detected) {
-                    (true, _) => Color::from_rgb8(0xFF, 0x33, 0x33), // Red (Selected)
-                    (false, true) => Color::from_rgb8(0x34, 0xDB, 0x98), // Green (Detected)
+                    (true, _) => self.palette.key_selected,
+                    (false, true) => self.palette.key_detected,
                     _ => Color::WHITE,
                 };
 
@@ -191,17 +225,16 @@ where
         }
 
         // Draw black keys
-        let mut white_key_idx: f32 = 0.0;
-        for i in 0..TOTAL_KEY_COUNT {
-            let note_in_octave = i % 12;
-            if IS_BLACK[note_in_octave] {
-                let key_x = (white_key_idx - 0.5) * white_key_width;
-                let is_detected = self.detected_key_index == Some(i as u8);
-                let is_selected = self.selected_key_index == Some(i as u8);
+        let mut white_keys_before = 0;
+        for i in 0..self.keyboard.key_count as usize {
+            if self.keyboard.is_black(i) {
+                let key_x = black_key_x(white_keys_before, white_key_width);
+                let is_detected = self.detected_positions.contains(&i);
+                let is_selected = self.selected_position == Some(i);
 
                 let color = match (is_selected, is_detected) {
-                    (true, _) => Color::from_rgb8(0xFF, 0x33, 0x33), // Red
-                    (false, true) => Color::from_rgb8(0x34, 0xDB, 0x98), // Green
+                    (true, _) => self.palette.key_selected,
+                    (false, true) => self.palette.key_detected,
                     _ => Color::BLACK,
                 };
 
@@ -211,10 +244,79 @@ where
                     Fill::from(color),
                 );
             } else {
-                white_key_idx += 1.0;
+                white_keys_before += 1;
             }
         }
 
         vec![frame.into_geometry()]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Width that makes each of the 52 white keys of a standard keyboard 10 pixels wide.
+    const WIDTH: f32 = 520.0;
+    const WHITE_KEY_WIDTH: f32 = 10.0;
+
+    /// Height just below the black keys, where only white keys are hit.
+    const BELOW_BLACK_KEYS: f32 = KEYBOARD_HEIGHT * BLACK_KEY_HEIGHT + 1.0;
+
+    fn hit(x: f32, y: f32) -> Option<usize> {
+        position_at(&KeyboardSpec::STANDARD, WIDTH, KEYBOARD_HEIGHT, BLACK_KEY_HEIGHT, x, y)
+    }
+
+    #[test]
+    fn boundary_between_white_keys_belongs_to_the_right_key() {
+        // B0 (position 2) and C1 (position 3) meet at the end of the second white key
+        let boundary = 2.0 * WHITE_KEY_WIDTH;
+        assert_eq!(hit(boundary - 0.01, BELOW_BLACK_KEYS), Some(2));
+        assert_eq!(hit(boundary, BELOW_BLACK_KEYS), Some(3));
+        // E1 and F1 have no black key between them, even near the top
+        let e_f = 5.0 * WHITE_KEY_WIDTH;
+        assert_eq!(hit(e_f - 0.01, 1.0), Some(7));
+        assert_eq!(hit(e_f, 1.0), Some(8));
+    }
+
+    #[test]
+    fn black_key_wins_where_it_overlaps_white_keys() {
+        // A#0 (position 1) straddles the line between A0 and B0
+        let line = WHITE_KEY_WIDTH;
+        let half_black = WHITE_KEY_WIDTH * BLACK_KEY_WIDTH / 2.0;
+        for x in [line - half_black, line - 0.5, line, line + half_black - 0.01] {
+            assert_eq!(hit(x, 1.0), Some(1), "x = {x}");
+            assert_eq!(hit(x, BELOW_BLACK_KEYS), Some(if x < line { 0 } else { 2 }), "x = {x}");
+        }
+        // Just beside the black key, the white keys are hit at any height
+        assert_eq!(hit(line - half_black - 0.01, 1.0), Some(0));
+        assert_eq!(hit(line + half_black, 1.0), Some(2));
+    }
+
+    #[test]
+    fn black_key_height_moves_the_overlap() {
+        let y = KEYBOARD_HEIGHT * 0.5;
+        let x = WHITE_KEY_WIDTH;
+        assert_eq!(position_at(&KeyboardSpec::STANDARD, WIDTH, KEYBOARD_HEIGHT, 0.6, x, y), Some(1));
+        assert_eq!(position_at(&KeyboardSpec::STANDARD, WIDTH, KEYBOARD_HEIGHT, 0.4, x, y), Some(2));
+    }
+
+    #[test]
+    fn points_off_the_keyboard_hit_nothing() {
+        assert_eq!(hit(-0.01, 1.0), None);
+        assert_eq!(hit(WIDTH, 1.0), None);
+        assert_eq!(hit(1.0, KEYBOARD_HEIGHT), None);
+        assert_eq!(hit(WIDTH - 0.01, BELOW_BLACK_KEYS), Some(87));
+    }
+
+    #[test]
+    fn extended_keyboard_keys_below_a0_have_no_key_index() {
+        // The 97-key keyboard starts at C0 and has 56 white keys
+        let keyboard = KeyboardSpec::IMPERIAL;
+        let width = 560.0;
+        let c0 = position_at(&keyboard, width, KEYBOARD_HEIGHT, BLACK_KEY_HEIGHT, 1.0, BELOW_BLACK_KEYS).unwrap();
+        let a0 = position_at(&keyboard, width, KEYBOARD_HEIGHT, BLACK_KEY_HEIGHT, 51.0, BELOW_BLACK_KEYS).unwrap();
+        assert_eq!((c0, keyboard.key_index(c0)), (0, None));
+        assert_eq!((a0, keyboard.key_index(a0)), (9, Some(0)));
+    }
+}
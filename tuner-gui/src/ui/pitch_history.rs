@@ -0,0 +1,126 @@
+//! # Pitch History Widget
+//!
+//! A small line graph of the recent cent deviation readings. Where the cent
+//! meter shows the momentary reading, this shows how the pitch drifts over
+//! the last few seconds of a sustained note.
+//!
+//! ## Features
+//! - Scrolling line plot, newest reading on the right
+//! - Vertical scale that adapts to the spread of the readings
+//! - Gaps wherever no pitch was detected
+
+use iced::widget::canvas::{self, Geometry, Path, Stroke};
+use iced::widget::container;
+use iced::{mouse, Color, Element, Point, Rectangle, Renderer, Theme};
+use std::collections::VecDeque;
+
+/// Smallest half-range of the vertical scale in cents.
+const MIN_RANGE_CENTS: f32 = 5.0;
+
+/// Largest half-range of the vertical scale in cents (same as the cent meter).
+const MAX_RANGE_CENTS: f32 = 50.0;
+
+/// Pitch history widget plotting cent deviation over time.
+pub struct PitchHistory {
+    /// Recent readings, oldest first (None where no pitch was detected)
+    history: VecDeque<Option<f32>>,
+    /// Number of readings the full width represents
+    capacity: usize,
+}
+
+impl PitchHistory {
+    /// Creates a new pitch history widget.
+    ///
+    /// # Arguments
+    /// * `history` - Recent cent deviations, oldest first
+    /// * `capacity` - Number of readings the full width of the graph represents
+    pub fn new(history: VecDeque<Option<f32>>, capacity: usize) -> Self {
+        Self { history, capacity }
+    }
+
+    /// Creates the view element for the pitch history graph.
+    pub fn view(self) -> Element<'static, super::super::Message> {
+        container(
+            canvas::Canvas::new(self)
+                .width(iced::Length::Fill)
+                .height(iced::Length::Fill),
+        )
+        .into()
+    }
+}
+
+impl<Message> canvas::Program<Message> for PitchHistory {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+
+        if !bounds.width.is_finite() || !bounds.height.is_finite() || self.capacity < 2 {
+            return vec![frame.into_geometry()];
+        }
+
+        // Scale to the largest reading so small drifts are still visible
+        let range = self.history
+            .iter()
+            .flatten()
+            .fold(MIN_RANGE_CENTS, |max, &cents| max.max(cents.abs()))
+            .min(MAX_RANGE_CENTS);
+        let center_y = bounds.height / 2.0;
+        let to_y = |cents: f32| center_y - cents.clamp(-range, range) / range * center_y;
+
+        // Zero line
+        let zero_line = Path::line(Point::new(0.0, center_y), Point::new(bounds.width, center_y));
+        frame.stroke(
+            &zero_line,
+            Stroke::default()
+                .with_width(1.0)
+                .with_color(Color::from_rgba8(0xFF, 0xFF, 0xFF, 0.3)),
+        );
+
+        // Right-align the history so the newest reading is always at the right edge
+        let step = bounds.width / (self.capacity - 1) as f32;
+        let offset = self.capacity.saturating_sub(self.history.len());
+
+        let trace = Path::new(|builder| {
+            let mut drawing = false;
+            for (i, reading) in self.history.iter().enumerate() {
+                match reading {
+                    Some(cents) => {
+                        let point = Point::new((offset + i) as f32 * step, to_y(*cents));
+                        if drawing {
+                            builder.line_to(point);
+                        } else {
+                            builder.move_to(point);
+                            drawing = true;
+                        }
+                    }
+                    None => drawing = false, // Leave a gap where no pitch was detected
+                }
+            }
+        });
+        frame.stroke(
+            &trace,
+            Stroke::default()
+                .with_width(2.0)
+                .with_color(Color::from_rgb8(0x34, 0x98, 0xDB)),
+        );
+
+        // Scale label
+        frame.fill_text(canvas::Text {
+            content: format!("±{:.0}¢", range),
+            position: Point::new(4.0, 2.0),
+            color: Color::from_rgba8(0xFF, 0xFF, 0xFF, 0.6),
+            size: iced::Pixels(12.0),
+            ..canvas::Text::default()
+        });
+
+        vec![frame.into_geometry()]
+    }
+}
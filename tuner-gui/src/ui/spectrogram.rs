@@ -6,17 +6,113 @@
 //! 
 //! ## Features
 //! - Real-time frequency spectrum display
-//! - Logarithmic magnitude scaling
+//! - Logarithmic (dBFS) magnitude scaling
+//! - Adjustable noise floor gate with reference line
 //! - Smooth bar chart visualization
+//! - Optional peak-hold line showing the decaying maximum of each bin
+//! - Waterfall (time-frequency) view of recent frames
+//! - Zoom into a frequency range, e.g. around the partials of one note
 //! - Optimized for piano frequency range
 
-use iced::widget::canvas::{self, Geometry, Path};
+use iced::widget::canvas::{self, Geometry, Path, Stroke};
 use iced::widget::container;
 use iced::{mouse, Color, Element, Point, Rectangle, Renderer, Size, Theme};
+use std::collections::VecDeque;
+use std::ops::Range;
+use super::palette::Palette;
+use tuner_core::fft;
 
 /// Small epsilon value to prevent log(0) errors in magnitude calculations.
 const EPSILON: f32 = 1e-12;
 
+/// Lowest level shown at the bottom of the spectrogram, in dBFS.
+const DISPLAY_FLOOR_DB: f32 = -120.0;
+
+/// Narrowest waterfall column in pixels; neighbouring bins are merged to respect it.
+const WATERFALL_MIN_COLUMN_WIDTH: f32 = 2.0;
+
+/// Frequency span the spectrogram and waterfall show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum SpectrumRange {
+    /// DC to Nyquist
+    #[default]
+    Full,
+    /// DC to 5 kHz
+    Below5k,
+    /// DC to 2 kHz
+    Below2k,
+    /// DC to 1 kHz
+    Below1k,
+    /// The manual key's partials; the full range in auto mode
+    AroundNote,
+}
+
+impl SpectrumRange {
+    /// Returns the next range, for cycling through them in the panel header.
+    pub fn next(self) -> Self {
+        match self {
+            SpectrumRange::Full => SpectrumRange::Below5k,
+            SpectrumRange::Below5k => SpectrumRange::Below2k,
+            SpectrumRange::Below2k => SpectrumRange::Below1k,
+            SpectrumRange::Below1k => SpectrumRange::AroundNote,
+            SpectrumRange::AroundNote => SpectrumRange::Full,
+        }
+    }
+
+    /// Returns the name shown on the panel header button.
+    pub fn label(self) -> &'static str {
+        match self {
+            SpectrumRange::Full => "Full",
+            SpectrumRange::Below5k => "0-5 kHz",
+            SpectrumRange::Below2k => "0-2 kHz",
+            SpectrumRange::Below1k => "0-1 kHz",
+            SpectrumRange::AroundNote => "Note",
+        }
+    }
+
+    /// Returns the frequencies shown, in Hz.
+    ///
+    /// # Arguments
+    /// * `note_band` - Band around the manual key's partials, `None` in auto mode
+    ///
+    /// # Returns
+    /// * `Some((min, max))` - Only this range is shown
+    /// * `None` - The whole spectrum is shown
+    pub fn frequencies(self, note_band: Option<(f32, f32)>) -> Option<(f32, f32)> {
+        match self {
+            SpectrumRange::Full => None,
+            SpectrumRange::Below5k => Some((0.0, 5000.0)),
+            SpectrumRange::Below2k => Some((0.0, 2000.0)),
+            SpectrumRange::Below1k => Some((0.0, 1000.0)),
+            SpectrumRange::AroundNote => note_band,
+        }
+    }
+}
+
+/// Finds the bins of a `bins`-long spectrum, computed over `fft_size` samples,
+/// that fall within `range`.
+///
+/// The range is widened to whole bins, so a peak right at either end stays
+/// visible. Without a range, or before the sample rate is known, every bin is
+/// returned.
+fn visible_bins(bins: usize, sample_rate: u32, fft_size: usize, range: Option<(f32, f32)>) -> Range<usize> {
+    let Some((min, max)) = range.filter(|_| sample_rate > 0 && fft_size > 0) else {
+        return 0..bins;
+    };
+    let start = (fft::frequency_to_bin(min, sample_rate, fft_size).floor().max(0.0) as usize).min(bins);
+    let end = (fft::frequency_to_bin(max, sample_rate, fft_size).ceil() as usize + 1).clamp(start, bins);
+    start..end
+}
+
+/// Converts a bin magnitude to dBFS.
+///
+/// A full-scale sine through a Hann window peaks at N/4 in the spectrum,
+/// which is used as the 0 dBFS reference.
+fn magnitude_to_dbfs(magnitude: f32, fft_size: usize) -> f32 {
+    let full_scale = fft_size as f32 / 4.0;
+    20.0 * ((magnitude + EPSILON) / full_scale).log10()
+}
+
 /// Spectrogram widget for displaying frequency spectrum data.
 /// 
 /// This widget visualizes the frequency content of audio signals
@@ -25,6 +121,18 @@ const EPSILON: f32 = 1e-12;
 pub struct Spectrogram {
     /// Magnitude spectrum data from FFT analysis
     data: Vec<f32>,
+    /// Held per-bin maxima drawn as a line over the bars, if enabled
+    peak_hold: Option<Vec<f32>>,
+    /// Noise floor gate in dBFS; bins below it are drawn as zero
+    noise_floor_db: f32,
+    /// Colors to draw with
+    palette: Palette,
+    /// Sample rate the spectrum was computed at, in Hz (0 if unknown)
+    sample_rate: u32,
+    /// Number of samples the spectrum was computed over
+    fft_size: usize,
+    /// Frequencies shown in Hz, or `None` for the whole spectrum
+    range: Option<(f32, f32)>,
 }
 
 impl Spectrogram {
@@ -32,8 +140,23 @@ impl Spectrogram {
     /// 
     /// # Arguments
     /// * `data` - Magnitude spectrum data from FFT analysis
-    pub fn new(data: Vec<f32>) -> Self {
-        Self { data }
+    /// * `peak_hold` - Held per-bin maxima to draw as a line, or `None` to hide it
+    /// * `noise_floor_db` - Gate threshold in dBFS below which bins are not drawn
+    /// * `palette` - Colors to draw with
+    /// * `sample_rate` - Sample rate the spectrum was computed at, in Hz (0 if unknown)
+    /// * `fft_size` - Number of samples the spectrum was computed over
+    /// * `range` - Lowest and highest frequency to show in Hz, spread over the
+    ///   full width, or `None` for DC to Nyquist
+    pub fn new(
+        data: Vec<f32>,
+        peak_hold: Option<Vec<f32>>,
+        noise_floor_db: f32,
+        palette: Palette,
+        sample_rate: u32,
+        fft_size: usize,
+        range: Option<(f32, f32)>,
+    ) -> Self {
+        Self { data, peak_hold, noise_floor_db, palette, sample_rate, fft_size, range }
     }
 
     /// Creates the view element for the spectrogram.
@@ -67,31 +190,210 @@ impl<Message> canvas::Program<Message> for Spectrogram {
             return vec![frame.into_geometry()];
         }
 
-        let max_magnitude = self.data.iter().fold(0.0f32, |max, &val| val.max(max));
+        let to_db = |magnitude: f32| magnitude_to_dbfs(magnitude, self.fft_size);
+
+        // Only the bins in range are drawn, and they alone set the scale
+        let shown = visible_bins(self.data.len(), self.sample_rate, self.fft_size, self.range);
+        let data = &self.data[shown.clone()];
+        if data.is_empty() {
+            return vec![frame.into_geometry()];
+        }
+
+        // Held peaks can sit above the live bars, so they count towards the scale
+        let peaks = self.peak_hold
+            .as_deref()
+            .filter(|peaks| peaks.len() == self.data.len())
+            .map(|peaks| &peaks[shown]);
+        let max_magnitude = data.iter()
+            .chain(peaks.unwrap_or_default())
+            .fold(0.0f32, |max, &val| val.max(max));
         if max_magnitude <= 0.0 {
             return vec![frame.into_geometry()];
         }
 
-        // FIX: Add EPSILON to prevent log(-inf)
-        let log_max = (max_magnitude + EPSILON).ln();
+        // The top of the display follows the loudest bin, but never drops below
+        // the gate so the reference line always stays visible.
+        let top_db = to_db(max_magnitude).max(self.noise_floor_db + 10.0);
+        let db_range = top_db - DISPLAY_FLOOR_DB;
+        let db_to_height = |db: f32| ((db - DISPLAY_FLOOR_DB) / db_range * bounds.height).max(0.0);
 
-        let bar_width = (bounds.width / self.data.len() as f32).max(1.0);
+        let bar_width = (bounds.width / data.len() as f32).max(1.0);
 
-        for (i, &magnitude) in self.data.iter().enumerate() {
-            // FIX: Add EPSILON here as well
-            let log_magnitude = (magnitude + EPSILON).ln();
-            let height = (log_magnitude / log_max * bounds.height).max(0.0);
+        for (i, &magnitude) in data.iter().enumerate() {
+            let magnitude_db = to_db(magnitude);
+            if magnitude_db < self.noise_floor_db {
+                continue; // Gated: treat as silence
+            }
+            let height = db_to_height(magnitude_db);
 
-            // The existing check is good, it will catch any remaining NaN/inf issues.
             if height.is_finite() && height > 0.0 {
                 let bar = Path::rectangle(
                     Point::new(i as f32 * bar_width, bounds.height - height),
                     Size::new(bar_width, height),
                 );
-                frame.fill(&bar, Color::from_rgb8(0x34, 0x98, 0xDB));
+                frame.fill(&bar, self.palette.spectrum);
             }
         }
 
+        // Draw the held peaks as a faint line through the top of each bin
+        if let Some(peaks) = peaks {
+            let peak_line = Path::new(|builder| {
+                for (i, &magnitude) in peaks.iter().enumerate() {
+                    let magnitude_db = to_db(magnitude);
+                    let height = if magnitude_db < self.noise_floor_db { 0.0 } else { db_to_height(magnitude_db) };
+                    let point = Point::new((i as f32 + 0.5) * bar_width, bounds.height - height);
+                    if i == 0 {
+                        builder.move_to(point);
+                    } else {
+                        builder.line_to(point);
+                    }
+                }
+            });
+            frame.stroke(
+                &peak_line,
+                Stroke::default()
+                    .with_width(1.0)
+                    .with_color(Color::from_rgba8(0xFF, 0xFF, 0xFF, 0.7)),
+            );
+        }
+
+        // Draw the noise floor gate as a horizontal reference line
+        let gate_y = bounds.height - db_to_height(self.noise_floor_db);
+        if gate_y.is_finite() {
+            let gate_line = Path::line(Point::new(0.0, gate_y), Point::new(bounds.width, gate_y));
+            frame.stroke(
+                &gate_line,
+                Stroke::default()
+                    .with_width(1.0)
+                    .with_color(Color::from_rgba8(0xFF, 0xFF, 0xFF, 0.4)),
+            );
+        }
+
         vec![frame.into_geometry()]
     }
-}
\ No newline at end of file
+}
+
+/// Waterfall widget showing how the spectrum evolves over time.
+///
+/// Each row is one analysis frame, with the newest frame at the top and
+/// older frames scrolling down. Rows are one hop apart, so with overlapping
+/// frames the rows get thinner and the height still spans the same time. Frequency runs horizontally on the same axis
+/// as the `Spectrogram` bars and magnitude is shown as color.
+pub struct Waterfall {
+    /// Recent magnitude spectra, oldest first
+    history: VecDeque<Vec<f32>>,
+    /// Number of rows the view is divided into (the history capacity)
+    rows: usize,
+    /// Noise floor gate in dBFS; bins below it are left dark
+    noise_floor_db: f32,
+    /// Sample rate the spectra were computed at, in Hz (0 if unknown)
+    sample_rate: u32,
+    /// Number of samples the newest spectrum was computed over
+    fft_size: usize,
+    /// Frequencies shown in Hz, or `None` for the whole spectrum
+    range: Option<(f32, f32)>,
+}
+
+impl Waterfall {
+    /// Creates a new waterfall widget.
+    ///
+    /// # Arguments
+    /// * `history` - Recent magnitude spectra, oldest first
+    /// * `rows` - Number of frames the full height represents (its time span divided by the hop)
+    /// * `noise_floor_db` - Gate threshold in dBFS below which bins are not drawn
+    /// * `sample_rate` - Sample rate the spectra were computed at, in Hz (0 if unknown)
+    /// * `fft_size` - Number of samples the newest spectrum was computed over
+    /// * `range` - Frequencies to show in Hz, as for [`Spectrogram::new`]
+    pub fn new(
+        history: VecDeque<Vec<f32>>,
+        rows: usize,
+        noise_floor_db: f32,
+        sample_rate: u32,
+        fft_size: usize,
+        range: Option<(f32, f32)>,
+    ) -> Self {
+        Self { history, rows, noise_floor_db, sample_rate, fft_size, range }
+    }
+
+    /// Creates the view element for the waterfall.
+    pub fn view(self) -> Element<'static, super::super::Message> {
+        container(
+            canvas::Canvas::new(self)
+                .width(iced::Length::Fill)
+                .height(iced::Length::Fill),
+        )
+        .into()
+    }
+}
+
+/// Maps a normalized level (0.0-1.0) onto a dark blue to yellow heat scale.
+fn heat_color(level: f32) -> Color {
+    let level = level.clamp(0.0, 1.0);
+    if level < 0.5 {
+        let t = level * 2.0;
+        Color::from_rgb(0.05, 0.1 + 0.5 * t, 0.3 + 0.5 * t)
+    } else {
+        let t = (level - 0.5) * 2.0;
+        Color::from_rgb(0.05 + 0.95 * t, 0.6 + 0.35 * t, 0.8 - 0.6 * t)
+    }
+}
+
+impl<Message> canvas::Program<Message> for Waterfall {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+
+        let all_bins = self.history.back().map_or(0, |row| row.len());
+        let shown = visible_bins(all_bins, self.sample_rate, self.fft_size, self.range);
+        let bins = shown.len();
+        if !bounds.width.is_finite() || !bounds.height.is_finite() || bins == 0 || self.rows == 0 {
+            return vec![frame.into_geometry()];
+        }
+
+        frame.fill_rectangle(Point::ORIGIN, bounds.size(), Color::from_rgb(0.02, 0.02, 0.05));
+
+        // Merge neighbouring bins so each column is at least a couple of pixels wide
+        let columns = bins.min((bounds.width / WATERFALL_MIN_COLUMN_WIDTH) as usize).max(1);
+        let bins_per_column = bins.div_ceil(columns);
+        let column_width = bounds.width / columns as f32;
+        let row_height = bounds.height / self.rows as f32;
+
+        // Rows from before an FFT size change don't line up with the newest one
+        let rows_in_range = || {
+            self.history
+                .iter()
+                .filter(move |spectrum| spectrum.len() == all_bins)
+                .map(|spectrum| &spectrum[shown.clone()])
+        };
+        let loudest = rows_in_range().flatten().fold(0.0f32, |max, &val| val.max(max));
+        let top_db = magnitude_to_dbfs(loudest, self.fft_size).max(self.noise_floor_db + 10.0);
+        let db_range = top_db - self.noise_floor_db;
+
+        // Newest frame on the top row
+        for (row_index, spectrum) in rows_in_range().rev().take(self.rows).enumerate() {
+            let y = row_index as f32 * row_height;
+            for (column, chunk) in spectrum.chunks(bins_per_column).enumerate() {
+                let magnitude = chunk.iter().fold(0.0f32, |max, &val| val.max(max));
+                let magnitude_db = magnitude_to_dbfs(magnitude, self.fft_size);
+                if magnitude_db < self.noise_floor_db {
+                    continue; // Gated: leave the background showing
+                }
+                frame.fill_rectangle(
+                    Point::new(column as f32 * column_width, y),
+                    Size::new(column_width, row_height),
+                    heat_color((magnitude_db - self.noise_floor_db) / db_range),
+                );
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+}